@@ -0,0 +1,15 @@
+-- Your SQL goes here
+
+CREATE TABLE languages (
+    pk uuid NOT NULL,
+    name VARCHAR NOT NULL,
+    version VARCHAR NOT NULL,
+    compile_command VARCHAR,
+    run_command VARCHAR NOT NULL,
+    file_extension VARCHAR NOT NULL,
+    is_enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    PRIMARY KEY (pk)
+);
+
+INSERT INTO languages (pk, name, version, compile_command, run_command, file_extension)
+VALUES ('aea02f71-ab0d-470e-9d0d-3577ec870e29', 'C++17', 'g++ 11', 'g++ -O2 -std=c++17 -o {binary} {source}', '{binary}', 'cpp');
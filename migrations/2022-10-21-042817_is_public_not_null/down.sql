@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+
+ALTER TABLE tasks
+ALTER COLUMN is_public DROP NOT NULL;
+
+ALTER TABLE contests
+ALTER COLUMN is_public DROP NOT NULL;
@@ -0,0 +1,9 @@
+-- Your SQL goes here
+
+UPDATE tasks SET is_public = FALSE WHERE is_public IS NULL;
+ALTER TABLE tasks
+ALTER COLUMN is_public SET NOT NULL;
+
+UPDATE contests SET is_public = FALSE WHERE is_public IS NULL;
+ALTER TABLE contests
+ALTER COLUMN is_public SET NOT NULL;
@@ -0,0 +1,21 @@
+-- Your SQL goes here
+
+CREATE TABLE similarities (
+    pk uuid DEFAULT uuid_generate_v4(),
+    task_pk uuid NOT NULL,
+    contest_pk uuid,
+    submission_a uuid NOT NULL,
+    submission_b uuid NOT NULL,
+    score DOUBLE PRECISION NOT NULL,
+    computed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (pk)
+);
+
+-- a pair is kept once for the whole task and once for each contest it was checked in
+CREATE UNIQUE INDEX similarities_task_pair_key
+ON similarities (submission_a, submission_b)
+WHERE contest_pk IS NULL;
+
+CREATE UNIQUE INDEX similarities_contest_pair_key
+ON similarities (contest_pk, submission_a, submission_b)
+WHERE contest_pk IS NOT NULL;
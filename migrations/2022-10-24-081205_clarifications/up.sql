@@ -0,0 +1,16 @@
+-- Your SQL goes here
+
+CREATE TABLE clarifications (
+    pk uuid DEFAULT uuid_generate_v4(),
+    contest_pk uuid NOT NULL,
+    task_pk uuid,
+    user_pk uuid NOT NULL,
+    question TEXT,
+    answer TEXT,
+    answered_by uuid,
+    is_public BOOLEAN NOT NULL DEFAULT FALSE,
+    asked_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    answered_at TIMESTAMPTZ,
+    PRIMARY KEY (pk),
+    FOREIGN KEY (contest_pk) REFERENCES contests (pk) ON DELETE CASCADE
+);
@@ -0,0 +1,11 @@
+-- Your SQL goes here
+
+CREATE TABLE virtual_participations (
+    pk uuid DEFAULT uuid_generate_v4(),
+    user_pk uuid NOT NULL,
+    contest_pk uuid NOT NULL,
+    start_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (pk),
+    UNIQUE (user_pk, contest_pk),
+    FOREIGN KEY (contest_pk) REFERENCES contests (pk) ON DELETE CASCADE
+);
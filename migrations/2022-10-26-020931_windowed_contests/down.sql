@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+
+ALTER TABLE contest_accessible_users
+DROP COLUMN started_at;
+
+ALTER TABLE contests
+DROP COLUMN participant_duration;
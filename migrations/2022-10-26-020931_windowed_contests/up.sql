@@ -0,0 +1,7 @@
+-- Your SQL goes here
+
+ALTER TABLE contests
+ADD COLUMN participant_duration INTEGER;
+
+ALTER TABLE contest_accessible_users
+ADD COLUMN started_at TIMESTAMPTZ;
@@ -0,0 +1,7 @@
+-- Your SQL goes here
+
+ALTER TABLE users
+ADD COLUMN email_verified BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- accounts made before verification existed are trusted
+UPDATE users SET email_verified = TRUE;
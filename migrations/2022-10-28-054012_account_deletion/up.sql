@@ -0,0 +1,4 @@
+-- Your SQL goes here
+
+ALTER TABLE users
+ADD COLUMN deleted_at TIMESTAMPTZ;
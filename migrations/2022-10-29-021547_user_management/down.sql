@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+
+DROP TABLE audit_logs;
+
+ALTER TABLE users
+DROP COLUMN is_banned;
@@ -0,0 +1,13 @@
+-- Your SQL goes here
+
+ALTER TABLE users
+ADD COLUMN is_banned BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE audit_logs (
+    pk UUID NOT NULL PRIMARY KEY,
+    admin_pk UUID NOT NULL,
+    action VARCHAR NOT NULL,
+    target_pk UUID,
+    detail TEXT,
+    created_at TIMESTAMPTZ NOT NULL
+);
@@ -0,0 +1,10 @@
+-- Your SQL goes here
+
+CREATE TABLE external_identities (
+    pk UUID NOT NULL PRIMARY KEY,
+    user_pk UUID NOT NULL,
+    provider VARCHAR NOT NULL,
+    subject VARCHAR NOT NULL,
+    linked_at TIMESTAMPTZ NOT NULL,
+    UNIQUE (provider, subject)
+);
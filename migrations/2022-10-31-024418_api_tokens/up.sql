@@ -0,0 +1,12 @@
+-- Your SQL goes here
+
+CREATE TABLE api_tokens (
+    pk UUID NOT NULL PRIMARY KEY,
+    user_pk UUID NOT NULL,
+    name VARCHAR NOT NULL,
+    token_hash VARCHAR NOT NULL UNIQUE,
+    scopes INTEGER NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    expires_at TIMESTAMPTZ,
+    last_used_at TIMESTAMPTZ
+);
@@ -0,0 +1,13 @@
+-- Your SQL goes here
+
+ALTER TABLE users
+ADD COLUMN totp_secret VARCHAR,
+ADD COLUMN totp_enabled BOOLEAN NOT NULL DEFAULT FALSE,
+ADD COLUMN totp_last_step BIGINT;
+
+CREATE TABLE recovery_codes (
+    pk UUID NOT NULL PRIMARY KEY,
+    user_pk UUID NOT NULL,
+    code_hash VARCHAR NOT NULL,
+    used_at TIMESTAMPTZ
+);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+
+ALTER TABLE audit_logs
+DROP COLUMN impersonator_pk;
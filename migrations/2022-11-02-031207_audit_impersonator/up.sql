@@ -0,0 +1,4 @@
+-- Your SQL goes here
+
+ALTER TABLE audit_logs
+ADD COLUMN impersonator_pk UUID;
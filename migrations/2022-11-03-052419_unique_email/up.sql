@@ -0,0 +1,4 @@
+-- Your SQL goes here
+
+-- deleted accounts and accounts provisioned without an address keep an empty email
+CREATE UNIQUE INDEX users_email_lower_key ON users (lower(email)) WHERE email <> '';
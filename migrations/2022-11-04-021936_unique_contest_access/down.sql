@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+
+ALTER TABLE contest_accessible_users
+DROP CONSTRAINT contest_accessible_users_contest_user_key;
@@ -0,0 +1,16 @@
+-- Your SQL goes here
+
+-- keeps the earliest start of each user
+DELETE FROM contest_accessible_users a
+USING contest_accessible_users b
+WHERE a.contest_pk = b.contest_pk
+  AND a.user_pk = b.user_pk
+  AND a.pk <> b.pk
+  AND (
+    (a.started_at IS NULL AND b.started_at IS NOT NULL)
+    OR a.started_at > b.started_at
+    OR (a.started_at IS NOT DISTINCT FROM b.started_at AND a.pk > b.pk)
+  );
+
+ALTER TABLE contest_accessible_users
+ADD CONSTRAINT contest_accessible_users_contest_user_key UNIQUE (contest_pk, user_pk);
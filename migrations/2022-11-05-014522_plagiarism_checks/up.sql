@@ -0,0 +1,7 @@
+-- Your SQL goes here
+
+CREATE TABLE plagiarism_checks (
+    contest_pk uuid NOT NULL,
+    checked_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (contest_pk)
+);
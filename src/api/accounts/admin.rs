@@ -0,0 +1,281 @@
+use actix_identity::Identity;
+use actix_session::Session;
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use chrono::prelude::*;
+use std::convert::TryFrom;
+use uuid::Uuid;
+
+use super::password::{bump_session_epoch, send_reset_mail, start_session};
+use super::*;
+use crate::api::judge::is_admin;
+use crate::db::accounts::AdminChange;
+use crate::db::models::AuditLog;
+
+pub const MAX_PAGE_SIZE: i64 = 100;
+
+// the admin who is impersonating the logged in user
+pub const IMPERSONATOR: &'static str = "impersonator";
+
+pub fn require_admin(viewer: Option<Uuid>) -> Result<Uuid, AccountError> {
+    let viewer = viewer.ok_or(AccountError::NotLoggedIn)?;
+    if is_admin(viewer) {
+        Ok(viewer)
+    } else {
+        Err(AccountError::PermissionDenied)
+    }
+}
+
+pub fn audit_entry(
+    admin_pk: Uuid,
+    impersonator: Option<Uuid>,
+    action: &str,
+    target_pk: Uuid,
+    detail: Option<String>,
+) -> AuditLog {
+    AuditLog {
+        pk: Uuid::new_v4(),
+        admin_pk,
+        action: action.to_string(),
+        target_pk: Some(target_pk),
+        detail,
+        created_at: Utc::now(),
+        impersonator_pk: impersonator,
+    }
+}
+
+// Every action of admins on accounts is written to the audit log in the same transaction
+// as its changes. `impersonator` is the admin behind the session if it is impersonated.
+fn audited(
+    admin_pk: Uuid,
+    impersonator: Option<Uuid>,
+    action: &str,
+    target_pk: Uuid,
+    changes: Vec<AdminChange>,
+    detail: Option<String>,
+) -> Result<(), AccountError> {
+    info!(
+        "Admin {} (impersonated by {:?}) did {} on {}: {}",
+        admin_pk,
+        impersonator,
+        action,
+        target_pk,
+        detail.clone().unwrap_or_default()
+    );
+    let log = audit_entry(admin_pk, impersonator, action, target_pk, detail);
+    accounts::apply_admin_changes(target_pk, changes, log).map_err(|_| AccountError::DatabaseError)
+}
+
+fn target_user(user_pk: Uuid) -> Result<User, AccountError> {
+    match accounts::find_user(user_pk) {
+        Ok(user) if user.deleted_at.is_none() => Ok(user),
+        _ => Err(AccountError::UserNotExists),
+    }
+}
+
+pub fn list_users(
+    viewer: Option<Uuid>,
+    keyword: Option<String>,
+    offset: i64,
+    limit: i64,
+) -> Result<(i64, Vec<User>), AccountError> {
+    require_admin(viewer)?;
+    accounts::search_users(
+        keyword.filter(|x| !x.is_empty()),
+        offset.max(0),
+        limit.clamp(0, MAX_PAGE_SIZE),
+    )
+    .map_err(|_| AccountError::DatabaseError)
+}
+
+pub fn list_audit_logs(
+    viewer: Option<Uuid>,
+    target_pk: Option<Uuid>,
+    offset: i64,
+    limit: i64,
+) -> Result<Vec<AuditLog>, AccountError> {
+    require_admin(viewer)?;
+    accounts::audit_logs(target_pk, offset.max(0), limit.clamp(0, MAX_PAGE_SIZE))
+        .map_err(|_| AccountError::DatabaseError)
+}
+
+// Banned users are logged out and cannot log in until they are unbanned.
+pub fn set_banned(
+    viewer: Option<Uuid>,
+    impersonator: Option<Uuid>,
+    user_pk: Uuid,
+    is_banned: bool,
+    reason: Option<String>,
+) -> Result<(), AccountError> {
+    let admin_pk = require_admin(viewer)?;
+    if admin_pk == user_pk {
+        return Err(AccountError::PermissionDenied);
+    }
+    target_user(user_pk)?;
+    audited(
+        admin_pk,
+        impersonator,
+        if is_banned { "ban" } else { "unban" },
+        user_pk,
+        vec![AdminChange::Banned(is_banned)],
+        reason,
+    )?;
+    if is_banned {
+        bump_session_epoch(user_pk)?;
+    }
+    Ok(())
+}
+
+// Logs out every session of the user and revokes its API tokens.
+pub fn force_logout(
+    viewer: Option<Uuid>,
+    impersonator: Option<Uuid>,
+    user_pk: Uuid,
+) -> Result<(), AccountError> {
+    let admin_pk = require_admin(viewer)?;
+    target_user(user_pk)?;
+    audited(
+        admin_pk,
+        impersonator,
+        "force_logout",
+        user_pk,
+        vec![AdminChange::RevokeApiTokens],
+        None,
+    )?;
+    bump_session_epoch(user_pk)
+}
+
+// The current password stops working and the owner gets a mail to choose a new one.
+pub async fn reset_user_password(
+    viewer: Option<Uuid>,
+    impersonator: Option<Uuid>,
+    user_pk: Uuid,
+) -> Result<(), AccountError> {
+    let admin_pk = require_admin(viewer)?;
+    let user = target_user(user_pk)?;
+    audited(
+        admin_pk,
+        impersonator,
+        "reset_password",
+        user_pk,
+        vec![
+            AdminChange::Password(String::new()),
+            AdminChange::RevokeApiTokens,
+        ],
+        None,
+    )?;
+    bump_session_epoch(user_pk)?;
+    send_reset_mail(&user).await
+}
+
+pub fn change_permission(
+    viewer: Option<Uuid>,
+    impersonator: Option<Uuid>,
+    user_pk: Uuid,
+    permission: AccountPerm,
+) -> Result<(), AccountError> {
+    let admin_pk = require_admin(viewer)?;
+    // admins cannot demote themselves, so that there is always one left
+    if admin_pk == user_pk {
+        return Err(AccountError::PermissionDenied);
+    }
+    let user = target_user(user_pk)?;
+    audited(
+        admin_pk,
+        impersonator,
+        "change_permission",
+        user_pk,
+        vec![AdminChange::Permission(permission as i32)],
+        Some(format!(
+            "{:?} -> {:?}",
+            AccountPerm::try_from(user.permission).unwrap_or(AccountPerm::User),
+            permission
+        )),
+    )
+}
+
+// Admins are never impersonated, so that impersonation cannot be chained to act with the
+// rights of another admin.
+pub fn check_impersonation(admin_pk: Uuid, target: &User) -> Result<(), AccountError> {
+    if admin_pk == target.pk || target.permission == AccountPerm::Admin as i32 {
+        Err(AccountError::PermissionDenied)
+    } else {
+        Ok(())
+    }
+}
+
+// Logs in as another user for debugging. The admin is remembered in the session, so that
+// they can switch back with `stop_impersonating`.
+pub fn impersonate_user(
+    request: &HttpRequest,
+    session: &Session,
+    viewer: Option<Uuid>,
+    user_pk: Uuid,
+) -> Result<(), AccountError> {
+    let admin_pk = require_admin(viewer)?;
+    let user = target_user(user_pk)?;
+    check_impersonation(admin_pk, &user)?;
+    audited(
+        admin_pk,
+        None,
+        "impersonate",
+        user_pk,
+        vec![],
+        Some(user.id),
+    )?;
+    session
+        .insert(IMPERSONATOR, admin_pk)
+        .map_err(|_| AccountError::DatabaseError)?;
+    start_session(request, session, user_pk);
+    Ok(())
+}
+
+pub fn stop_impersonation(
+    request: &HttpRequest,
+    session: &Session,
+    viewer: Option<Uuid>,
+) -> Result<(), AccountError> {
+    let user_pk = viewer.ok_or(AccountError::NotLoggedIn)?;
+    let admin_pk = session
+        .remove_as::<Uuid>(IMPERSONATOR)
+        .and_then(|x| x.ok())
+        .ok_or(AccountError::NotImpersonating)?;
+    audited(admin_pk, None, "stop_impersonating", user_pk, vec![], None)?;
+    start_session(request, session, admin_pk);
+    Ok(())
+}
+
+fn respond(result: Result<(), AccountError>) -> HttpResponse {
+    let (status, body) = match result {
+        Ok(_) => (true, AccountError::None),
+        Err(err) => (false, err),
+    };
+    HttpResponse::Ok()
+        .content_type("application/json")
+        .json(ResponseBlock { status, body })
+}
+
+#[post("/impersonate/stop")]
+pub async fn stop_impersonating(
+    user: Option<Identity>,
+    request: HttpRequest,
+    session: Session,
+) -> HttpResponse {
+    let viewer = user.map(|user| user.id().unwrap().parse::<Uuid>().unwrap());
+    respond(stop_impersonation(&request, &session, viewer))
+}
+
+#[post("/impersonate/{user_pk}")]
+pub async fn impersonate(
+    user: Option<Identity>,
+    request: HttpRequest,
+    session: Session,
+    path: web::Path<Uuid>,
+) -> HttpResponse {
+    let viewer = user.map(|user| user.id().unwrap().parse::<Uuid>().unwrap());
+    respond(impersonate_user(
+        &request,
+        &session,
+        viewer,
+        path.into_inner(),
+    ))
+}
@@ -0,0 +1,231 @@
+use actix_identity::IdentityExt;
+use actix_session::SessionExt;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{AUTHORIZATION, UPGRADE};
+use actix_web::http::Method;
+use async_graphql::parser::parse_query;
+use async_graphql::parser::types::{DocumentOperations, OperationType, Selection};
+use std::future::Future;
+use uuid::Uuid;
+
+use super::*;
+use crate::api::judge::is_admin;
+use crate::db::models::ApiToken;
+
+pub const TOKEN_PREFIX: &'static str = "pms_";
+pub const TOKEN_NAME_MAX_LENGTH: usize = 64;
+pub const MAX_TOKENS_PER_USER: usize = 20;
+
+// mutations allowed with the submit scope, anything else needs the admin scope
+pub const SUBMIT_MUTATIONS: [&'static str; 5] = [
+    "submit",
+    "submitOutputs",
+    "askClarification",
+    "startContest",
+    "startVirtual",
+];
+
+#[derive(Enum, Eq, PartialEq, Clone, Serialize, Deserialize, Debug, Copy)]
+#[repr(i32)]
+pub enum TokenScope {
+    Read = 0x01,
+    Submit = 0x02,
+    Admin = 0x04,
+}
+
+impl TokenScope {
+    pub fn all() -> [Self; 3] {
+        [Self::Read, Self::Submit, Self::Admin]
+    }
+
+    pub fn from_bits(bits: i32) -> Vec<Self> {
+        Self::all()
+            .into_iter()
+            .filter(|scope| bits & *scope as i32 != 0)
+            .collect()
+    }
+
+    pub fn to_bits(scopes: &[Self]) -> i32 {
+        scopes.iter().fold(0, |bits, scope| bits | *scope as i32)
+    }
+}
+
+// Attached to requests authenticated by a bearer token
+#[derive(Clone, Copy, Debug)]
+pub struct TokenAuth {
+    pub token_pk: Uuid,
+    pub user_pk: Uuid,
+    pub scopes: i32,
+}
+
+impl TokenAuth {
+    pub fn allows(&self, scope: TokenScope) -> bool {
+        self.scopes & scope as i32 != 0
+    }
+}
+
+tokio::task_local! {
+    static TOKEN_SCOPES: i32;
+}
+
+// Admin rights are dropped while serving a token without the admin scope.
+pub fn admin_scope_allowed() -> bool {
+    TOKEN_SCOPES
+        .try_with(|scopes| scopes & TokenScope::Admin as i32 != 0)
+        .unwrap_or(true)
+}
+
+pub fn hash_token(token: &str) -> String {
+    let mut hasher = Sha3_256::new();
+    hasher.update(token.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+// The scope a GraphQL request needs. Unparsable queries are rejected by the schema later.
+pub fn required_scope(query: &str) -> TokenScope {
+    let doc = match parse_query(query) {
+        Ok(doc) => doc,
+        Err(_) => return TokenScope::Read,
+    };
+    let operations = match &doc.operations {
+        DocumentOperations::Single(op) => vec![&op.node],
+        DocumentOperations::Multiple(ops) => ops.values().map(|op| &op.node).collect(),
+    };
+    let mut required = TokenScope::Read;
+    for op in operations {
+        if op.ty != OperationType::Mutation {
+            continue;
+        }
+        let submit_only = op
+            .selection_set
+            .node
+            .items
+            .iter()
+            .all(|item| match &item.node {
+                Selection::Field(field) => {
+                    SUBMIT_MUTATIONS.contains(&field.node.name.node.as_str())
+                }
+                _ => false,
+            });
+        if !submit_only {
+            return TokenScope::Admin;
+        }
+        required = TokenScope::Submit;
+    }
+    required
+}
+
+pub fn create_token(
+    user_pk: Uuid,
+    name: String,
+    scopes: Vec<TokenScope>,
+    expires_at: Option<DateTime<Utc>>,
+) -> Result<(ApiToken, String), AccountError> {
+    let name = name.trim().to_string();
+    if name.is_empty() || name.chars().count() > TOKEN_NAME_MAX_LENGTH {
+        return Err(AccountError::InvalidTokenName);
+    }
+    if scopes.is_empty() {
+        return Err(AccountError::NoScope);
+    }
+    if scopes.contains(&TokenScope::Admin) && !is_admin(user_pk) {
+        return Err(AccountError::PermissionDenied);
+    }
+    let count = accounts::api_tokens_of(user_pk)
+        .map_err(|_| AccountError::DatabaseError)?
+        .len();
+    if count >= MAX_TOKENS_PER_USER {
+        return Err(AccountError::TooManyTokens);
+    }
+    let token = format!(
+        "{}{}{}",
+        TOKEN_PREFIX,
+        Uuid::new_v4().as_simple(),
+        Uuid::new_v4().as_simple()
+    );
+    let item = accounts::new_api_token(ApiToken {
+        pk: Uuid::new_v4(),
+        user_pk,
+        name,
+        token_hash: hash_token(&token),
+        scopes: TokenScope::to_bits(&scopes),
+        created_at: Utc::now(),
+        expires_at,
+        last_used_at: None,
+    })
+    .map_err(|_| AccountError::DatabaseError)?;
+    Ok((item, token))
+}
+
+// by its owner or an admin
+pub fn revoke_token(viewer: Uuid, token_pk: Uuid) -> Result<(), AccountError> {
+    let token = accounts::find_api_token(token_pk).map_err(|_| AccountError::TokenNotFound)?;
+    if token.user_pk != viewer && !is_admin(viewer) {
+        return Err(AccountError::TokenNotFound);
+    }
+    accounts::delete_api_token(token_pk)
+        .map(|_| ())
+        .map_err(|_| AccountError::DatabaseError)
+}
+
+pub fn authenticate(token: &str) -> Option<TokenAuth> {
+    if !token.starts_with(TOKEN_PREFIX) {
+        return None;
+    }
+    let item = accounts::api_token_by_hash(hash_token(token)).ok()?;
+    if item.expires_at.map_or(false, |at| at <= Utc::now()) {
+        return None;
+    }
+    let user = accounts::find_user(item.user_pk).ok()?;
+    if user.deleted_at.is_some() || user.is_banned {
+        return None;
+    }
+    accounts::touch_api_token(item.pk).ok();
+    Some(TokenAuth {
+        token_pk: item.pk,
+        user_pk: item.user_pk,
+        scopes: item.scopes,
+    })
+}
+
+// Logs the request in by its `Authorization: Bearer` token unless it already has a
+// session. Out of `/api/gql`, tokens are accepted only for reading with GET, and never
+// for websockets whose subscriptions outlive the request.
+pub fn login_by_token(req: &ServiceRequest) -> Option<TokenAuth> {
+    if req.get_identity().is_ok() || req.headers().contains_key(UPGRADE) {
+        return None;
+    }
+    let token = req
+        .headers()
+        .get(AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")?
+        .trim();
+    let auth = authenticate(token)?;
+    let is_gql = req.path().ends_with("/gql") && *req.method() == Method::POST;
+    if !is_gql && (*req.method() != Method::GET || !auth.allows(TokenScope::Read)) {
+        return None;
+    }
+    Identity::login(&req.extensions(), auth.user_pk.to_string()).ok()?;
+    req.extensions_mut().insert(auth);
+    Some(auth)
+}
+
+// Token logins last for a single request, so their session is never stored.
+pub async fn with_token<F, B>(
+    auth: Option<TokenAuth>,
+    fut: F,
+) -> Result<ServiceResponse<B>, actix_web::Error>
+where
+    F: Future<Output = Result<ServiceResponse<B>, actix_web::Error>>,
+{
+    match auth {
+        Some(auth) => {
+            let res = TOKEN_SCOPES.scope(auth.scopes, fut).await?;
+            res.request().get_session().purge();
+            Ok(res)
+        }
+        None => fut.await,
+    }
+}
@@ -13,8 +13,60 @@ pub enum AccountError {
     DatabaseError,
     #[error("You're password is not matched")]
     PassNotMatched,
-    #[error("Requested user id already exists")]
+    #[error("Requested user id does not exist")]
     UserNotExists,
+    #[error("Requested user id already exists")]
+    UserAlreadyExists,
+    #[error("Requested email is already in use")]
+    EmailAlreadyExists,
+    #[error("User id must be 3 to 20 letters, digits, '_', '-' or '.'")]
+    InvalidId,
+    #[error("Password must be 8 to 128 characters with letters and digits")]
+    WeakPassword,
+    #[error("Email address is not valid")]
+    InvalidEmail,
+    #[error("Your email address is not verified yet")]
+    EmailNotVerified,
+    #[error("Requested token is invalid or expired")]
+    InvalidToken,
+    #[error("Mail cannot be sent")]
+    MailError,
+    #[error("Too many requests, try again later")]
+    TooManyRequests,
+    #[error("Requested language does not exist")]
+    LanguageNotFound,
+    #[error("You don't have permission")]
+    PermissionDenied,
+    #[error("This account is banned")]
+    Banned,
+    #[error("You are not impersonating anyone")]
+    NotImpersonating,
+    #[error("Requested identity provider does not exist")]
+    UnknownProvider,
+    #[error("Login with the identity provider failed")]
+    OidcFailed,
+    #[error("No account is linked to this identity")]
+    IdentityNotLinked,
+    #[error("This identity is already linked to an account")]
+    IdentityAlreadyLinked,
+    #[error("Token name must be 1 to 64 characters")]
+    InvalidTokenName,
+    #[error("At least one scope must be given")]
+    NoScope,
+    #[error("Too many tokens, revoke some first")]
+    TooManyTokens,
+    #[error("Requested token does not exist")]
+    TokenNotFound,
+    #[error("Enter the code of your authenticator app to continue")]
+    TotpRequired,
+    #[error("Code is not valid")]
+    InvalidCode,
+    #[error("Two-factor authentication is already enabled")]
+    TotpAlreadyEnabled,
+    #[error("Two-factor authentication is not enabled")]
+    TotpNotEnabled,
+    #[error("Too many failed attempts, the account is locked for a while")]
+    AccountLocked,
 }
 
 impl ErrorExtensions for AccountError {
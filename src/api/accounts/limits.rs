@@ -0,0 +1,67 @@
+use crate::middlewares::rate_limit::RateLimit;
+use crate::CONFIG;
+
+pub fn login_limit() -> RateLimit {
+    RateLimit {
+        name: "login",
+        limit: CONFIG.rate_limits.login_per_minute,
+        window_secs: 60,
+    }
+}
+
+// failed logins of an account, which is locked once they exceed the limit
+pub fn lockout_limit() -> RateLimit {
+    RateLimit {
+        name: "lockout",
+        limit: CONFIG.rate_limits.lockout_failures,
+        window_secs: CONFIG.rate_limits.lockout_minutes * 60,
+    }
+}
+
+pub fn register_limit() -> RateLimit {
+    RateLimit {
+        name: "register",
+        limit: CONFIG.rate_limits.register_per_hour,
+        window_secs: 3600,
+    }
+}
+
+pub fn reset_limit() -> RateLimit {
+    RateLimit {
+        name: "reset",
+        limit: CONFIG.rate_limits.reset_per_hour,
+        window_secs: 3600,
+    }
+}
+
+pub fn reset_account_limit() -> RateLimit {
+    RateLimit {
+        name: "reset_account",
+        limit: CONFIG.rate_limits.reset_per_account_hour,
+        window_secs: 3600,
+    }
+}
+
+pub fn verify_limit() -> RateLimit {
+    RateLimit {
+        name: "verify",
+        limit: CONFIG.rate_limits.verify_per_hour,
+        window_secs: 3600,
+    }
+}
+
+pub fn verify_account_limit() -> RateLimit {
+    RateLimit {
+        name: "verify_account",
+        limit: CONFIG.rate_limits.verify_per_account_hour,
+        window_secs: 3600,
+    }
+}
+
+pub fn submit_limit() -> Option<RateLimit> {
+    CONFIG.rate_limits.submit_per_minute.map(|limit| RateLimit {
+        name: "submit",
+        limit,
+        window_secs: 60,
+    })
+}
@@ -1,19 +1,41 @@
+pub mod admin;
+pub mod api_tokens;
 pub mod errors;
+pub mod limits;
+pub mod oidc;
+pub mod otp;
+pub mod password;
+pub mod profile;
+pub mod tokens;
+pub mod two_factor;
+pub mod validation;
 
 use actix_identity::Identity;
+use actix_session::Session;
 use actix_web::http::header::LOCATION;
 use actix_web::{delete, get, post, web, HttpMessage, HttpRequest, HttpResponse};
 use async_graphql::*;
+use chrono::prelude::*;
+use chrono_tz::Tz;
+use diesel::result::DatabaseErrorKind;
 use serde::{Deserialize, Serialize};
 use sha3::{Digest, Sha3_256};
 use std::convert::TryFrom;
+use uuid::Uuid;
 
 use super::constants::*;
 use super::ResponseBlock;
 use crate::db::accounts;
+use crate::db::models::{NewUser, User};
+use crate::mail::*;
 use crate::middlewares::postgresql::establish_connection;
+use crate::middlewares::rate_limit::{LimitBy, RateLimiter};
+use crate::CONFIG;
 
 pub use errors::*;
+use limits::*;
+use tokens::*;
+use validation::*;
 
 #[derive(Enum, Eq, PartialEq, Clone, Serialize, Deserialize, Debug, Copy)]
 #[repr(i32)]
@@ -34,6 +56,134 @@ impl TryFrom<i32> for AccountPerm {
     }
 }
 
+pub const EMAIL_TOKEN_HOURS: i64 = 24;
+
+pub fn hash_password(pass: &str) -> String {
+    let mut hasher = Sha3_256::new();
+    hasher.update(pass.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+#[derive(Clone, Debug)]
+pub struct Registration {
+    pub id: String,
+    pub pass: String,
+    pub email: String,
+    pub first_name: String,
+    pub last_name: String,
+    pub timezone: Tz,
+    pub preferred_language: Uuid,
+}
+
+pub async fn register_user(form: Registration, ip: Option<String>) -> Result<(), AccountError> {
+    if let Some(ip) = ip {
+        if register_limit().hit(&ip).is_some() {
+            return Err(AccountError::TooManyRequests);
+        }
+    }
+    validate_id(&form.id)?;
+    validate_password(&form.id, &form.pass)?;
+    validate_email(&form.email)?;
+    if accounts::by_id(form.id.clone()).is_ok() {
+        return Err(AccountError::UserAlreadyExists);
+    }
+    if accounts::by_email(form.email.clone()).is_ok() {
+        return Err(AccountError::EmailAlreadyExists);
+    }
+    let user = accounts::register(NewUser {
+        id: form.id,
+        pass: hash_password(&form.pass),
+        permission: AccountPerm::User as i32,
+        timezone: form.timezone.to_string(),
+        first_name: form.first_name,
+        last_name: form.last_name,
+        email: form.email,
+        preferred_language: form.preferred_language,
+    })
+    .map_err(|err| write_error(&err))?;
+    // the account stays usable even if the mail is lost, it can be sent again
+    if let Err(err) = send_verification(&user).await {
+        warn!("Cannot send verification mail to {}: {:?}", user.pk, err);
+    }
+    Ok(())
+}
+
+// Two requests with the same address can both pass the check before either is saved, and
+// then the unique index refuses the later one.
+pub fn write_error(err: &diesel::result::Error) -> AccountError {
+    match err {
+        diesel::result::Error::DatabaseError(DatabaseErrorKind::UniqueViolation, info)
+            if info.constraint_name() == Some(accounts::EMAIL_KEY) =>
+        {
+            AccountError::EmailAlreadyExists
+        }
+        _ => AccountError::DatabaseError,
+    }
+}
+
+// The token is bound to the address, so it is void once the email changes.
+pub async fn send_verification(user: &User) -> Result<(), AccountError> {
+    let token = sign(
+        EMAIL_VERIFICATION,
+        &format!("{}:{}", user.pk, user.email),
+        Utc::now() + chrono::Duration::hours(EMAIL_TOKEN_HOURS),
+        secret(),
+    );
+    let link = format!(
+        "{}/verify-email?token={}",
+        CONFIG.accounts.public_url.clone().unwrap_or_default(),
+        token
+    );
+    send_mail(Mail {
+        to: user.email.clone(),
+        subject: String::from("Verify your email address"),
+        body: format!(
+            "Hello {},\n\nOpen the link below within {} hours to verify your email address.\n\n{}\n",
+            user.id, EMAIL_TOKEN_HOURS, link
+        ),
+    })
+    .await
+    .map_err(|err| {
+        error!("{}", err);
+        AccountError::MailError
+    })
+}
+
+pub fn confirm_email(token: &str) -> Result<(), AccountError> {
+    let subject = verify(token, EMAIL_VERIFICATION, Utc::now(), secret())
+        .ok_or(AccountError::InvalidToken)?;
+    let (pk, email) = subject.split_once(':').ok_or(AccountError::InvalidToken)?;
+    let pk = pk.parse::<Uuid>().map_err(|_| AccountError::InvalidToken)?;
+    match accounts::verify_email(pk, email.to_string()) {
+        Ok(1) => Ok(()),
+        Ok(_) => Err(AccountError::InvalidToken),
+        Err(_) => Err(AccountError::DatabaseError),
+    }
+}
+
+// Succeeds for unknown addresses too, so that it does not reveal registered ones. Only
+// the limit per client address is reported, as it does not depend on the address asked for.
+pub async fn resend_verification(email: String, ip: Option<String>) -> Result<(), AccountError> {
+    if let Some(ip) = ip {
+        if verify_limit().hit(&ip).is_some() {
+            return Err(AccountError::TooManyRequests);
+        }
+    }
+    let user = match accounts::by_email(email) {
+        Ok(user) if !user.email_verified => user,
+        _ => return Ok(()),
+    };
+    let result = if verify_account_limit().hit(&user.pk.to_string()).is_some() {
+        Err(AccountError::TooManyRequests)
+    } else {
+        send_verification(&user).await
+    };
+    if let Err(err) = result {
+        warn!("Verification mail of {} is not sent: {:?}", user.id, err);
+    }
+    Ok(())
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct LoginRequest {
     pub id: String,
@@ -82,23 +232,47 @@ pub async fn delete_self(user: Option<Identity>) -> HttpResponse {
     }
 }
 
-#[post("/login")]
+// Accounts are locked for a while after repeated wrong passwords, whoever tries them.
+#[post(
+    "/login",
+    wrap = "RateLimiter::new(login_limit(), LimitBy::ClientAddr)"
+)]
 pub async fn login(
     user: Option<Identity>,
     request: HttpRequest,
+    session: Session,
     form: web::Json<LoginRequest>,
 ) -> HttpResponse {
     if let None = user {
-        let mut hasher = Sha3_256::new();
-        hasher.update(form.password.as_bytes());
-        let pass_hashed = hex::encode(hasher.finalize());
-        if let Ok((error, pk)) = accounts::login(form.id.clone(), pass_hashed) {
-            Identity::login(&request.extensions(), pk.to_string()).ok();
+        if lockout_limit().is_exceeded(&form.id) {
+            return HttpResponse::Ok()
+                .content_type("application/json")
+                .json(ResponseBlock {
+                    status: false,
+                    body: AccountError::AccountLocked,
+                });
+        }
+        if let Ok((error, pk)) = accounts::login(form.id.clone(), hash_password(&form.password)) {
+            if error == AccountError::PassNotMatched {
+                lockout_limit().hit(&form.id);
+            }
+            if error != AccountError::None {
+                return HttpResponse::Ok()
+                    .content_type("application/json")
+                    .json(ResponseBlock {
+                        status: false,
+                        body: error,
+                    });
+            }
+            lockout_limit().clear(&form.id);
+            let result = accounts::find_user(pk)
+                .map_err(|_| AccountError::DatabaseError)
+                .and_then(|user| two_factor::begin_session(&request, &session, &user));
             HttpResponse::Ok()
                 .content_type("application/json")
                 .json(ResponseBlock {
-                    status: true,
-                    body: error,
+                    status: result.is_ok(),
+                    body: result.err().unwrap_or(AccountError::None),
                 })
         } else {
             HttpResponse::Ok()
@@ -0,0 +1,354 @@
+use actix_identity::Identity;
+use actix_session::Session;
+use actix_web::http::header::LOCATION;
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use chrono::prelude::*;
+use openidconnect::core::{CoreAuthenticationFlow, CoreClient, CoreProviderMetadata};
+use openidconnect::reqwest::async_http_client;
+use openidconnect::{
+    AuthorizationCode, ClientId, ClientSecret, CsrfToken, IssuerUrl, Nonce, PkceCodeChallenge,
+    PkceCodeVerifier, RedirectUrl, Scope, TokenResponse,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::two_factor::begin_session;
+use super::*;
+use crate::config::OidcProvider;
+use crate::db::models::ExternalIdentity;
+
+const OIDC_PENDING: &'static str = "oidc_pending";
+
+// Kept in the session from the redirect to the provider until its callback
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct PendingLogin {
+    provider: String,
+    state: String,
+    nonce: String,
+    verifier: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct CallbackQuery {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+}
+
+// Verified claims of the ID token
+#[derive(Clone, Debug, Default)]
+pub struct ExternalClaims {
+    pub subject: String,
+    pub email: Option<String>,
+    pub email_verified: bool,
+    pub username: Option<String>,
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+}
+
+pub fn find_provider(name: &str) -> Result<&'static OidcProvider, AccountError> {
+    CONFIG
+        .oidc
+        .iter()
+        .find(|provider| provider.name == name)
+        .ok_or(AccountError::UnknownProvider)
+}
+
+async fn client_of(provider: &OidcProvider) -> Result<CoreClient, AccountError> {
+    let issuer =
+        IssuerUrl::new(provider.issuer.clone()).map_err(|_| AccountError::UnknownProvider)?;
+    let redirect_url = RedirectUrl::new(provider.redirect_url.clone())
+        .map_err(|_| AccountError::UnknownProvider)?;
+    let metadata = CoreProviderMetadata::discover_async(issuer, async_http_client)
+        .await
+        .map_err(|err| {
+            error!("Cannot discover OIDC provider {}: {:?}", provider.name, err);
+            AccountError::OidcFailed
+        })?;
+    Ok(CoreClient::from_provider_metadata(
+        metadata,
+        ClientId::new(provider.client_id.clone()),
+        provider.client_secret.clone().map(ClientSecret::new),
+    )
+    .set_redirect_uri(redirect_url))
+}
+
+// Returns the authorization URL of the provider, using the authorization code flow with PKCE
+pub async fn begin_login(session: &Session, name: &str) -> Result<String, AccountError> {
+    let provider = find_provider(name)?;
+    let client = client_of(provider).await?;
+    let (challenge, verifier) = PkceCodeChallenge::new_random_sha256();
+    let mut request = client.authorize_url(
+        CoreAuthenticationFlow::AuthorizationCode,
+        CsrfToken::new_random,
+        Nonce::new_random,
+    );
+    for scope in provider.scopes.iter() {
+        request = request.add_scope(Scope::new(scope.clone()));
+    }
+    let (url, state, nonce) = request.set_pkce_challenge(challenge).url();
+    session
+        .insert(
+            OIDC_PENDING,
+            PendingLogin {
+                provider: provider.name.clone(),
+                state: state.secret().clone(),
+                nonce: nonce.secret().clone(),
+                verifier: verifier.secret().clone(),
+            },
+        )
+        .map_err(|_| AccountError::DatabaseError)?;
+    Ok(url.to_string())
+}
+
+async fn exchange(
+    provider: &OidcProvider,
+    pending: PendingLogin,
+    code: String,
+) -> Result<ExternalClaims, AccountError> {
+    let client = client_of(provider).await?;
+    let token = client
+        .exchange_code(AuthorizationCode::new(code))
+        .set_pkce_verifier(PkceCodeVerifier::new(pending.verifier))
+        .request_async(async_http_client)
+        .await
+        .map_err(|err| {
+            warn!("Code exchange with {} failed: {:?}", provider.name, err);
+            AccountError::OidcFailed
+        })?;
+    let id_token = token.id_token().ok_or(AccountError::OidcFailed)?;
+    let claims = id_token
+        .claims(&client.id_token_verifier(), &Nonce::new(pending.nonce))
+        .map_err(|err| {
+            warn!("Invalid ID token from {}: {:?}", provider.name, err);
+            AccountError::OidcFailed
+        })?;
+    Ok(ExternalClaims {
+        subject: claims.subject().as_str().to_string(),
+        email: claims.email().map(|x| x.as_str().to_string()),
+        email_verified: claims.email_verified().unwrap_or(false),
+        username: claims.preferred_username().map(|x| x.as_str().to_string()),
+        first_name: claims
+            .given_name()
+            .and_then(|x| x.get(None))
+            .map(|x| x.as_str().to_string()),
+        last_name: claims
+            .family_name()
+            .and_then(|x| x.get(None))
+            .map(|x| x.as_str().to_string()),
+    })
+}
+
+fn active_user(user_pk: Uuid) -> Result<Uuid, AccountError> {
+    match accounts::find_user(user_pk) {
+        Ok(user) if user.deleted_at.is_some() => Err(AccountError::UserNotExists),
+        Ok(user) if user.is_banned => Err(AccountError::Banned),
+        Ok(user) => Ok(user.pk),
+        Err(_) => Err(AccountError::UserNotExists),
+    }
+}
+
+fn unused_id(claims: &ExternalClaims) -> String {
+    let base = suggest_id(claims.username.as_deref(), claims.email.as_deref());
+    (0..1000)
+        .map(|n| {
+            if n == 0 {
+                base.clone()
+            } else {
+                format!("{}{}", base, n)
+            }
+        })
+        .find(|id| accounts::by_id(id.clone()).is_err())
+        .unwrap_or_else(|| Uuid::new_v4().as_simple().to_string()[..ID_MAX_LENGTH].to_string())
+}
+
+// New accounts have no password, so they can log in only through their provider
+// until they reset it by mail.
+fn provision(claims: &ExternalClaims) -> Result<User, AccountError> {
+    let email = claims
+        .email
+        .clone()
+        .filter(|email| validate_email(email).is_ok() && accounts::by_email(email.clone()).is_err())
+        .unwrap_or_default();
+    let user = accounts::register(NewUser {
+        id: unused_id(claims),
+        pass: String::new(),
+        permission: AccountPerm::User as i32,
+        timezone: CONFIG.general.timezone.to_string(),
+        first_name: claims.first_name.clone().unwrap_or_default(),
+        last_name: claims.last_name.clone().unwrap_or_default(),
+        email: email.clone(),
+        preferred_language: CONFIG.general.default_language,
+    })
+    .map_err(|_| AccountError::DatabaseError)?;
+    if claims.email_verified && !email.is_empty() {
+        accounts::verify_email(user.pk, email).map_err(|_| AccountError::DatabaseError)?;
+    }
+    Ok(user)
+}
+
+// Where an external identity goes on login
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LinkTarget {
+    Linked(Uuid), // already linked to this account
+    LinkTo(Uuid), // to be linked to this existing account
+    Provision,    // to be linked to a new account
+}
+
+// Decides the account of an external identity from what is already known about it.
+// Unknown identities are linked to the logged in user, to the owner of the same email if
+// the provider is trusted for that and both sides verified it, or to a new account.
+pub fn link_target(
+    provider: &OidcProvider,
+    claims: &ExternalClaims,
+    viewer: Option<Uuid>,
+    linked: Option<&ExternalIdentity>,
+    email_owner: Option<&User>,
+) -> Result<LinkTarget, AccountError> {
+    if let Some(identity) = linked {
+        if viewer.map_or(false, |viewer| viewer != identity.user_pk) {
+            return Err(AccountError::IdentityAlreadyLinked);
+        }
+        return Ok(LinkTarget::Linked(identity.user_pk));
+    }
+    let email_owner = email_owner.filter(|user| {
+        provider.link_by_email
+            && claims.email_verified
+            && user.email_verified
+            && claims
+                .email
+                .as_deref()
+                .map_or(false, |email| email.eq_ignore_ascii_case(&user.email))
+    });
+    if let Some(viewer) = viewer {
+        Ok(LinkTarget::LinkTo(viewer))
+    } else if let Some(user) = email_owner {
+        Ok(LinkTarget::LinkTo(user.pk))
+    } else if provider.auto_provision {
+        Ok(LinkTarget::Provision)
+    } else {
+        Err(AccountError::IdentityNotLinked)
+    }
+}
+
+pub fn resolve_user(
+    provider: &OidcProvider,
+    claims: &ExternalClaims,
+    viewer: Option<Uuid>,
+) -> Result<Uuid, AccountError> {
+    let linked =
+        accounts::find_external_identity(provider.name.clone(), claims.subject.clone()).ok();
+    let email_owner = claims
+        .email
+        .clone()
+        .and_then(|email| accounts::by_email(email).ok());
+    let user_pk = match link_target(
+        provider,
+        claims,
+        viewer,
+        linked.as_ref(),
+        email_owner.as_ref(),
+    )? {
+        LinkTarget::Linked(user_pk) => return active_user(user_pk),
+        LinkTarget::LinkTo(user_pk) => user_pk,
+        LinkTarget::Provision => provision(claims)?.pk,
+    };
+    let user_pk = active_user(user_pk)?;
+    accounts::link_external_identity(ExternalIdentity {
+        pk: Uuid::new_v4(),
+        user_pk,
+        provider: provider.name.clone(),
+        subject: claims.subject.clone(),
+        linked_at: Utc::now(),
+    })
+    .map_err(|_| AccountError::DatabaseError)?;
+    Ok(user_pk)
+}
+
+pub async fn finish_login(
+    session: &Session,
+    viewer: Option<Uuid>,
+    name: &str,
+    query: CallbackQuery,
+) -> Result<Uuid, AccountError> {
+    let provider = find_provider(name)?;
+    // the pending login is used at most once, whatever the result is
+    let pending = session
+        .remove_as::<PendingLogin>(OIDC_PENDING)
+        .and_then(|x| x.ok())
+        .ok_or(AccountError::InvalidToken)?;
+    if let Some(err) = query.error {
+        info!("OIDC login with {} was refused: {}", provider.name, err);
+        return Err(AccountError::OidcFailed);
+    }
+    if pending.provider != provider.name || query.state.as_ref() != Some(&pending.state) {
+        return Err(AccountError::InvalidToken);
+    }
+    let code = query.code.ok_or(AccountError::OidcFailed)?;
+    let claims = exchange(provider, pending, code).await?;
+    resolve_user(provider, &claims, viewer)
+}
+
+// Unlinking the last identity of an account without a password would lock it out.
+pub fn unlink_identity(user_pk: Uuid, provider: String) -> Result<(), AccountError> {
+    let user = accounts::find_user(user_pk).map_err(|_| AccountError::UserNotExists)?;
+    let identities =
+        accounts::external_identities_of(user_pk).map_err(|_| AccountError::DatabaseError)?;
+    if !identities.iter().any(|x| x.provider == provider) {
+        return Err(AccountError::IdentityNotLinked);
+    }
+    if user.pass.is_empty() && identities.len() == 1 {
+        return Err(AccountError::PermissionDenied);
+    }
+    accounts::unlink_external_identity(user_pk, provider)
+        .map(|_| ())
+        .map_err(|_| AccountError::DatabaseError)
+}
+
+fn error_response(err: AccountError) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("application/json")
+        .json(ResponseBlock {
+            status: false,
+            body: err,
+        })
+}
+
+#[get("/oidc/{provider}/login")]
+pub async fn oidc_login(session: Session, path: web::Path<String>) -> HttpResponse {
+    match begin_login(&session, &path.into_inner()).await {
+        Ok(url) => HttpResponse::Found()
+            .insert_header((LOCATION, url))
+            .finish(),
+        Err(err) => error_response(err),
+    }
+}
+
+#[get("/oidc/{provider}/callback")]
+pub async fn oidc_callback(
+    user: Option<Identity>,
+    request: HttpRequest,
+    session: Session,
+    path: web::Path<String>,
+    query: web::Query<CallbackQuery>,
+) -> HttpResponse {
+    let viewer = user.map(|user| user.id().unwrap().parse::<Uuid>().unwrap());
+    match finish_login(&session, viewer, &path.into_inner(), query.into_inner()).await {
+        Ok(user_pk) => {
+            let public_url = CONFIG.accounts.public_url.clone().unwrap_or_default();
+            let location = match accounts::find_user(user_pk)
+                .map_err(|_| AccountError::DatabaseError)
+                .and_then(|user| begin_session(&request, &session, &user))
+            {
+                Ok(_) => format!("{}/", public_url),
+                // the frontend asks for the code and posts it to /accounts/login/totp
+                Err(AccountError::TotpRequired) => format!("{}/login/totp", public_url),
+                Err(err) => return error_response(err),
+            };
+            HttpResponse::Found()
+                .insert_header((LOCATION, location))
+                .finish()
+        }
+        Err(err) => error_response(err),
+    }
+}
@@ -0,0 +1,102 @@
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+
+pub const TOTP_DIGITS: u32 = 6;
+pub const TOTP_PERIOD: u64 = 30;
+// steps accepted before and after the current one, for clock drift
+pub const TOTP_SKEW: u64 = 1;
+
+const BASE32_ALPHABET: &'static [u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+// RFC 4648 base32 without padding, as authenticator apps expect
+pub fn base32_encode(data: &[u8]) -> String {
+    let mut out = String::new();
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for byte in data {
+        buffer = (buffer << 8) | *byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    out
+}
+
+pub fn base32_decode(text: &str) -> Option<Vec<u8>> {
+    let mut out = vec![];
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for x in text.chars().filter(|x| *x != '=' && !x.is_whitespace()) {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|y| *y as char == x.to_ascii_uppercase())?;
+        buffer = (buffer << 5) | value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+// RFC 4226
+pub fn hotp(secret: &[u8], counter: u64) -> u32 {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("HMAC takes keys of any size");
+    mac.update(&counter.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let code = u32::from_be_bytes([
+        digest[offset] & 0x7f,
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3],
+    ]);
+    code % 10u32.pow(TOTP_DIGITS)
+}
+
+pub fn totp_step(unix_time: u64) -> u64 {
+    unix_time / TOTP_PERIOD
+}
+
+// Returns the step the code belongs to, so that the caller can refuse codes of steps
+// already used.
+pub fn verify_totp(secret: &[u8], code: &str, unix_time: u64) -> Option<u64> {
+    let code = code.trim();
+    if code.len() != TOTP_DIGITS as usize || !code.chars().all(|x| x.is_ascii_digit()) {
+        return None;
+    }
+    let code = code.parse::<u32>().ok()?;
+    let step = totp_step(unix_time);
+    (step.saturating_sub(TOTP_SKEW)..=step + TOTP_SKEW).find(|step| hotp(secret, *step) == code)
+}
+
+fn uri_encode(text: &str) -> String {
+    text.bytes()
+        .map(|x| {
+            if x.is_ascii_alphanumeric() || b"-_.~".contains(&x) {
+                (x as char).to_string()
+            } else {
+                format!("%{:02X}", x)
+            }
+        })
+        .collect()
+}
+
+// Key URI understood by authenticator apps, usually shown as a QR code
+pub fn otpauth_uri(issuer: &str, account: &str, secret: &str) -> String {
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&digits={}&period={}",
+        uri_encode(issuer),
+        uri_encode(account),
+        secret,
+        uri_encode(issuer),
+        TOTP_DIGITS,
+        TOTP_PERIOD
+    )
+}
@@ -0,0 +1,202 @@
+use actix_identity::{Identity, IdentityExt};
+use actix_session::{Session, SessionExt};
+use actix_web::dev::ServiceRequest;
+use redis::Commands;
+use uuid::Uuid;
+
+use super::limits::*;
+use super::validation::validate_password;
+use super::*;
+use crate::middlewares::redis::establish_redis;
+
+pub const RESET_TOKEN_MINUTES: usize = 30;
+
+// Sessions remember the epoch of their user at login, and the epoch is bumped on a
+// password reset so that every older session is logged out.
+pub const SESSION_EPOCH: &'static str = "session_epoch";
+
+fn epoch_key(user_pk: Uuid) -> String {
+    format!("session_epoch:{}", user_pk)
+}
+
+fn reset_key(token: &str) -> String {
+    format!("password_reset:{}", token)
+}
+
+pub fn session_epoch(user_pk: Uuid) -> Option<i64> {
+    let mut redis = establish_redis().ok()?;
+    let epoch: Option<i64> = redis.get(epoch_key(user_pk)).ok()?;
+    Some(epoch.unwrap_or(0))
+}
+
+pub fn bump_session_epoch(user_pk: Uuid) -> Result<(), AccountError> {
+    let mut redis = establish_redis().map_err(|_| AccountError::DatabaseError)?;
+    redis
+        .incr::<_, _, i64>(epoch_key(user_pk), 1)
+        .map(|_| ())
+        .map_err(|_| AccountError::DatabaseError)
+}
+
+// Logs out every session of the user and revokes its API tokens, for when its
+// credentials may be known to someone else
+pub fn revoke_credentials(user_pk: Uuid) -> Result<(), AccountError> {
+    accounts::delete_api_tokens_of(user_pk).map_err(|_| AccountError::DatabaseError)?;
+    bump_session_epoch(user_pk)
+}
+
+// Logs `user_pk` in on the session of the request, remembering its current epoch
+pub fn start_session(request: &HttpRequest, session: &Session, user_pk: Uuid) {
+    Identity::login(&request.extensions(), user_pk.to_string()).ok();
+    if let Some(epoch) = session_epoch(user_pk) {
+        session.insert(SESSION_EPOCH, epoch).ok();
+    }
+}
+
+// Logs out the identity of the request if its session predates a password reset.
+// Sessions are trusted as they are while redis is unreachable.
+pub fn check_session(req: &ServiceRequest) {
+    if let Ok(identity) = req.get_identity() {
+        if let Some(user_pk) = identity.id().ok().and_then(|x| x.parse::<Uuid>().ok()) {
+            let epoch = req
+                .get_session()
+                .get::<i64>(SESSION_EPOCH)
+                .ok()
+                .flatten()
+                .unwrap_or(0);
+            if session_epoch(user_pk).map_or(false, |cur| cur != epoch) {
+                identity.logout();
+            }
+        }
+    }
+}
+
+// Checks the password asked for sensitive changes. Wrong ones count toward the lockout
+// like those of the login, so that a hijacked session cannot be used to guess it.
+pub fn check_password(user: &User, pass: &str) -> Result<(), AccountError> {
+    if lockout_limit().is_exceeded(&user.id) {
+        return Err(AccountError::AccountLocked);
+    }
+    if hash_password(pass) != user.pass {
+        lockout_limit().hit(&user.id);
+        return Err(AccountError::PassNotMatched);
+    }
+    Ok(())
+}
+
+pub fn change_password(user_pk: Uuid, current: &str, new: &str) -> Result<(), AccountError> {
+    let user = accounts::find_user(user_pk).map_err(|_| AccountError::UserNotExists)?;
+    check_password(&user, current)?;
+    validate_password(&user.id, new)?;
+    accounts::set_password(user_pk, hash_password(new))
+        .map(|_| ())
+        .map_err(|_| AccountError::DatabaseError)
+}
+
+// Succeeds for unknown addresses too, so that it does not reveal registered ones. Only
+// the limit per client address is reported, as it does not depend on the address asked for.
+pub async fn request_password_reset(email: String, ip: Option<String>) -> Result<(), AccountError> {
+    if let Some(ip) = ip {
+        if reset_limit().hit(&ip).is_some() {
+            return Err(AccountError::TooManyRequests);
+        }
+    }
+    let user = match accounts::by_email(email) {
+        Ok(user) => user,
+        Err(_) => return Ok(()),
+    };
+    let result = if reset_account_limit().hit(&user.pk.to_string()).is_some() {
+        Err(AccountError::TooManyRequests)
+    } else {
+        send_reset_mail(&user).await
+    };
+    if let Err(err) = result {
+        warn!("Password reset of {} is not sent: {:?}", user.id, err);
+    }
+    Ok(())
+}
+
+pub async fn send_reset_mail(user: &User) -> Result<(), AccountError> {
+    let token = format!(
+        "{}{}",
+        Uuid::new_v4().as_simple(),
+        Uuid::new_v4().as_simple()
+    );
+    let mut redis = establish_redis().map_err(|_| AccountError::DatabaseError)?;
+    redis
+        .set_ex::<_, _, ()>(
+            reset_key(&token),
+            user.pk.to_string(),
+            RESET_TOKEN_MINUTES * 60,
+        )
+        .map_err(|_| AccountError::DatabaseError)?;
+    let link = format!(
+        "{}/reset-password?token={}",
+        CONFIG.accounts.public_url.clone().unwrap_or_default(),
+        token
+    );
+    send_mail(Mail {
+        to: user.email.clone(),
+        subject: String::from("Reset your password"),
+        body: format!(
+            "Hello {},\n\nOpen the link below within {} minutes to set a new password. \
+             Ignore this mail if you did not ask for it.\n\n{}\n",
+            user.id, RESET_TOKEN_MINUTES, link
+        ),
+    })
+    .await
+    .map_err(|err| {
+        error!("{}", err);
+        AccountError::MailError
+    })
+}
+
+// Where reset tokens are kept, which is redis but for testing
+pub trait ResetTokens {
+    fn user_of(&mut self, token: &str) -> Result<Option<Uuid>, AccountError>;
+    // Removes the token, returning whether it was still there
+    fn take(&mut self, token: &str) -> Result<bool, AccountError>;
+}
+
+impl ResetTokens for redis::Connection {
+    fn user_of(&mut self, token: &str) -> Result<Option<Uuid>, AccountError> {
+        self.get::<_, Option<String>>(reset_key(token))
+            .map(|x| x.and_then(|x| x.parse::<Uuid>().ok()))
+            .map_err(|_| AccountError::DatabaseError)
+    }
+
+    fn take(&mut self, token: &str) -> Result<bool, AccountError> {
+        self.del::<_, i64>(reset_key(token))
+            .map(|removed| removed > 0)
+            .map_err(|_| AccountError::DatabaseError)
+    }
+}
+
+// Consumes the token once `check` accepts its user, so that a rejected password can be
+// tried again with the same mail. Only one of concurrent uses gets it.
+pub fn use_reset_token<T>(
+    tokens: &mut impl ResetTokens,
+    token: &str,
+    check: impl FnOnce(Uuid) -> Result<T, AccountError>,
+) -> Result<T, AccountError> {
+    let user_pk = tokens.user_of(token)?.ok_or(AccountError::InvalidToken)?;
+    let checked = check(user_pk)?;
+    if !tokens.take(token)? {
+        return Err(AccountError::InvalidToken);
+    }
+    Ok(checked)
+}
+
+pub fn reset_password(token: &str, new: &str) -> Result<(), AccountError> {
+    let mut redis = establish_redis().map_err(|_| AccountError::DatabaseError)?;
+    let user = use_reset_token(&mut *redis, token, |user_pk| {
+        let user = accounts::find_user(user_pk).map_err(|_| AccountError::InvalidToken)?;
+        if user.deleted_at.is_some() {
+            return Err(AccountError::InvalidToken);
+        }
+        validate_password(&user.id, new)?;
+        Ok(user)
+    })?;
+    accounts::set_password(user.pk, hash_password(new)).map_err(|_| AccountError::DatabaseError)?;
+    lockout_limit().clear(&user.id);
+    revoke_credentials(user.pk)
+}
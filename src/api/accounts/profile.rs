@@ -0,0 +1,239 @@
+use actix_identity::Identity;
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use actix_web::{get, HttpResponse};
+use chrono::prelude::*;
+use chrono_tz::Tz;
+use serde::Serialize;
+use uuid::Uuid;
+
+use super::password::{bump_session_epoch, check_password};
+use super::*;
+use crate::api::judge::judge_states;
+use crate::db::clarifications::clarifications_by;
+use crate::db::judge::{search_submissions, SubmissionQuery};
+use crate::db::keydb::*;
+use crate::db::languages::find_language;
+use crate::db::models::*;
+use crate::judge::api::SubmissionState;
+use crate::APPDATA;
+
+#[derive(Clone, Debug, Default)]
+pub struct ProfileUpdate {
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    pub email: Option<String>,
+    pub timezone: Option<Tz>,
+    pub preferred_language: Option<Uuid>,
+}
+
+// Changes the form makes to the stored profile, None when it changes nothing. A new
+// email address has to be verified again before it is trusted.
+pub fn profile_changes(
+    user: &User,
+    form: ProfileUpdate,
+) -> Result<Option<UserChanges>, AccountError> {
+    let mut changes = UserChanges {
+        first_name: form.first_name,
+        last_name: form.last_name,
+        timezone: form.timezone.map(|tz| tz.to_string()),
+        preferred_language: form.preferred_language,
+        ..Default::default()
+    };
+    if let Some(email) = form.email.filter(|email| *email != user.email) {
+        validate_email(&email)?;
+        changes.email = Some(email);
+        changes.email_verified = Some(false);
+    }
+    if changes.first_name.is_none()
+        && changes.last_name.is_none()
+        && changes.timezone.is_none()
+        && changes.preferred_language.is_none()
+        && changes.email.is_none()
+    {
+        return Ok(None);
+    }
+    Ok(Some(changes))
+}
+
+pub async fn update_profile(user_pk: Uuid, form: ProfileUpdate) -> Result<User, AccountError> {
+    let user = accounts::find_user(user_pk).map_err(|_| AccountError::UserNotExists)?;
+    let changes = match profile_changes(&user, form)? {
+        Some(changes) => changes,
+        None => return Ok(user),
+    };
+    if let Some(lang_uuid) = changes.preferred_language {
+        find_language(lang_uuid).map_err(|_| AccountError::LanguageNotFound)?;
+    }
+    if let Some(email) = changes.email.clone() {
+        // the same address in another case still belongs to this user
+        if accounts::by_email(email)
+            .map(|x| x.pk != user_pk)
+            .unwrap_or(false)
+        {
+            return Err(AccountError::EmailAlreadyExists);
+        }
+    }
+    let reverify = changes.email.is_some();
+    let user = accounts::update_profile(user_pk, changes).map_err(|err| write_error(&err))?;
+    if reverify {
+        if let Err(err) = send_verification(&user).await {
+            warn!("Cannot send verification mail to {}: {:?}", user.pk, err);
+        }
+    }
+    Ok(user)
+}
+
+// Anonymizes the account and logs out all of its sessions. Submissions are kept so
+// that contest results do not change.
+pub fn delete_account(user_pk: Uuid, pass: &str) -> Result<(), AccountError> {
+    let user = accounts::find_user(user_pk).map_err(|_| AccountError::UserNotExists)?;
+    check_password(&user, pass)?;
+    accounts::anonymize_user(user_pk).map_err(|_| AccountError::DatabaseError)?;
+    accounts::unlink_all_identities(user_pk).map_err(|_| AccountError::DatabaseError)?;
+    accounts::delete_api_tokens_of(user_pk).map_err(|_| AccountError::DatabaseError)?;
+    accounts::set_totp(user_pk, None, false).map_err(|_| AccountError::DatabaseError)?;
+    accounts::replace_recovery_codes(user_pk, vec![]).map_err(|_| AccountError::DatabaseError)?;
+    if let Err(err) = bump_session_epoch(user_pk) {
+        warn!("Cannot invalidate sessions of {}: {:?}", user_pk, err);
+    }
+    Ok(())
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ProfileData {
+    pub pk: Uuid,
+    pub id: String,
+    pub email: String,
+    pub email_verified: bool,
+    pub first_name: String,
+    pub last_name: String,
+    pub timezone: String,
+    pub preferred_language: Uuid,
+    pub permission: i32,
+    pub totp_enabled: bool,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SubmissionData {
+    pub pk: Uuid,
+    pub task_pk: Uuid,
+    pub lang_uuid: Uuid,
+    pub issued_at: DateTime<Utc>,
+    pub state: Option<SubmissionState>,
+    pub score: Option<f64>,
+    pub source: Option<String>,
+}
+
+// API tokens without their hashes
+#[derive(Clone, Debug, Serialize)]
+pub struct ApiTokenData {
+    pub pk: Uuid,
+    pub name: String,
+    pub scopes: i32,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+// Everything stored about a user, handed out on request
+#[derive(Clone, Debug, Serialize)]
+pub struct UserData {
+    pub exported_at: DateTime<Utc>,
+    pub profile: ProfileData,
+    pub identities: Vec<ExternalIdentity>,
+    pub api_tokens: Vec<ApiTokenData>,
+    pub teams: Vec<Team>,
+    pub contests: Vec<ContestAccessibleUser>,
+    pub virtual_participations: Vec<VirtualParticipation>,
+    pub clarifications: Vec<Clarification>,
+    pub submissions: Vec<SubmissionData>,
+}
+
+pub async fn export_user_data(user_pk: Uuid) -> Result<UserData, AccountError> {
+    let user = accounts::find_user(user_pk).map_err(|_| AccountError::UserNotExists)?;
+    let query = SubmissionQuery {
+        user_pk: Some(user_pk),
+        ..Default::default()
+    };
+    let submissions = search_submissions(query).map_err(|_| AccountError::DatabaseError)?;
+    let pks: Vec<Uuid> = submissions.iter().map(|x| x.pk).collect();
+    let states = judge_states(pks.clone()).await;
+    let mut sources = get_many(&APPDATA.source_db, pks).await;
+    let submissions = submissions
+        .into_iter()
+        .map(|submission| {
+            let state = states.get(&submission.pk);
+            SubmissionData {
+                pk: submission.pk,
+                task_pk: submission.task_pk,
+                lang_uuid: submission.lang_uuid,
+                issued_at: submission.issued_at,
+                state: state.map(|x| x.submission_state),
+                score: state.map(|x| x.score),
+                source: sources.remove(&submission.pk),
+            }
+        })
+        .collect();
+    Ok(UserData {
+        exported_at: Utc::now(),
+        profile: ProfileData {
+            pk: user.pk,
+            id: user.id,
+            email: user.email,
+            email_verified: user.email_verified,
+            first_name: user.first_name,
+            last_name: user.last_name,
+            timezone: user.timezone,
+            preferred_language: user.preferred_language,
+            permission: user.permission,
+            totp_enabled: user.totp_enabled,
+        },
+        identities: accounts::external_identities_of(user_pk)
+            .map_err(|_| AccountError::DatabaseError)?,
+        api_tokens: accounts::api_tokens_of(user_pk)
+            .map_err(|_| AccountError::DatabaseError)?
+            .into_iter()
+            .map(|token| ApiTokenData {
+                pk: token.pk,
+                name: token.name,
+                scopes: token.scopes,
+                created_at: token.created_at,
+                expires_at: token.expires_at,
+                last_used_at: token.last_used_at,
+            })
+            .collect(),
+        teams: accounts::teams_of(user_pk).map_err(|_| AccountError::DatabaseError)?,
+        contests: accounts::contest_accesses_of(user_pk)
+            .map_err(|_| AccountError::DatabaseError)?,
+        virtual_participations: accounts::virtual_participations_by(user_pk)
+            .map_err(|_| AccountError::DatabaseError)?,
+        clarifications: clarifications_by(user_pk).map_err(|_| AccountError::DatabaseError)?,
+        submissions,
+    })
+}
+
+#[get("/self/export")]
+pub async fn export_self(user: Option<Identity>) -> HttpResponse {
+    let result = match user {
+        Some(user) => export_user_data(user.id().unwrap().parse::<Uuid>().unwrap()).await,
+        None => Err(AccountError::NotLoggedIn),
+    };
+    match result {
+        Ok(data) => HttpResponse::Ok()
+            .content_type("application/json")
+            .insert_header(ContentDisposition {
+                disposition: DispositionType::Attachment,
+                parameters: vec![DispositionParam::Filename(format!(
+                    "{}.json",
+                    data.profile.id
+                ))],
+            })
+            .json(data),
+        Err(err) => HttpResponse::Ok()
+            .content_type("application/json")
+            .json(ResponseBlock {
+                status: false,
+                body: err,
+            }),
+    }
+}
@@ -0,0 +1,64 @@
+use chrono::prelude::*;
+use sha3::{Digest, Sha3_256};
+
+use crate::CONFIG;
+
+pub const EMAIL_VERIFICATION: &'static str = "verify-email";
+
+lazy_static! {
+    static ref SECRET: String = match CONFIG.accounts.secret.clone() {
+        Some(secret) => secret,
+        None => {
+            warn!("accounts.secret is not set, tokens sent by mail expire on restart");
+            format!("{}{}", uuid::Uuid::new_v4(), uuid::Uuid::new_v4())
+        }
+    };
+}
+
+pub fn secret() -> &'static str {
+    &SECRET
+}
+
+fn signature(payload: &str, secret: &str) -> String {
+    let mut hasher = Sha3_256::new();
+    hasher.update(secret.as_bytes());
+    hasher.update([0u8]);
+    hasher.update(payload.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+// `{payload}.{signature}` in hex, where the payload is `{purpose}:{expiry}:{subject}`
+pub fn sign(purpose: &str, subject: &str, expires_at: DateTime<Utc>, secret: &str) -> String {
+    let payload = format!("{}:{}:{}", purpose, expires_at.timestamp(), subject);
+    format!(
+        "{}.{}",
+        hex::encode(payload.as_bytes()),
+        signature(&payload, secret)
+    )
+}
+
+// the subject of a valid and unexpired token for `purpose`
+pub fn verify(token: &str, purpose: &str, now: DateTime<Utc>, secret: &str) -> Option<String> {
+    let (payload, sig) = token.split_once('.')?;
+    let payload = String::from_utf8(hex::decode(payload).ok()?).ok()?;
+    let expected = signature(&payload, secret);
+    // compared in constant time
+    if expected.len() != sig.len()
+        || expected
+            .bytes()
+            .zip(sig.bytes())
+            .fold(0u8, |acc, (x, y)| acc | (x ^ y))
+            != 0
+    {
+        return None;
+    }
+    let mut parts = payload.splitn(3, ':');
+    if parts.next()? != purpose {
+        return None;
+    }
+    let expires_at = parts.next()?.parse::<i64>().ok()?;
+    if expires_at <= now.timestamp() {
+        return None;
+    }
+    Some(parts.next()?.to_string())
+}
@@ -0,0 +1,212 @@
+use actix_session::Session;
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::limits::*;
+use super::otp::*;
+use super::password::{check_password, start_session};
+use super::*;
+use crate::middlewares::rate_limit::{LimitBy, RateLimiter};
+
+pub const RECOVERY_CODES: usize = 10;
+pub const CHALLENGE_MINUTES: i64 = 5;
+pub const CHALLENGE_ATTEMPTS: u32 = 5;
+
+// the user who passed the first login step and still has to enter a code
+const PENDING_2FA: &'static str = "pending_2fa";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Challenge {
+    user_pk: Uuid,
+    expires_at: DateTime<Utc>,
+    attempts: u32,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct CodeRequest {
+    pub code: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct Enrollment {
+    pub secret: String,
+    pub uri: String,
+}
+
+fn issuer() -> String {
+    CONFIG
+        .accounts
+        .totp_issuer
+        .clone()
+        .unwrap_or_else(|| String::from("PMS"))
+}
+
+// Logs the user in, or asks for the second factor first when it is enabled.
+pub fn begin_session(
+    request: &HttpRequest,
+    session: &Session,
+    user: &User,
+) -> Result<(), AccountError> {
+    if !user.totp_enabled {
+        start_session(request, session, user.pk);
+        return Ok(());
+    }
+    session
+        .insert(
+            PENDING_2FA,
+            Challenge {
+                user_pk: user.pk,
+                expires_at: Utc::now() + chrono::Duration::minutes(CHALLENGE_MINUTES),
+                attempts: 0,
+            },
+        )
+        .map_err(|_| AccountError::DatabaseError)?;
+    Err(AccountError::TotpRequired)
+}
+
+// A TOTP code or one of the unused recovery codes
+pub fn check_code(user: &User, code: &str) -> Result<(), AccountError> {
+    let secret = user
+        .totp_secret
+        .as_deref()
+        .and_then(base32_decode)
+        .ok_or(AccountError::TotpNotEnabled)?;
+    if let Some(step) = verify_totp(&secret, code, Utc::now().timestamp() as u64) {
+        return match accounts::use_totp_step(user.pk, step as i64) {
+            Ok(1) => Ok(()),
+            Ok(_) => Err(AccountError::InvalidCode),
+            Err(_) => Err(AccountError::DatabaseError),
+        };
+    }
+    match accounts::use_recovery_code(user.pk, hash_password(&normalize_recovery_code(code))) {
+        Ok(1) => Ok(()),
+        Ok(_) => Err(AccountError::InvalidCode),
+        Err(_) => Err(AccountError::DatabaseError),
+    }
+}
+
+pub fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|x| x.is_ascii_alphanumeric())
+        .map(|x| x.to_ascii_lowercase())
+        .collect()
+}
+
+pub fn finish_login(
+    request: &HttpRequest,
+    session: &Session,
+    code: &str,
+) -> Result<(), AccountError> {
+    let mut challenge = session
+        .get::<Challenge>(PENDING_2FA)
+        .ok()
+        .flatten()
+        .ok_or(AccountError::NotLoggedIn)?;
+    if challenge.expires_at <= Utc::now() || challenge.attempts >= CHALLENGE_ATTEMPTS {
+        session.remove(PENDING_2FA);
+        return Err(AccountError::NotLoggedIn);
+    }
+    let user = accounts::find_user(challenge.user_pk).map_err(|_| AccountError::UserNotExists)?;
+    if lockout_limit().is_exceeded(&user.id) {
+        return Err(AccountError::AccountLocked);
+    }
+    if let Err(err) = check_code(&user, code) {
+        lockout_limit().hit(&user.id);
+        challenge.attempts += 1;
+        session.insert(PENDING_2FA, challenge).ok();
+        return Err(err);
+    }
+    session.remove(PENDING_2FA);
+    start_session(request, session, user.pk);
+    Ok(())
+}
+
+// Starts over with a new secret, which is enabled by `confirm_totp`.
+pub fn enroll_totp(user_pk: Uuid, pass: &str) -> Result<Enrollment, AccountError> {
+    let user = accounts::find_user(user_pk).map_err(|_| AccountError::UserNotExists)?;
+    check_password(&user, pass)?;
+    if user.totp_enabled {
+        return Err(AccountError::TotpAlreadyEnabled);
+    }
+    let secret =
+        base32_encode(&[*Uuid::new_v4().as_bytes(), *Uuid::new_v4().as_bytes()].concat()[..20]);
+    accounts::set_totp(user_pk, Some(secret.clone()), false)
+        .map_err(|_| AccountError::DatabaseError)?;
+    Ok(Enrollment {
+        uri: otpauth_uri(&issuer(), &user.id, &secret),
+        secret,
+    })
+}
+
+// Returns the recovery codes, which are shown only this once.
+pub fn confirm_totp(user_pk: Uuid, code: &str) -> Result<Vec<String>, AccountError> {
+    let user = accounts::find_user(user_pk).map_err(|_| AccountError::UserNotExists)?;
+    if user.totp_enabled {
+        return Err(AccountError::TotpAlreadyEnabled);
+    }
+    let secret = user
+        .totp_secret
+        .as_deref()
+        .and_then(base32_decode)
+        .ok_or(AccountError::TotpNotEnabled)?;
+    let step = verify_totp(&secret, code, Utc::now().timestamp() as u64)
+        .ok_or(AccountError::InvalidCode)?;
+    accounts::set_totp(user_pk, user.totp_secret.clone(), true)
+        .map_err(|_| AccountError::DatabaseError)?;
+    accounts::use_totp_step(user_pk, step as i64).map_err(|_| AccountError::DatabaseError)?;
+    regenerate_recovery_codes(user_pk)
+}
+
+pub fn regenerate_recovery_codes(user_pk: Uuid) -> Result<Vec<String>, AccountError> {
+    let codes: Vec<String> = (0..RECOVERY_CODES)
+        .map(|_| {
+            let raw = Uuid::new_v4().as_simple().to_string();
+            format!("{}-{}", &raw[..5], &raw[5..10])
+        })
+        .collect();
+    accounts::replace_recovery_codes(
+        user_pk,
+        codes
+            .iter()
+            .map(|code| hash_password(&normalize_recovery_code(code)))
+            .collect(),
+    )
+    .map_err(|_| AccountError::DatabaseError)?;
+    Ok(codes)
+}
+
+pub fn disable_totp(user_pk: Uuid, pass: &str, code: &str) -> Result<(), AccountError> {
+    let user = accounts::find_user(user_pk).map_err(|_| AccountError::UserNotExists)?;
+    check_password(&user, pass)?;
+    if !user.totp_enabled {
+        return Err(AccountError::TotpNotEnabled);
+    }
+    if let Err(err) = check_code(&user, code) {
+        lockout_limit().hit(&user.id);
+        return Err(err);
+    }
+    accounts::set_totp(user_pk, None, false).map_err(|_| AccountError::DatabaseError)?;
+    accounts::replace_recovery_codes(user_pk, vec![])
+        .map(|_| ())
+        .map_err(|_| AccountError::DatabaseError)
+}
+
+#[post(
+    "/login/totp",
+    wrap = "RateLimiter::new(login_limit(), LimitBy::ClientAddr)"
+)]
+pub async fn login_totp(
+    request: HttpRequest,
+    session: Session,
+    form: web::Json<CodeRequest>,
+) -> HttpResponse {
+    let (status, body) = match finish_login(&request, &session, &form.code) {
+        Ok(_) => (true, AccountError::None),
+        Err(err) => (false, err),
+    };
+    HttpResponse::Ok()
+        .content_type("application/json")
+        .json(ResponseBlock { status, body })
+}
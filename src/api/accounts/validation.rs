@@ -0,0 +1,81 @@
+use super::errors::AccountError;
+
+pub const ID_MIN_LENGTH: usize = 3;
+pub const ID_MAX_LENGTH: usize = 20;
+pub const PASSWORD_MIN_LENGTH: usize = 8;
+pub const PASSWORD_MAX_LENGTH: usize = 128;
+
+// letters, digits, `_`, `-` and `.`, starting with a letter or a digit
+pub fn validate_id(id: &str) -> Result<(), AccountError> {
+    let len = id.chars().count();
+    if len < ID_MIN_LENGTH
+        || len > ID_MAX_LENGTH
+        || !id.starts_with(|x: char| x.is_ascii_alphanumeric())
+        || !id
+            .chars()
+            .all(|x| x.is_ascii_alphanumeric() || x == '_' || x == '-' || x == '.')
+    {
+        Err(AccountError::InvalidId)
+    } else {
+        Ok(())
+    }
+}
+
+// A valid id made from the preferred username or the email of an external identity.
+// Uniqueness is up to the caller, which may append digits up to `ID_MAX_LENGTH`.
+pub fn suggest_id(username: Option<&str>, email: Option<&str>) -> String {
+    let base = username
+        .or_else(|| email.and_then(|x| x.split('@').next()))
+        .unwrap_or_default();
+    let id: String = base
+        .chars()
+        .skip_while(|x| !x.is_ascii_alphanumeric())
+        .take_while(|x| x.is_ascii_alphanumeric() || *x == '_' || *x == '-' || *x == '.')
+        .take(ID_MAX_LENGTH - 4)
+        .collect();
+    if id.len() < ID_MIN_LENGTH {
+        String::from("user")
+    } else {
+        id
+    }
+}
+
+// at least one letter and one digit, and not the same as the id
+pub fn validate_password(id: &str, password: &str) -> Result<(), AccountError> {
+    let len = password.chars().count();
+    if len < PASSWORD_MIN_LENGTH
+        || len > PASSWORD_MAX_LENGTH
+        || !password.chars().any(|x| x.is_alphabetic())
+        || !password.chars().any(|x| x.is_numeric())
+        || password.eq_ignore_ascii_case(id)
+    {
+        Err(AccountError::WeakPassword)
+    } else {
+        Ok(())
+    }
+}
+
+pub fn validate_email(email: &str) -> Result<(), AccountError> {
+    let valid = match email.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && local.len() <= 64
+                && email.len() <= 254
+                && !domain.contains('@')
+                && !email.chars().any(|x| x.is_whitespace() || x.is_control())
+                && domain.split('.').count() >= 2
+                && domain.split('.').all(|label| {
+                    !label.is_empty()
+                        && !label.starts_with('-')
+                        && !label.ends_with('-')
+                        && label.chars().all(|x| x.is_alphanumeric() || x == '-')
+                })
+        }
+        None => false,
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(AccountError::InvalidEmail)
+    }
+}
@@ -0,0 +1,194 @@
+use actix_identity::Identity;
+use actix_web::{get, web, HttpResponse};
+use chrono::prelude::*;
+use serde_json::Value;
+use uuid::Uuid;
+
+use super::*;
+use crate::contests::clics;
+
+// Read-only endpoints of the CLICS Contest API. Contests, problems, teams and the
+// scoreboard are open once a public contest has started; submissions, judgements,
+// runs and the event feed once it has ended. Admins can read everything at any time.
+
+fn can_read(viewer: Option<Uuid>, contest: &Contest, secret: bool) -> bool {
+    if viewer.map(is_admin).unwrap_or(false) {
+        return true;
+    }
+    contest.is_public && contest.start_at <= Utc::now() && (!secret || contest.is_ended())
+}
+
+fn viewer_of(user: Option<Identity>) -> Option<Uuid> {
+    user.map(|user| user.id().unwrap().parse::<Uuid>().unwrap())
+}
+
+fn not_found() -> HttpResponse {
+    HttpResponse::NotFound().json(ResponseBlock {
+        status: false,
+        body: ContestError::ContestNotFound,
+    })
+}
+
+fn error(err: ContestError) -> HttpResponse {
+    match err {
+        ContestError::PermissionDenied => HttpResponse::Forbidden().json(ResponseBlock {
+            status: false,
+            body: err,
+        }),
+        ContestError::ContestNotFound => not_found(),
+        _ => HttpResponse::InternalServerError().json(ResponseBlock {
+            status: false,
+            body: err,
+        }),
+    }
+}
+
+fn find_by_id(items: Vec<Value>, id: &str) -> HttpResponse {
+    match items.into_iter().find(|x| x["id"] == id) {
+        Some(item) => HttpResponse::Ok().json(item),
+        None => not_found(),
+    }
+}
+
+async fn collection(
+    viewer: Option<Uuid>,
+    contest_pk: Uuid,
+    name: &str,
+) -> Result<Option<Vec<Value>>, ContestError> {
+    let contest = find_contest(contest_pk).map_err(|_| ContestError::ContestNotFound)?;
+    let secret = matches!(name, "submissions" | "judgements" | "runs");
+    if !can_read(viewer, &contest, secret) {
+        return Err(ContestError::PermissionDenied);
+    }
+    if name == "judgement-types" {
+        return Ok(Some(clics::judgement_types()));
+    }
+    let results = contest_results(&contest).await?;
+    let items = match name {
+        "problems" => clics::problems(&results.tasks),
+        "teams" => clics::teams(&results.users),
+        "submissions" => results
+            .submissions
+            .iter()
+            .map(|x| clics::submission(&results.contest, x))
+            .collect(),
+        "judgements" => results
+            .submissions
+            .iter()
+            .map(|x| clics::judgement(&results.contest, x))
+            .collect(),
+        "runs" => results
+            .submissions
+            .iter()
+            .flat_map(|x| clics::runs(&results.contest, x))
+            .collect(),
+        _ => return Ok(None),
+    };
+    Ok(Some(items))
+}
+
+#[get("")]
+pub async fn clics_contests(user: Option<Identity>) -> HttpResponse {
+    let viewer = viewer_of(user);
+    match all_contests() {
+        Ok(contests) => HttpResponse::Ok().json(
+            contests
+                .iter()
+                .filter(|contest| can_read(viewer, contest, false))
+                .map(|contest| clics::contest(&export_contest(contest)))
+                .collect::<Vec<Value>>(),
+        ),
+        Err(_) => error(ContestError::DatabaseError),
+    }
+}
+
+#[get("/{contest_pk}")]
+pub async fn clics_contest(user: Option<Identity>, path: web::Path<Uuid>) -> HttpResponse {
+    let viewer = viewer_of(user);
+    match find_contest(path.into_inner()) {
+        Ok(contest) if can_read(viewer, &contest, false) => {
+            HttpResponse::Ok().json(clics::contest(&export_contest(&contest)))
+        }
+        Ok(_) => error(ContestError::PermissionDenied),
+        Err(_) => not_found(),
+    }
+}
+
+#[get("/{contest_pk}/state")]
+pub async fn clics_state(user: Option<Identity>, path: web::Path<Uuid>) -> HttpResponse {
+    let viewer = viewer_of(user);
+    match find_contest(path.into_inner()) {
+        Ok(contest) if can_read(viewer, &contest, false) => {
+            HttpResponse::Ok().json(clics::state(&export_contest(&contest), Utc::now()))
+        }
+        Ok(_) => error(ContestError::PermissionDenied),
+        Err(_) => not_found(),
+    }
+}
+
+#[get("/{contest_pk}/scoreboard")]
+pub async fn clics_scoreboard(user: Option<Identity>, path: web::Path<Uuid>) -> HttpResponse {
+    let viewer = viewer_of(user);
+    let result = async {
+        let contest = find_contest(path.into_inner()).map_err(|_| ContestError::ContestNotFound)?;
+        if !can_read(viewer, &contest, false) {
+            return Err(ContestError::PermissionDenied);
+        }
+        Ok(clics::scoreboard(
+            &contest_results(&contest).await?,
+            Utc::now(),
+        ))
+    };
+    match result.await {
+        Ok(scoreboard) => HttpResponse::Ok().json(scoreboard),
+        Err(err) => error(err),
+    }
+}
+
+// The whole feed at once, since events are not kept as they happen
+#[get("/{contest_pk}/event-feed")]
+pub async fn clics_event_feed(user: Option<Identity>, path: web::Path<Uuid>) -> HttpResponse {
+    let viewer = viewer_of(user);
+    let result = async {
+        let contest = find_contest(path.into_inner()).map_err(|_| ContestError::ContestNotFound)?;
+        if !can_read(viewer, &contest, true) {
+            return Err(ContestError::PermissionDenied);
+        }
+        Ok(export(
+            &contest_results(&contest).await?,
+            ExportFormat::Clics,
+        ))
+    };
+    match result.await {
+        Ok(feed) => HttpResponse::Ok()
+            .content_type(ExportFormat::Clics.content_type())
+            .body(feed),
+        Err(err) => error(err),
+    }
+}
+
+#[get("/{contest_pk}/{collection}")]
+pub async fn clics_collection(
+    user: Option<Identity>,
+    path: web::Path<(Uuid, String)>,
+) -> HttpResponse {
+    let (contest_pk, name) = path.into_inner();
+    match collection(viewer_of(user), contest_pk, &name).await {
+        Ok(Some(items)) => HttpResponse::Ok().json(items),
+        Ok(None) => not_found(),
+        Err(err) => error(err),
+    }
+}
+
+#[get("/{contest_pk}/{collection}/{id}")]
+pub async fn clics_item(
+    user: Option<Identity>,
+    path: web::Path<(Uuid, String, String)>,
+) -> HttpResponse {
+    let (contest_pk, name, id) = path.into_inner();
+    match collection(viewer_of(user), contest_pk, &name).await {
+        Ok(Some(items)) => find_by_id(items, &id),
+        Ok(None) => not_found(),
+        Err(err) => error(err),
+    }
+}
@@ -0,0 +1,42 @@
+use async_graphql::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Error, Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Enum)]
+pub enum ContestError {
+    #[error("Ok")]
+    None,
+    #[error("You aren't logged in")]
+    NotLoggedIn,
+    #[error("Some database error occurred")]
+    DatabaseError,
+    #[error("You don't have permission")]
+    PermissionDenied,
+    #[error("Requested contest does not exist")]
+    ContestNotFound,
+    #[error("Requested contest is not running")]
+    ContestNotRunning,
+    #[error("Requested contest has not ended yet")]
+    ContestNotEnded,
+    #[error("You have already participated in the contest")]
+    AlreadyParticipated,
+    #[error("Requested contest does not have personal time windows")]
+    NotWindowed,
+    #[error("You have already started the contest")]
+    AlreadyStarted,
+    #[error("Requested task is not a part of the contest")]
+    TaskNotInContest,
+    #[error("Requested clarification does not exist")]
+    ClarificationNotFound,
+    #[error("Requested export format is not supported")]
+    UnknownFormat,
+    #[error("Message is empty")]
+    EmptyMessage,
+}
+
+impl ErrorExtensions for ContestError {
+    fn extend(&self) -> FieldError {
+        self.extend_with(|err, e| match err {
+            _ => {}
+        })
+    }
+}
@@ -0,0 +1,620 @@
+pub mod clics;
+pub mod errors;
+
+use actix_identity::Identity;
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use actix_web::{get, web, HttpResponse};
+use chrono::prelude::*;
+use chrono::Duration;
+use std::collections::HashMap;
+use std::sync::RwLock;
+use uuid::Uuid;
+
+use super::ResponseBlock;
+use crate::api::judge::{is_admin, judge_states, load_task_of};
+use crate::contests::export::*;
+use crate::contests::standings::*;
+use crate::contests::*;
+use crate::db::accounts::find_user;
+use crate::db::clarifications::*;
+use crate::db::contests::*;
+use crate::db::judge::*;
+use crate::db::languages::find_language;
+use crate::db::models::{self, Clarification, Contest, VirtualParticipation};
+use crate::db::tasks::find_task;
+use crate::judge::api::{Submission, SubmissionState, TestCase, TestState};
+use crate::APPDATA;
+
+pub use errors::*;
+
+// Jury members are admins for now
+pub fn is_jury(user_pk: Uuid, _contest: &Contest) -> bool {
+    is_admin(user_pk)
+}
+
+pub fn can_participate(user_pk: Uuid, contest: &Contest) -> Result<bool, ContestError> {
+    if contest.is_public || is_jury(user_pk, contest) {
+        Ok(true)
+    } else {
+        is_contest_participant(contest.pk, user_pk).map_err(|_| ContestError::DatabaseError)
+    }
+}
+
+fn non_empty(text: String) -> Result<String, ContestError> {
+    if text.trim().is_empty() {
+        Err(ContestError::EmptyMessage)
+    } else {
+        Ok(text)
+    }
+}
+
+fn publish(clarification: &Clarification) {
+    APPDATA
+        .clarification_addr
+        .do_send(ClarificationMessage::Publish(clarification.clone()));
+}
+
+// Participants ask only while the contest is running
+pub fn ask_clarification(
+    user_pk: Uuid,
+    contest_pk: Uuid,
+    task_pk: Option<Uuid>,
+    question: String,
+) -> Result<Clarification, ContestError> {
+    let contest = find_contest(contest_pk).map_err(|_| ContestError::ContestNotFound)?;
+    if !can_participate(user_pk, &contest)? {
+        return Err(ContestError::PermissionDenied);
+    }
+    if !contest.is_running() {
+        return Err(ContestError::ContestNotRunning);
+    }
+    if let Some(task_pk) = task_pk {
+        let task_pks = contest_task_pks(contest_pk).map_err(|_| ContestError::DatabaseError)?;
+        if !task_pks.contains(&task_pk) {
+            return Err(ContestError::TaskNotInContest);
+        }
+    }
+    let clarification = new_clarification(Clarification {
+        pk: Uuid::new_v4(),
+        contest_pk,
+        task_pk,
+        user_pk,
+        question: Some(non_empty(question)?),
+        answer: None,
+        answered_by: None,
+        is_public: false,
+        asked_at: Utc::now(),
+        answered_at: None,
+    })
+    .map_err(|_| ContestError::DatabaseError)?;
+    publish(&clarification);
+    Ok(clarification)
+}
+
+// A broadcast answer is shown to every participant of the contest
+pub fn answer(
+    user_pk: Uuid,
+    clarification_pk: Uuid,
+    text: String,
+    broadcast: bool,
+) -> Result<Clarification, ContestError> {
+    let clarification =
+        find_clarification(clarification_pk).map_err(|_| ContestError::ClarificationNotFound)?;
+    let contest =
+        find_contest(clarification.contest_pk).map_err(|_| ContestError::ContestNotFound)?;
+    if !is_jury(user_pk, &contest) {
+        return Err(ContestError::PermissionDenied);
+    }
+    let clarification =
+        answer_clarification(clarification_pk, non_empty(text)?, user_pk, broadcast)
+            .map_err(|_| ContestError::DatabaseError)?;
+    publish(&clarification);
+    Ok(clarification)
+}
+
+pub fn announce(
+    user_pk: Uuid,
+    contest_pk: Uuid,
+    task_pk: Option<Uuid>,
+    text: String,
+) -> Result<Clarification, ContestError> {
+    let contest = find_contest(contest_pk).map_err(|_| ContestError::ContestNotFound)?;
+    if !is_jury(user_pk, &contest) {
+        return Err(ContestError::PermissionDenied);
+    }
+    let now = Utc::now();
+    let clarification = new_clarification(Clarification {
+        pk: Uuid::new_v4(),
+        contest_pk,
+        task_pk,
+        user_pk,
+        question: None,
+        answer: Some(non_empty(text)?),
+        answered_by: Some(user_pk),
+        is_public: true,
+        asked_at: now,
+        answered_at: Some(now),
+    })
+    .map_err(|_| ContestError::DatabaseError)?;
+    publish(&clarification);
+    Ok(clarification)
+}
+
+// Jury members see every question, participants public ones and their own
+pub fn list_clarifications(
+    user_pk: Uuid,
+    contest_pk: Uuid,
+) -> Result<Vec<Clarification>, ContestError> {
+    let contest = find_contest(contest_pk).map_err(|_| ContestError::ContestNotFound)?;
+    if is_jury(user_pk, &contest) {
+        contest_clarifications(contest_pk, None).map_err(|_| ContestError::DatabaseError)
+    } else if can_participate(user_pk, &contest)? {
+        contest_clarifications(contest_pk, Some(user_pk)).map_err(|_| ContestError::DatabaseError)
+    } else {
+        Err(ContestError::PermissionDenied)
+    }
+}
+
+pub async fn subscribe_clarifications(
+    user_pk: Uuid,
+    contest_pk: Uuid,
+) -> Result<ClarificationStream, ContestError> {
+    let contest = find_contest(contest_pk).map_err(|_| ContestError::ContestNotFound)?;
+    let jury = is_jury(user_pk, &contest);
+    if !can_participate(user_pk, &contest)? {
+        return Err(ContestError::PermissionDenied);
+    }
+    if !jury && !contest.is_running() {
+        return Err(ContestError::ContestNotRunning);
+    }
+    let subscriber = Subscriber {
+        user_pk,
+        is_jury: jury,
+    };
+    APPDATA
+        .clarification_addr
+        .send(ClarificationSubscribeMessage::Subscribe(
+            contest_pk, subscriber,
+        ))
+        .await
+        .map_err(|_| ContestError::DatabaseError)
+}
+
+// In windowed contests participants start whenever they like during the contest
+pub fn start_contest(
+    user_pk: Uuid,
+    contest_pk: Uuid,
+) -> Result<(DateTime<Utc>, DateTime<Utc>), ContestError> {
+    let contest = find_contest(contest_pk).map_err(|_| ContestError::ContestNotFound)?;
+    if !contest.is_windowed() {
+        return Err(ContestError::NotWindowed);
+    }
+    if !can_participate(user_pk, &contest)? {
+        return Err(ContestError::PermissionDenied);
+    }
+    if !contest.is_running() {
+        return Err(ContestError::ContestNotRunning);
+    }
+    let now = Utc::now();
+    match save_personal_start(contest_pk, user_pk, now) {
+        Ok(0) => Err(ContestError::AlreadyStarted),
+        Ok(_) => Ok(contest.personal_window(now)),
+        Err(_) => Err(ContestError::DatabaseError),
+    }
+}
+
+// (start, end) of the official participation, None until the user starts a windowed contest
+pub fn participant_window(
+    contest: &Contest,
+    user_pk: Uuid,
+) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    if contest.is_windowed() {
+        let started_at = find_personal_start(contest.pk, user_pk).ok()??;
+        Some(contest.personal_window(started_at))
+    } else {
+        Some((contest.start_at, contest.end_at))
+    }
+}
+
+// in seconds, None outside of the user's window
+pub fn remaining_time(user_pk: Uuid, contest_pk: Uuid) -> Result<Option<i64>, ContestError> {
+    let contest = find_contest(contest_pk).map_err(|_| ContestError::ContestNotFound)?;
+    let now = Utc::now();
+    Ok(participant_window(&contest, user_pk)
+        .filter(|(start, end)| *start <= now && now < *end)
+        .map(|(_, end)| (end - now).num_seconds()))
+}
+
+// Replays are allowed once the contest is over, for users who did not take part in it,
+// neither by submitting nor by starting their own window
+pub fn start_virtual(
+    user_pk: Uuid,
+    contest_pk: Uuid,
+) -> Result<VirtualParticipation, ContestError> {
+    let contest = find_contest(contest_pk).map_err(|_| ContestError::ContestNotFound)?;
+    if !can_participate(user_pk, &contest)? {
+        return Err(ContestError::PermissionDenied);
+    }
+    if !contest.is_ended() {
+        return Err(ContestError::ContestNotEnded);
+    }
+    if find_virtual_participation(contest_pk, user_pk).is_ok() {
+        return Err(ContestError::AlreadyParticipated);
+    }
+    let started =
+        find_personal_start(contest_pk, user_pk).map_err(|_| ContestError::DatabaseError)?;
+    if started.is_some() {
+        return Err(ContestError::AlreadyParticipated);
+    }
+    let query = SubmissionQuery {
+        user_pk: Some(user_pk),
+        task_pks: Some(contest_task_pks(contest_pk).map_err(|_| ContestError::DatabaseError)?),
+        issued_after: Some(contest.start_at),
+        issued_before: Some(contest.end_at),
+        ..Default::default()
+    };
+    let submitted = search_submissions(query).map_err(|_| ContestError::DatabaseError)?;
+    if !submitted.is_empty() {
+        return Err(ContestError::AlreadyParticipated);
+    }
+    new_virtual_participation(VirtualParticipation {
+        pk: Uuid::new_v4(),
+        user_pk,
+        contest_pk,
+        start_at: Utc::now(),
+    })
+    .map_err(|_| ContestError::DatabaseError)
+}
+
+// (start, end) of the user's running replay
+pub fn virtual_window(contest: &Contest, user_pk: Uuid) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    let participation = find_virtual_participation(contest.pk, user_pk).ok()?;
+    Some((
+        participation.start_at,
+        participation.start_at + contest.duration(),
+    ))
+}
+
+pub struct ContestSubmission {
+    pub submission: models::Submission,
+    pub state: Submission,
+    pub kind: ParticipationKind,
+    pub elapsed: i64, // in seconds since the start of the window
+}
+
+// Every judged submission on the tasks of the contest, classified by the window it was
+// made in, oldest first. Submissions still being judged are left out.
+pub async fn contest_submissions(
+    contest: &Contest,
+) -> Result<Vec<ContestSubmission>, ContestError> {
+    let task_pks = contest_task_pks(contest.pk).map_err(|_| ContestError::DatabaseError)?;
+    let virtuals: HashMap<Uuid, DateTime<Utc>> = virtual_participations_of(contest.pk)
+        .map_err(|_| ContestError::DatabaseError)?
+        .into_iter()
+        .map(|x| (x.user_pk, x.start_at))
+        .collect();
+    let personal: HashMap<Uuid, DateTime<Utc>> = if contest.is_windowed() {
+        personal_starts(contest.pk)
+            .map_err(|_| ContestError::DatabaseError)?
+            .into_iter()
+            .collect()
+    } else {
+        HashMap::new()
+    };
+    let query = SubmissionQuery {
+        task_pks: Some(task_pks),
+        issued_after: Some(contest.start_at),
+        ..Default::default()
+    };
+    let mut participants: HashMap<Uuid, bool> = HashMap::new();
+    let mut candidates = vec![];
+    let mut submissions = search_submissions(query).map_err(|_| ContestError::DatabaseError)?;
+    submissions.reverse();
+    for submission in submissions {
+        let at = submission.issued_at;
+        let virtual_start = virtuals
+            .get(&submission.user_pk)
+            .filter(|start| **start <= at && at < **start + contest.duration());
+        let (kind, start) = if at < contest.end_at {
+            let participant = match participants.get(&submission.user_pk) {
+                Some(x) => *x,
+                None => {
+                    let x = can_participate(submission.user_pk, contest)?;
+                    participants.insert(submission.user_pk, x);
+                    x
+                }
+            };
+            let window = if contest.is_windowed() {
+                personal
+                    .get(&submission.user_pk)
+                    .map(|started_at| contest.personal_window(*started_at))
+            } else {
+                Some((contest.start_at, contest.end_at))
+            };
+            match window {
+                Some((start, end)) if participant && start <= at && at < end => {
+                    (ParticipationKind::Official, start)
+                }
+                _ => continue,
+            }
+        } else if let Some(start) = virtual_start {
+            (ParticipationKind::Virtual, *start)
+        } else {
+            (ParticipationKind::Upsolving, contest.end_at)
+        };
+        candidates.push((submission, kind, (at - start).num_seconds()));
+    }
+    let mut states = judge_states(candidates.iter().map(|(x, _, _)| x.pk).collect()).await;
+    Ok(candidates
+        .into_iter()
+        .filter_map(|(submission, kind, elapsed)| {
+            let state = states.remove(&submission.pk).filter(|state| {
+                state.submission_state == SubmissionState::Success
+                    || state.submission_state == SubmissionState::CompileError
+            })?;
+            Some(ContestSubmission {
+                elapsed,
+                submission,
+                state,
+                kind,
+            })
+        })
+        .collect())
+}
+
+pub async fn contest_attempts(contest: &Contest) -> Result<Vec<Attempt>, ContestError> {
+    Ok(contest_submissions(contest)
+        .await?
+        .into_iter()
+        .filter(|x| x.state.submission_state == SubmissionState::Success)
+        .map(|x| Attempt {
+            user_pk: x.submission.user_pk,
+            kind: x.kind,
+            task_pk: x.submission.task_pk,
+            elapsed: x.elapsed,
+            score: x.state.score,
+            solved: x.state.is_solved(),
+        })
+        .collect())
+}
+
+// The virtual scoreboard shows official rows next to replays, frozen at the elapsed time
+// of the viewer's own running replay.
+pub async fn standings(
+    viewer: Option<Uuid>,
+    contest_pk: Uuid,
+    kind: ParticipationKind,
+) -> Result<Vec<StandingRow>, ContestError> {
+    let contest = find_contest(contest_pk).map_err(|_| ContestError::ContestNotFound)?;
+    let allowed = match viewer {
+        Some(viewer) => can_participate(viewer, &contest)?,
+        None => contest.is_public,
+    };
+    if !allowed {
+        return Err(ContestError::PermissionDenied);
+    }
+    let task_pks = contest_task_pks(contest_pk).map_err(|_| ContestError::DatabaseError)?;
+    let attempts = contest_attempts(&contest).await?;
+    let (attempts, cutoff): (Vec<Attempt>, Option<i64>) = match kind {
+        ParticipationKind::Official | ParticipationKind::Upsolving => (
+            attempts.into_iter().filter(|x| x.kind == kind).collect(),
+            None,
+        ),
+        ParticipationKind::Virtual => {
+            let now = Utc::now();
+            let cutoff = viewer
+                .and_then(|viewer| virtual_window(&contest, viewer))
+                .filter(|(start, end)| *start <= now && now < *end)
+                .map(|(start, _)| (now - start).num_seconds());
+            (
+                attempts
+                    .into_iter()
+                    .filter(|x| x.kind != ParticipationKind::Upsolving)
+                    .collect(),
+                cutoff,
+            )
+        }
+    };
+    Ok(build_standings(&task_pks, attempts, cutoff))
+}
+
+// CLICS judgement type of a judged submission. A submission failing several tests is
+// judged by its most severe failure: TLE, then MLE, then RTE, otherwise WA.
+pub fn judgement_type(state: &Submission) -> &'static str {
+    if state.submission_state == SubmissionState::CompileError {
+        return "CE";
+    }
+    if state.is_solved() {
+        return "AC";
+    }
+    let statuses: Vec<TestState> = state.cases.values().map(|x| x.status).collect();
+    if statuses.contains(&TestState::TimeLimitExceed) {
+        "TLE"
+    } else if statuses.contains(&TestState::MemLimitExceed) {
+        "MLE"
+    } else if statuses.contains(&TestState::RuntimeErr)
+        || statuses.contains(&TestState::DiedOnSignal)
+    {
+        "RTE"
+    } else {
+        "WA"
+    }
+}
+
+pub fn export_contest(contest: &Contest) -> ExportContest {
+    ExportContest {
+        pk: contest.pk,
+        name: contest.name.clone(),
+        start_at: contest.start_at,
+        end_at: contest.end_at,
+        duration: contest.duration().num_seconds(),
+    }
+}
+
+fn test_runs(state: &Submission) -> Vec<ExportRun> {
+    let mut cases: Vec<&TestCase> = state.cases.values().collect();
+    cases.sort_by(|a, b| a.name.cmp(&b.name));
+    cases
+        .into_iter()
+        .filter_map(|case| {
+            let judgement = match case.status {
+                TestState::Ready | TestState::Pending => return None,
+                TestState::Success => "AC",
+                TestState::TimeLimitExceed => "TLE",
+                TestState::MemLimitExceed => "MLE",
+                TestState::RuntimeErr | TestState::DiedOnSignal => "RTE",
+                _ => "WA",
+            };
+            Some(ExportRun {
+                judgement: judgement.to_string(),
+                run_time: case.time.unwrap_or(0) as f64 / 1000.0,
+            })
+        })
+        .collect()
+}
+
+// CLICS clients poll every collection on its own, so results are rebuilt at most once
+// per RESULTS_TTL seconds for each contest.
+const RESULTS_TTL: i64 = 10;
+
+lazy_static! {
+    static ref RESULTS: RwLock<HashMap<Uuid, (DateTime<Utc>, ContestResults)>> =
+        RwLock::new(HashMap::new());
+}
+
+// Final official standings and submissions of the contest
+pub async fn contest_results(contest: &Contest) -> Result<ContestResults, ContestError> {
+    let now = Utc::now();
+    let ttl = Duration::seconds(RESULTS_TTL);
+    let cached = RESULTS
+        .read()
+        .unwrap()
+        .get(&contest.pk)
+        .filter(|(built_at, _)| now - *built_at < ttl)
+        .map(|(_, results)| results.clone());
+    if let Some(results) = cached {
+        return Ok(results);
+    }
+    let results = build_contest_results(contest).await?;
+    let mut cache = RESULTS.write().unwrap();
+    cache.retain(|_, (built_at, _)| now - *built_at < ttl);
+    cache.insert(contest.pk, (now, results.clone()));
+    Ok(results)
+}
+
+async fn build_contest_results(contest: &Contest) -> Result<ContestResults, ContestError> {
+    let task_pks = contest_task_pks(contest.pk).map_err(|_| ContestError::DatabaseError)?;
+    let mut tasks = vec![];
+    for (idx, task_pk) in task_pks.iter().enumerate() {
+        let task = find_task(*task_pk).map_err(|_| ContestError::DatabaseError)?;
+        let max_score = match load_task_of(*task_pk).await {
+            Ok(loader) => loader
+                .subtasks
+                .subtasks
+                .values()
+                .map(|(subtask, _)| subtask.score)
+                .sum(),
+            Err(_) => 0.0,
+        };
+        tasks.push(ExportTask {
+            pk: task.pk,
+            label: task_label(idx),
+            name: task.name,
+            max_score,
+        });
+    }
+    let items: Vec<ContestSubmission> = contest_submissions(contest)
+        .await?
+        .into_iter()
+        .filter(|x| x.kind == ParticipationKind::Official)
+        .collect();
+    let mut user_pks = contest_user_pks(contest.pk).map_err(|_| ContestError::DatabaseError)?;
+    for x in items.iter() {
+        if !user_pks.contains(&x.submission.user_pk) {
+            user_pks.push(x.submission.user_pk);
+        }
+    }
+    let mut users: Vec<ExportUser> = vec![];
+    for user_pk in user_pks {
+        let user = find_user(user_pk).map_err(|_| ContestError::DatabaseError)?;
+        users.push(ExportUser {
+            pk: user_pk,
+            id: user.id,
+            name: format!("{} {}", user.first_name, user.last_name),
+        });
+    }
+    let mut languages: HashMap<Uuid, String> = HashMap::new();
+    let mut submissions = vec![];
+    let mut attempts = vec![];
+    for x in items.iter() {
+        let user_pk = x.submission.user_pk;
+        let language = languages
+            .entry(x.submission.lang_uuid)
+            .or_insert_with(|| {
+                find_language(x.submission.lang_uuid)
+                    .map(|lang| lang.name)
+                    .unwrap_or_else(|_| String::from("output"))
+            })
+            .clone();
+        submissions.push(ExportSubmission {
+            pk: x.submission.pk,
+            user_pk,
+            task_pk: x.submission.task_pk,
+            language,
+            issued_at: x.submission.issued_at,
+            elapsed: x.elapsed,
+            score: x.state.score,
+            judgement: judgement_type(&x.state).to_string(),
+            runs: test_runs(&x.state),
+        });
+        if x.state.submission_state == SubmissionState::Success {
+            attempts.push(Attempt {
+                user_pk,
+                kind: x.kind,
+                task_pk: x.submission.task_pk,
+                elapsed: x.elapsed,
+                score: x.state.score,
+                solved: x.state.is_solved(),
+            });
+        }
+    }
+    Ok(ContestResults {
+        contest: export_contest(contest),
+        standings: build_standings(&task_pks, attempts, None),
+        tasks,
+        users,
+        submissions,
+    })
+}
+
+#[get("/{contest_pk}/export/{format}")]
+pub async fn export_results(
+    user: Option<Identity>,
+    path: web::Path<(Uuid, String)>,
+) -> HttpResponse {
+    let viewer = user.map(|user| user.id().unwrap().parse::<Uuid>().unwrap());
+    let (contest_pk, format) = path.into_inner();
+    let result = async {
+        if !viewer.map(is_admin).unwrap_or(false) {
+            return Err(ContestError::PermissionDenied);
+        }
+        let format = ExportFormat::from_name(&format).ok_or(ContestError::UnknownFormat)?;
+        let contest = find_contest(contest_pk).map_err(|_| ContestError::ContestNotFound)?;
+        Ok((format, export(&contest_results(&contest).await?, format)))
+    };
+    match result.await {
+        Ok((format, body)) => HttpResponse::Ok()
+            .content_type(format.content_type())
+            .insert_header(ContentDisposition {
+                disposition: DispositionType::Attachment,
+                parameters: vec![DispositionParam::Filename(format.file_name().to_string())],
+            })
+            .body(body),
+        Err(err) => HttpResponse::Ok()
+            .content_type("application/json")
+            .json(ResponseBlock {
+                status: false,
+                body: err,
+            }),
+    }
+}
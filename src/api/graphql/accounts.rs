@@ -1,8 +1,11 @@
+use crate::api::accounts::api_tokens::TokenScope;
 use crate::api::accounts::*;
+use crate::db::models::{ApiToken, AuditLog, ExternalIdentity, User};
 use actix_identity::Identity;
 use actix_web::{get, route, web};
 use async_graphql::*;
 use async_graphql_actix_web::{GraphQLRequest, GraphQLResponse};
+use chrono::prelude::*;
 use chrono_tz::Tz;
 use uuid::Uuid;
 
@@ -17,6 +20,15 @@ pub struct RegisterRequest {
     pub preferred_language: Option<Uuid>,
 }
 
+#[derive(Clone, Debug, InputObject)]
+pub struct ProfileRequest {
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    pub email: Option<String>,
+    pub timezone: Option<Tz>,
+    pub preferred_language: Option<Uuid>,
+}
+
 pub struct UserGql {
     pub pk: Uuid,
     pub id: String,
@@ -26,6 +38,27 @@ pub struct UserGql {
     pub last_name: String,
     pub email: String,
     pub preferred_language: Uuid,
+    pub email_verified: bool,
+    pub is_banned: bool,
+    pub totp_enabled: bool,
+}
+
+impl From<User> for UserGql {
+    fn from(user: User) -> Self {
+        Self {
+            pk: user.pk,
+            id: user.id.clone(),
+            permission: user.permission.try_into().unwrap_or(AccountPerm::User),
+            timezone: user.timezone().unwrap_or(Tz::UTC),
+            first_name: user.first_name,
+            last_name: user.last_name,
+            email: user.email,
+            preferred_language: user.preferred_language,
+            email_verified: user.email_verified,
+            is_banned: user.is_banned,
+            totp_enabled: user.totp_enabled,
+        }
+    }
 }
 
 #[Object]
@@ -61,4 +94,149 @@ impl UserGql {
     async fn preferred_language(&self) -> Uuid {
         self.preferred_language
     }
+
+    async fn email_verified(&self) -> bool {
+        self.email_verified
+    }
+
+    async fn is_banned(&self) -> bool {
+        self.is_banned
+    }
+
+    async fn totp_enabled(&self) -> bool {
+        self.totp_enabled
+    }
+}
+
+pub struct UserListGql {
+    pub total: i64,
+    pub users: Vec<UserGql>,
+}
+
+#[Object]
+impl UserListGql {
+    // number of all matched users, regardless of the page
+    async fn total(&self) -> i64 {
+        self.total
+    }
+
+    async fn users(&self) -> &Vec<UserGql> {
+        &self.users
+    }
+}
+
+pub struct AuditLogGql {
+    pub log: AuditLog,
+}
+
+#[Object]
+impl AuditLogGql {
+    async fn pk(&self) -> Uuid {
+        self.log.pk
+    }
+
+    async fn admin_pk(&self) -> Uuid {
+        self.log.admin_pk
+    }
+
+    async fn action(&self) -> String {
+        self.log.action.clone()
+    }
+
+    async fn target_pk(&self) -> Option<Uuid> {
+        self.log.target_pk
+    }
+
+    async fn detail(&self) -> Option<String> {
+        self.log.detail.clone()
+    }
+
+    async fn created_at(&self) -> DateTime<Utc> {
+        self.log.created_at
+    }
+
+    async fn impersonator_pk(&self) -> Option<Uuid> {
+        self.log.impersonator_pk
+    }
+}
+
+pub struct ExternalIdentityGql {
+    pub identity: ExternalIdentity,
+}
+
+#[Object]
+impl ExternalIdentityGql {
+    async fn provider(&self) -> String {
+        self.identity.provider.clone()
+    }
+
+    async fn linked_at(&self) -> DateTime<Utc> {
+        self.identity.linked_at
+    }
+}
+
+pub struct ApiTokenGql {
+    pub token: ApiToken,
+}
+
+#[Object]
+impl ApiTokenGql {
+    async fn pk(&self) -> Uuid {
+        self.token.pk
+    }
+
+    async fn name(&self) -> String {
+        self.token.name.clone()
+    }
+
+    async fn scopes(&self) -> Vec<TokenScope> {
+        TokenScope::from_bits(self.token.scopes)
+    }
+
+    async fn created_at(&self) -> DateTime<Utc> {
+        self.token.created_at
+    }
+
+    async fn expires_at(&self) -> Option<DateTime<Utc>> {
+        self.token.expires_at
+    }
+
+    async fn last_used_at(&self) -> Option<DateTime<Utc>> {
+        self.token.last_used_at
+    }
+}
+
+pub struct NewApiTokenGql {
+    pub secret: String,
+    pub token: ApiTokenGql,
+}
+
+#[Object]
+impl NewApiTokenGql {
+    // to be sent as `Authorization: Bearer <secret>`
+    async fn secret(&self) -> String {
+        self.secret.clone()
+    }
+
+    async fn token(&self) -> &ApiTokenGql {
+        &self.token
+    }
+}
+
+pub struct TotpEnrollmentGql {
+    pub secret: String,
+    pub uri: String,
+}
+
+#[Object]
+impl TotpEnrollmentGql {
+    // base32, for entering by hand
+    async fn secret(&self) -> String {
+        self.secret.clone()
+    }
+
+    // otpauth URI, for QR codes
+    async fn uri(&self) -> String {
+        self.uri.clone()
+    }
 }
@@ -0,0 +1,127 @@
+use crate::contests::standings::*;
+use crate::db::models::Clarification;
+use async_graphql::*;
+use chrono::prelude::*;
+use uuid::Uuid;
+
+pub struct ClarificationGql {
+    pub pk: Uuid,
+    pub contest_pk: Uuid,
+    pub task_pk: Option<Uuid>,
+    pub asked_by: Option<Uuid>, // hidden from other participants
+    pub question: Option<String>,
+    pub answer: Option<String>,
+    pub is_public: bool,
+    pub asked_at: DateTime<Utc>,
+    pub answered_at: Option<DateTime<Utc>>,
+}
+
+impl ClarificationGql {
+    pub fn new(x: Clarification, viewer: Uuid, is_jury: bool) -> Self {
+        Self {
+            pk: x.pk,
+            contest_pk: x.contest_pk,
+            task_pk: x.task_pk,
+            asked_by: Some(x.user_pk).filter(|pk| is_jury || *pk == viewer),
+            question: x.question,
+            answer: x.answer,
+            is_public: x.is_public,
+            asked_at: x.asked_at,
+            answered_at: x.answered_at,
+        }
+    }
+}
+
+#[Object]
+impl ClarificationGql {
+    async fn pk(&self) -> Uuid {
+        self.pk
+    }
+
+    async fn contest_pk(&self) -> Uuid {
+        self.contest_pk
+    }
+
+    async fn task_pk(&self) -> Option<Uuid> {
+        self.task_pk
+    }
+
+    async fn asked_by(&self) -> Option<Uuid> {
+        self.asked_by
+    }
+
+    // None for announcements
+    async fn question(&self) -> Option<String> {
+        self.question.clone()
+    }
+
+    async fn answer(&self) -> Option<String> {
+        self.answer.clone()
+    }
+
+    async fn is_public(&self) -> bool {
+        self.is_public
+    }
+
+    async fn is_announcement(&self) -> bool {
+        self.question.is_none()
+    }
+
+    async fn asked_at(&self) -> DateTime<Utc> {
+        self.asked_at
+    }
+
+    async fn answered_at(&self) -> Option<DateTime<Utc>> {
+        self.answered_at
+    }
+}
+
+#[derive(Clone, Debug, SimpleObject)]
+pub struct TaskResultGql {
+    pub task_pk: Uuid,
+    pub score: f64,
+    pub attempts: usize,
+    pub solved_at: Option<i64>, // in seconds
+}
+
+impl From<TaskResult> for TaskResultGql {
+    fn from(x: TaskResult) -> Self {
+        Self {
+            task_pk: x.task_pk,
+            score: x.score,
+            attempts: x.attempts,
+            solved_at: x.solved_at,
+        }
+    }
+}
+
+#[derive(Clone, Debug, SimpleObject)]
+pub struct StandingRowGql {
+    pub rank: usize,
+    pub user_pk: Uuid,
+    pub kind: ParticipationKind,
+    pub total: f64,
+    pub solved: usize,
+    pub penalty: i64, // in minutes
+    pub tasks: Vec<TaskResultGql>,
+}
+
+impl From<StandingRow> for StandingRowGql {
+    fn from(x: StandingRow) -> Self {
+        Self {
+            rank: x.rank,
+            user_pk: x.user_pk,
+            kind: x.kind,
+            total: x.total,
+            solved: x.solved,
+            penalty: x.penalty,
+            tasks: x.tasks.into_iter().map(TaskResultGql::from).collect(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, SimpleObject)]
+pub struct TimeWindowGql {
+    pub start_at: DateTime<Utc>,
+    pub end_at: DateTime<Utc>,
+}
@@ -0,0 +1,58 @@
+use crate::db::models::Language;
+use async_graphql::*;
+use uuid::Uuid;
+
+pub struct LanguageGql {
+    pub uuid: Uuid,
+    pub name: String,
+    pub version: String,
+    pub compile_command: Option<String>,
+    pub run_command: String,
+    pub file_extension: String,
+    pub is_enabled: bool,
+}
+
+impl From<Language> for LanguageGql {
+    fn from(lang: Language) -> Self {
+        Self {
+            uuid: lang.pk,
+            name: lang.name,
+            version: lang.version,
+            compile_command: lang.compile_command,
+            run_command: lang.run_command,
+            file_extension: lang.file_extension,
+            is_enabled: lang.is_enabled,
+        }
+    }
+}
+
+#[Object]
+impl LanguageGql {
+    async fn uuid(&self) -> Uuid {
+        self.uuid
+    }
+
+    async fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    async fn version(&self) -> String {
+        self.version.clone()
+    }
+
+    async fn compile_command(&self) -> Option<String> {
+        self.compile_command.clone()
+    }
+
+    async fn run_command(&self) -> String {
+        self.run_command.clone()
+    }
+
+    async fn file_extension(&self) -> String {
+        self.file_extension.clone()
+    }
+
+    async fn is_enabled(&self) -> bool {
+        self.is_enabled
+    }
+}
@@ -1,22 +1,45 @@
 pub mod accounts;
+pub mod contests;
+pub mod languages;
+pub mod plagiarism;
+pub mod submissions;
+pub mod tasks;
 
+use crate::api::accounts::api_tokens::{self, TokenAuth, TokenScope};
+use crate::api::accounts::profile::{self, ProfileUpdate};
 use crate::api::accounts::*;
+use crate::api::accounts::{admin, oidc, password, two_factor};
+use crate::api::contests::{self as contest_api, ContestError};
+use crate::api::judge::{self as judge_api, JudgeError};
 use crate::api::ResponseBlock;
+use crate::contests::standings::ParticipationKind;
 use crate::db::accounts::*;
-use crate::db::models::NewUser;
-use crate::judge::{api::*, JudgeService, SubscribeMessage};
+use crate::db::contests::find_contest;
+use crate::db::judge::SubmissionQuery;
+use crate::db::languages::*;
+use crate::db::plagiarism::find_similarities;
+use crate::judge::api::*;
+use crate::middlewares::rate_limit::client_addr;
+use crate::plagiarism::winnowing::*;
+use crate::plagiarism::PlagiarismMessage;
 use crate::{APPDATA, CONFIG};
 
 use accounts::*;
+use contests::*;
+use languages::*;
+use plagiarism::*;
+use submissions::*;
+use tasks::*;
 
 use actix_identity::Identity;
-use actix_web::{web, HttpRequest, HttpResponse};
+use actix_session::SessionExt;
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse};
 use async_graphql::futures_util::FutureExt;
 use async_graphql::http::{playground_source, GraphQLPlaygroundConfig};
 use async_graphql::*;
 use async_graphql_actix_web::{GraphQLRequest, GraphQLResponse, GraphQLSubscription};
+use chrono::{DateTime, Utc};
 use futures_util::{Stream, StreamExt};
-use sha3::{Digest, Sha3_256};
 use uuid::Uuid;
 
 pub struct Mutation;
@@ -37,15 +60,37 @@ pub async fn gql_playground() -> HttpResponse {
     }
 }
 
+// Address of the client, as seen through the trusted proxies
+pub struct ClientAddr(pub String);
+
+// Admin impersonating the logged in user
+pub struct Impersonator(pub Uuid);
+
 pub async fn gql_endpoint(
     schema: web::Data<GqlSchema>,
     user: Option<Identity>,
+    req: HttpRequest,
     gql_req: GraphQLRequest,
 ) -> GraphQLResponse {
     let mut gql_req = gql_req.into_inner();
     if let Some(user) = user {
         gql_req = gql_req.data(user.id().unwrap());
     }
+    if let Some(addr) = client_addr(&req) {
+        gql_req = gql_req.data(ClientAddr(addr));
+    }
+    if let Ok(Some(admin_pk)) = req.get_session().get::<Uuid>(admin::IMPERSONATOR) {
+        gql_req = gql_req.data(Impersonator(admin_pk));
+    }
+    let auth = req.extensions().get::<TokenAuth>().copied();
+    if let Some(auth) = auth {
+        let scope = api_tokens::required_scope(&gql_req.query);
+        if !auth.allows(scope) {
+            let message = format!("The token needs the {:?} scope", scope);
+            return async_graphql::Response::from_errors(vec![ServerError::new(message, None)])
+                .into();
+        }
+    }
     schema.execute(gql_req).await.into()
 }
 
@@ -73,59 +118,869 @@ impl QueryRoot {
         if let Some(pk) = ctx.data_opt::<String>() {
             let pk = pk.parse::<Uuid>().unwrap();
             let from_db = find_user(pk).unwrap();
-            Ok(UserGql {
-                pk,
-                id: from_db.id.clone(),
-                permission: from_db.permission.try_into().unwrap(),
-                timezone: from_db.timezone().unwrap(),
-                first_name: from_db.first_name,
-                last_name: from_db.last_name,
-                email: from_db.email,
-                preferred_language: from_db.preferred_language,
-            })
+            Ok(UserGql::from(from_db))
         } else {
             Err(AccountError::NotLoggedIn)
         }
     }
+
+    // names of the identity providers to log in with
+    async fn oidc_providers(&self) -> Vec<String> {
+        CONFIG.oidc.iter().map(|x| x.name.clone()).collect()
+    }
+
+    async fn linked_identities<'ctx>(
+        &self,
+        ctx: &'ctx async_graphql::Context<'_>,
+    ) -> Result<Vec<ExternalIdentityGql>, AccountError> {
+        let user_pk = ctx
+            .data_opt::<String>()
+            .map(|pk| pk.parse::<Uuid>().unwrap())
+            .ok_or(AccountError::NotLoggedIn)?;
+        let identities =
+            external_identities_of(user_pk).map_err(|_| AccountError::DatabaseError)?;
+        Ok(identities
+            .into_iter()
+            .map(|identity| ExternalIdentityGql { identity })
+            .collect())
+    }
+
+    async fn api_tokens<'ctx>(
+        &self,
+        ctx: &'ctx async_graphql::Context<'_>,
+    ) -> Result<Vec<ApiTokenGql>, AccountError> {
+        let user_pk = ctx
+            .data_opt::<String>()
+            .map(|pk| pk.parse::<Uuid>().unwrap())
+            .ok_or(AccountError::NotLoggedIn)?;
+        let tokens = api_tokens_of(user_pk).map_err(|_| AccountError::DatabaseError)?;
+        Ok(tokens
+            .into_iter()
+            .map(|token| ApiTokenGql { token })
+            .collect())
+    }
+
+    async fn recovery_codes_left<'ctx>(
+        &self,
+        ctx: &'ctx async_graphql::Context<'_>,
+    ) -> Result<i64, AccountError> {
+        let user_pk = ctx
+            .data_opt::<String>()
+            .map(|pk| pk.parse::<Uuid>().unwrap())
+            .ok_or(AccountError::NotLoggedIn)?;
+        recovery_codes_left(user_pk).map_err(|_| AccountError::DatabaseError)
+    }
+
+    async fn users<'ctx>(
+        &self,
+        ctx: &'ctx async_graphql::Context<'_>,
+        keyword: Option<String>,
+        #[graphql(default = 0)] offset: i64,
+        #[graphql(default = 20)] limit: i64,
+    ) -> Result<UserListGql, AccountError> {
+        let viewer = ctx
+            .data_opt::<String>()
+            .map(|pk| pk.parse::<Uuid>().unwrap());
+        let (total, users) = admin::list_users(viewer, keyword, offset, limit)?;
+        Ok(UserListGql {
+            total,
+            users: users.into_iter().map(UserGql::from).collect(),
+        })
+    }
+
+    async fn audit_logs<'ctx>(
+        &self,
+        ctx: &'ctx async_graphql::Context<'_>,
+        target: Option<Uuid>,
+        #[graphql(default = 0)] offset: i64,
+        #[graphql(default = 20)] limit: i64,
+    ) -> Result<Vec<AuditLogGql>, AccountError> {
+        let viewer = ctx
+            .data_opt::<String>()
+            .map(|pk| pk.parse::<Uuid>().unwrap());
+        let logs = admin::list_audit_logs(viewer, target, offset, limit)?;
+        Ok(logs.into_iter().map(|log| AuditLogGql { log }).collect())
+    }
+
+    async fn languages(
+        &self,
+        #[graphql(default = true)] enabled_only: bool,
+    ) -> Result<Vec<LanguageGql>, JudgeError> {
+        all_languages(enabled_only)
+            .map(|langs| langs.into_iter().map(LanguageGql::from).collect())
+            .map_err(|_| JudgeError::DatabaseError)
+    }
+
+    async fn language(&self, uuid: Uuid) -> Result<LanguageGql, JudgeError> {
+        find_language(uuid)
+            .map(LanguageGql::from)
+            .map_err(|_| JudgeError::LanguageNotFound)
+    }
+
+    async fn task<'ctx>(
+        &self,
+        ctx: &'ctx async_graphql::Context<'_>,
+        pk: Uuid,
+    ) -> Result<TaskGql, JudgeError> {
+        let viewer = ctx
+            .data_opt::<String>()
+            .map(|pk| pk.parse::<Uuid>().unwrap());
+        let loader = judge_api::read_task(viewer, pk).await?;
+        Ok(TaskGql { pk, loader })
+    }
+
+    async fn task_languages(&self, task_pk: Uuid) -> Result<Vec<LanguageGql>, JudgeError> {
+        let task = judge_api::load_task_of(task_pk).await?;
+        let langs = all_languages(true).map_err(|_| JudgeError::DatabaseError)?;
+        Ok(langs
+            .into_iter()
+            .filter(|lang| task.accepts_language(&lang.pk))
+            .map(LanguageGql::from)
+            .collect())
+    }
+
+    async fn submissions<'ctx>(
+        &self,
+        ctx: &'ctx async_graphql::Context<'_>,
+        #[graphql(default)] filter: SubmissionFilter,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> Result<SubmissionConnection> {
+        let viewer = ctx
+            .data_opt::<String>()
+            .map(|pk| pk.parse::<Uuid>().unwrap());
+        let query = SubmissionQuery {
+            user_pk: filter.user_pk,
+            task_pks: filter.task_pk.map(|pk| vec![pk]),
+            lang_uuid: filter.lang_uuid,
+            issued_after: filter.issued_after,
+            issued_before: filter.issued_before,
+            ..Default::default()
+        };
+        paginate(viewer, query, filter, after, before, first, last).await
+    }
+
+    async fn source<'ctx>(
+        &self,
+        ctx: &'ctx async_graphql::Context<'_>,
+        judge_uuid: Uuid,
+    ) -> Result<SourceGql, JudgeError> {
+        let viewer = ctx
+            .data_opt::<String>()
+            .map(|pk| pk.parse::<Uuid>().unwrap());
+        let (submission, lang, code) = judge_api::read_source(viewer, judge_uuid).await?;
+        Ok(SourceGql {
+            pk: submission.pk,
+            language: lang.into(),
+            code,
+        })
+    }
+
+    async fn clarifications<'ctx>(
+        &self,
+        ctx: &'ctx async_graphql::Context<'_>,
+        contest_pk: Uuid,
+    ) -> Result<Vec<ClarificationGql>, ContestError> {
+        let viewer = ctx
+            .data_opt::<String>()
+            .map(|pk| pk.parse::<Uuid>().unwrap())
+            .ok_or(ContestError::NotLoggedIn)?;
+        let contest = find_contest(contest_pk).map_err(|_| ContestError::ContestNotFound)?;
+        let jury = contest_api::is_jury(viewer, &contest);
+        Ok(contest_api::list_clarifications(viewer, contest_pk)?
+            .into_iter()
+            .map(|x| ClarificationGql::new(x, viewer, jury))
+            .collect())
+    }
+
+    async fn standings<'ctx>(
+        &self,
+        ctx: &'ctx async_graphql::Context<'_>,
+        contest_pk: Uuid,
+        #[graphql(default_with = "ParticipationKind::Official")] kind: ParticipationKind,
+    ) -> Result<Vec<StandingRowGql>, ContestError> {
+        let viewer = ctx
+            .data_opt::<String>()
+            .map(|pk| pk.parse::<Uuid>().unwrap());
+        Ok(contest_api::standings(viewer, contest_pk, kind)
+            .await?
+            .into_iter()
+            .map(StandingRowGql::from)
+            .collect())
+    }
+
+    // in seconds, None outside of the viewer's window
+    async fn remaining_time<'ctx>(
+        &self,
+        ctx: &'ctx async_graphql::Context<'_>,
+        contest_pk: Uuid,
+    ) -> Result<Option<i64>, ContestError> {
+        let viewer = ctx
+            .data_opt::<String>()
+            .map(|pk| pk.parse::<Uuid>().unwrap())
+            .ok_or(ContestError::NotLoggedIn)?;
+        contest_api::remaining_time(viewer, contest_pk)
+    }
+
+    async fn virtual_window<'ctx>(
+        &self,
+        ctx: &'ctx async_graphql::Context<'_>,
+        contest_pk: Uuid,
+    ) -> Result<Option<TimeWindowGql>, ContestError> {
+        let viewer = ctx
+            .data_opt::<String>()
+            .map(|pk| pk.parse::<Uuid>().unwrap())
+            .ok_or(ContestError::NotLoggedIn)?;
+        let contest = find_contest(contest_pk).map_err(|_| ContestError::ContestNotFound)?;
+        Ok(contest_api::virtual_window(&contest, viewer)
+            .map(|(start_at, end_at)| TimeWindowGql { start_at, end_at }))
+    }
+
+    async fn similarities<'ctx>(
+        &self,
+        ctx: &'ctx async_graphql::Context<'_>,
+        task_pk: Option<Uuid>,
+        contest_pk: Option<Uuid>,
+        #[graphql(default = 0.5)] min_score: f64,
+        #[graphql(default = 100)] limit: i64,
+    ) -> Result<Vec<SimilarityGql>, JudgeError> {
+        let viewer = ctx
+            .data_opt::<String>()
+            .map(|pk| pk.parse::<Uuid>().unwrap());
+        if !viewer.map(judge_api::is_admin).unwrap_or(false) {
+            return Err(JudgeError::PermissionDenied);
+        }
+        find_similarities(task_pk, contest_pk, min_score, limit)
+            .map(|items| items.into_iter().map(SimilarityGql::from).collect())
+            .map_err(|_| JudgeError::DatabaseError)
+    }
+
+    async fn side_by_side<'ctx>(
+        &self,
+        ctx: &'ctx async_graphql::Context<'_>,
+        submission_a: Uuid,
+        submission_b: Uuid,
+    ) -> Result<SideBySideGql, JudgeError> {
+        let viewer = ctx
+            .data_opt::<String>()
+            .map(|pk| pk.parse::<Uuid>().unwrap());
+        if !viewer.map(judge_api::is_admin).unwrap_or(false) {
+            return Err(JudgeError::PermissionDenied);
+        }
+        let (left, left_lang, left_code) = judge_api::read_source(viewer, submission_a).await?;
+        let (right, right_lang, right_code) = judge_api::read_source(viewer, submission_b).await?;
+        let left_prints = fingerprints(&tokenize(
+            &left_code,
+            CommentStyle::from_extension(&left_lang.file_extension),
+        ));
+        let right_prints = fingerprints(&tokenize(
+            &right_code,
+            CommentStyle::from_extension(&right_lang.file_extension),
+        ));
+        Ok(SideBySideGql {
+            score: similarity(&left_prints, &right_prints),
+            matches: matches(&left_prints, &right_prints)
+                .into_iter()
+                .map(MatchGql::from)
+                .collect(),
+            left: SourceGql {
+                pk: left.pk,
+                language: left_lang.into(),
+                code: left_code,
+            },
+            right: SourceGql {
+                pk: right.pk,
+                language: right_lang.into(),
+                code: right_code,
+            },
+        })
+    }
 }
 
 #[Object]
 impl Mutation {
-    async fn register(&self, register_req: RegisterRequest) -> ResponseBlock<AccountError> {
-        if let Ok(_) = by_id(register_req.id.clone()) {
-            ResponseBlock {
+    async fn register<'ctx>(
+        &self,
+        ctx: &'ctx async_graphql::Context<'_>,
+        register_req: RegisterRequest,
+    ) -> ResponseBlock<AccountError> {
+        let ip = ctx.data_opt::<ClientAddr>().map(|x| x.0.clone());
+        let form = Registration {
+            id: register_req.id,
+            pass: register_req.pass,
+            email: register_req.email,
+            first_name: register_req.first_name,
+            last_name: register_req.last_name,
+            timezone: register_req
+                .timezone
+                .unwrap_or_else(|| CONFIG.general.timezone),
+            preferred_language: register_req
+                .preferred_language
+                .unwrap_or_else(|| CONFIG.general.default_language),
+        };
+        match register_user(form, ip).await {
+            Ok(_) => ResponseBlock {
+                status: true,
+                body: AccountError::None,
+            },
+            Err(err) => ResponseBlock {
+                status: false,
+                body: err,
+            },
+        }
+    }
+
+    async fn verify_email(&self, token: String) -> ResponseBlock<AccountError> {
+        match confirm_email(&token) {
+            Ok(_) => ResponseBlock {
+                status: true,
+                body: AccountError::None,
+            },
+            Err(err) => ResponseBlock {
                 status: false,
-                body: AccountError::UserNotExists,
+                body: err,
+            },
+        }
+    }
+
+    async fn resend_verification<'ctx>(
+        &self,
+        ctx: &'ctx async_graphql::Context<'_>,
+        email: String,
+    ) -> ResponseBlock<AccountError> {
+        let ip = ctx.data_opt::<ClientAddr>().map(|x| x.0.clone());
+        match resend_verification(email, ip).await {
+            Ok(_) => ResponseBlock {
+                status: true,
+                body: AccountError::None,
+            },
+            Err(err) => ResponseBlock {
+                status: false,
+                body: err,
+            },
+        }
+    }
+
+    async fn change_password<'ctx>(
+        &self,
+        ctx: &'ctx async_graphql::Context<'_>,
+        current_password: String,
+        new_password: String,
+    ) -> ResponseBlock<AccountError> {
+        let user_pk = match ctx.data_opt::<String>() {
+            Some(pk) => pk.parse::<Uuid>().unwrap(),
+            None => {
+                return ResponseBlock {
+                    status: false,
+                    body: AccountError::NotLoggedIn,
+                }
             }
-        } else {
-            let mut hasher = Sha3_256::new();
-            hasher.update(register_req.pass.as_bytes());
-            let pass_hashed = hex::encode(hasher.finalize());
-            let user = NewUser {
-                id: register_req.id,
-                pass: pass_hashed,
-                permission: AccountPerm::User as i32,
-                timezone: register_req
-                    .timezone
-                    .unwrap_or_else(|| CONFIG.general.timezone)
-                    .to_string(),
-                first_name: register_req.first_name,
-                last_name: register_req.last_name,
-                email: register_req.email,
-                preferred_language: register_req
-                    .preferred_language
-                    .unwrap_or_else(|| CONFIG.general.default_language),
-            };
-            match register(user) {
-                Ok(_) => ResponseBlock {
-                    status: true,
-                    body: AccountError::None,
-                },
-                Err(err) => ResponseBlock {
+        };
+        match password::change_password(user_pk, &current_password, &new_password) {
+            Ok(_) => ResponseBlock {
+                status: true,
+                body: AccountError::None,
+            },
+            Err(err) => ResponseBlock {
+                status: false,
+                body: err,
+            },
+        }
+    }
+
+    async fn request_password_reset<'ctx>(
+        &self,
+        ctx: &'ctx async_graphql::Context<'_>,
+        email: String,
+    ) -> ResponseBlock<AccountError> {
+        let ip = ctx.data_opt::<ClientAddr>().map(|x| x.0.clone());
+        match password::request_password_reset(email, ip).await {
+            Ok(_) => ResponseBlock {
+                status: true,
+                body: AccountError::None,
+            },
+            Err(err) => ResponseBlock {
+                status: false,
+                body: err,
+            },
+        }
+    }
+
+    async fn reset_password(
+        &self,
+        token: String,
+        new_password: String,
+    ) -> ResponseBlock<AccountError> {
+        match password::reset_password(&token, &new_password) {
+            Ok(_) => ResponseBlock {
+                status: true,
+                body: AccountError::None,
+            },
+            Err(err) => ResponseBlock {
+                status: false,
+                body: err,
+            },
+        }
+    }
+
+    async fn update_profile<'ctx>(
+        &self,
+        ctx: &'ctx async_graphql::Context<'_>,
+        profile: ProfileRequest,
+    ) -> ResponseBlock<AccountError> {
+        let user_pk = match ctx.data_opt::<String>() {
+            Some(pk) => pk.parse::<Uuid>().unwrap(),
+            None => {
+                return ResponseBlock {
+                    status: false,
+                    body: AccountError::NotLoggedIn,
+                }
+            }
+        };
+        let form = ProfileUpdate {
+            first_name: profile.first_name,
+            last_name: profile.last_name,
+            email: profile.email,
+            timezone: profile.timezone,
+            preferred_language: profile.preferred_language,
+        };
+        match profile::update_profile(user_pk, form).await {
+            Ok(_) => ResponseBlock {
+                status: true,
+                body: AccountError::None,
+            },
+            Err(err) => ResponseBlock {
+                status: false,
+                body: err,
+            },
+        }
+    }
+
+    // Submissions of the account are kept, but nothing else identifies its owner afterwards
+    async fn delete_account<'ctx>(
+        &self,
+        ctx: &'ctx async_graphql::Context<'_>,
+        password: String,
+    ) -> ResponseBlock<AccountError> {
+        let user_pk = match ctx.data_opt::<String>() {
+            Some(pk) => pk.parse::<Uuid>().unwrap(),
+            None => {
+                return ResponseBlock {
+                    status: false,
+                    body: AccountError::NotLoggedIn,
+                }
+            }
+        };
+        match profile::delete_account(user_pk, &password) {
+            Ok(_) => ResponseBlock {
+                status: true,
+                body: AccountError::None,
+            },
+            Err(err) => ResponseBlock {
+                status: false,
+                body: err,
+            },
+        }
+    }
+
+    async fn unlink_identity<'ctx>(
+        &self,
+        ctx: &'ctx async_graphql::Context<'_>,
+        provider: String,
+    ) -> ResponseBlock<AccountError> {
+        let user_pk = match ctx.data_opt::<String>() {
+            Some(pk) => pk.parse::<Uuid>().unwrap(),
+            None => {
+                return ResponseBlock {
+                    status: false,
+                    body: AccountError::NotLoggedIn,
+                }
+            }
+        };
+        match oidc::unlink_identity(user_pk, provider) {
+            Ok(_) => ResponseBlock {
+                status: true,
+                body: AccountError::None,
+            },
+            Err(err) => ResponseBlock {
+                status: false,
+                body: err,
+            },
+        }
+    }
+
+    // The returned secret is not stored, so it cannot be shown again.
+    async fn create_api_token<'ctx>(
+        &self,
+        ctx: &'ctx async_graphql::Context<'_>,
+        name: String,
+        scopes: Vec<TokenScope>,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<NewApiTokenGql, AccountError> {
+        let user_pk = ctx
+            .data_opt::<String>()
+            .map(|pk| pk.parse::<Uuid>().unwrap())
+            .ok_or(AccountError::NotLoggedIn)?;
+        let (token, secret) = api_tokens::create_token(user_pk, name, scopes, expires_at)?;
+        Ok(NewApiTokenGql {
+            secret,
+            token: ApiTokenGql { token },
+        })
+    }
+
+    async fn revoke_api_token<'ctx>(
+        &self,
+        ctx: &'ctx async_graphql::Context<'_>,
+        pk: Uuid,
+    ) -> ResponseBlock<AccountError> {
+        let user_pk = match ctx.data_opt::<String>() {
+            Some(pk) => pk.parse::<Uuid>().unwrap(),
+            None => {
+                return ResponseBlock {
                     status: false,
-                    body: AccountError::DatabaseError,
-                },
+                    body: AccountError::NotLoggedIn,
+                }
             }
+        };
+        match api_tokens::revoke_token(user_pk, pk) {
+            Ok(_) => ResponseBlock {
+                status: true,
+                body: AccountError::None,
+            },
+            Err(err) => ResponseBlock {
+                status: false,
+                body: err,
+            },
+        }
+    }
+
+    async fn enroll_totp<'ctx>(
+        &self,
+        ctx: &'ctx async_graphql::Context<'_>,
+        password: String,
+    ) -> Result<TotpEnrollmentGql, AccountError> {
+        let user_pk = ctx
+            .data_opt::<String>()
+            .map(|pk| pk.parse::<Uuid>().unwrap())
+            .ok_or(AccountError::NotLoggedIn)?;
+        let enrollment = two_factor::enroll_totp(user_pk, &password)?;
+        Ok(TotpEnrollmentGql {
+            secret: enrollment.secret,
+            uri: enrollment.uri,
+        })
+    }
+
+    // Enables TOTP with a code of the enrolled secret, returning the recovery codes
+    async fn confirm_totp<'ctx>(
+        &self,
+        ctx: &'ctx async_graphql::Context<'_>,
+        code: String,
+    ) -> Result<Vec<String>, AccountError> {
+        let user_pk = ctx
+            .data_opt::<String>()
+            .map(|pk| pk.parse::<Uuid>().unwrap())
+            .ok_or(AccountError::NotLoggedIn)?;
+        two_factor::confirm_totp(user_pk, &code)
+    }
+
+    async fn regenerate_recovery_codes<'ctx>(
+        &self,
+        ctx: &'ctx async_graphql::Context<'_>,
+        code: String,
+    ) -> Result<Vec<String>, AccountError> {
+        let user_pk = ctx
+            .data_opt::<String>()
+            .map(|pk| pk.parse::<Uuid>().unwrap())
+            .ok_or(AccountError::NotLoggedIn)?;
+        let user = find_user(user_pk).map_err(|_| AccountError::UserNotExists)?;
+        if !user.totp_enabled {
+            return Err(AccountError::TotpNotEnabled);
+        }
+        two_factor::check_code(&user, &code)?;
+        two_factor::regenerate_recovery_codes(user_pk)
+    }
+
+    async fn disable_totp<'ctx>(
+        &self,
+        ctx: &'ctx async_graphql::Context<'_>,
+        password: String,
+        code: String,
+    ) -> ResponseBlock<AccountError> {
+        let user_pk = match ctx.data_opt::<String>() {
+            Some(pk) => pk.parse::<Uuid>().unwrap(),
+            None => {
+                return ResponseBlock {
+                    status: false,
+                    body: AccountError::NotLoggedIn,
+                }
+            }
+        };
+        match two_factor::disable_totp(user_pk, &password, &code) {
+            Ok(_) => ResponseBlock {
+                status: true,
+                body: AccountError::None,
+            },
+            Err(err) => ResponseBlock {
+                status: false,
+                body: err,
+            },
+        }
+    }
+
+    async fn ban_user<'ctx>(
+        &self,
+        ctx: &'ctx async_graphql::Context<'_>,
+        user: Uuid,
+        reason: Option<String>,
+    ) -> ResponseBlock<AccountError> {
+        let viewer = ctx
+            .data_opt::<String>()
+            .map(|pk| pk.parse::<Uuid>().unwrap());
+        let impersonator = ctx.data_opt::<Impersonator>().map(|x| x.0);
+        match admin::set_banned(viewer, impersonator, user, true, reason) {
+            Ok(_) => ResponseBlock {
+                status: true,
+                body: AccountError::None,
+            },
+            Err(err) => ResponseBlock {
+                status: false,
+                body: err,
+            },
+        }
+    }
+
+    async fn unban_user<'ctx>(
+        &self,
+        ctx: &'ctx async_graphql::Context<'_>,
+        user: Uuid,
+    ) -> ResponseBlock<AccountError> {
+        let viewer = ctx
+            .data_opt::<String>()
+            .map(|pk| pk.parse::<Uuid>().unwrap());
+        let impersonator = ctx.data_opt::<Impersonator>().map(|x| x.0);
+        match admin::set_banned(viewer, impersonator, user, false, None) {
+            Ok(_) => ResponseBlock {
+                status: true,
+                body: AccountError::None,
+            },
+            Err(err) => ResponseBlock {
+                status: false,
+                body: err,
+            },
+        }
+    }
+
+    async fn force_logout<'ctx>(
+        &self,
+        ctx: &'ctx async_graphql::Context<'_>,
+        user: Uuid,
+    ) -> ResponseBlock<AccountError> {
+        let viewer = ctx
+            .data_opt::<String>()
+            .map(|pk| pk.parse::<Uuid>().unwrap());
+        let impersonator = ctx.data_opt::<Impersonator>().map(|x| x.0);
+        match admin::force_logout(viewer, impersonator, user) {
+            Ok(_) => ResponseBlock {
+                status: true,
+                body: AccountError::None,
+            },
+            Err(err) => ResponseBlock {
+                status: false,
+                body: err,
+            },
+        }
+    }
+
+    async fn reset_user_password<'ctx>(
+        &self,
+        ctx: &'ctx async_graphql::Context<'_>,
+        user: Uuid,
+    ) -> ResponseBlock<AccountError> {
+        let viewer = ctx
+            .data_opt::<String>()
+            .map(|pk| pk.parse::<Uuid>().unwrap());
+        let impersonator = ctx.data_opt::<Impersonator>().map(|x| x.0);
+        match admin::reset_user_password(viewer, impersonator, user).await {
+            Ok(_) => ResponseBlock {
+                status: true,
+                body: AccountError::None,
+            },
+            Err(err) => ResponseBlock {
+                status: false,
+                body: err,
+            },
+        }
+    }
+
+    async fn change_permission<'ctx>(
+        &self,
+        ctx: &'ctx async_graphql::Context<'_>,
+        user: Uuid,
+        permission: AccountPerm,
+    ) -> ResponseBlock<AccountError> {
+        let viewer = ctx
+            .data_opt::<String>()
+            .map(|pk| pk.parse::<Uuid>().unwrap());
+        let impersonator = ctx.data_opt::<Impersonator>().map(|x| x.0);
+        match admin::change_permission(viewer, impersonator, user, permission) {
+            Ok(_) => ResponseBlock {
+                status: true,
+                body: AccountError::None,
+            },
+            Err(err) => ResponseBlock {
+                status: false,
+                body: err,
+            },
+        }
+    }
+
+    async fn submit<'ctx>(
+        &self,
+        ctx: &'ctx async_graphql::Context<'_>,
+        task_pk: Uuid,
+        lang_uuid: Uuid,
+        source: String,
+    ) -> Result<Uuid, JudgeError> {
+        if let Some(pk) = ctx.data_opt::<String>() {
+            let user_pk = pk.parse::<Uuid>().unwrap();
+            judge_api::submit(user_pk, task_pk, lang_uuid, source).await
+        } else {
+            Err(JudgeError::NotLoggedIn)
+        }
+    }
+
+    async fn submit_outputs<'ctx>(
+        &self,
+        ctx: &'ctx async_graphql::Context<'_>,
+        task_pk: Uuid,
+        outputs: Vec<OutputFile>,
+    ) -> Result<Uuid, JudgeError> {
+        if let Some(pk) = ctx.data_opt::<String>() {
+            let user_pk = pk.parse::<Uuid>().unwrap();
+            let outputs = outputs
+                .into_iter()
+                .map(|file| (file.name, file.content))
+                .collect();
+            judge_api::submit_outputs(user_pk, task_pk, outputs).await
+        } else {
+            Err(JudgeError::NotLoggedIn)
+        }
+    }
+
+    async fn start_contest<'ctx>(
+        &self,
+        ctx: &'ctx async_graphql::Context<'_>,
+        contest_pk: Uuid,
+    ) -> Result<TimeWindowGql, ContestError> {
+        if let Some(pk) = ctx.data_opt::<String>() {
+            let user_pk = pk.parse::<Uuid>().unwrap();
+            let (start_at, end_at) = contest_api::start_contest(user_pk, contest_pk)?;
+            Ok(TimeWindowGql { start_at, end_at })
+        } else {
+            Err(ContestError::NotLoggedIn)
+        }
+    }
+
+    async fn start_virtual<'ctx>(
+        &self,
+        ctx: &'ctx async_graphql::Context<'_>,
+        contest_pk: Uuid,
+    ) -> Result<TimeWindowGql, ContestError> {
+        if let Some(pk) = ctx.data_opt::<String>() {
+            let user_pk = pk.parse::<Uuid>().unwrap();
+            let participation = contest_api::start_virtual(user_pk, contest_pk)?;
+            let contest = find_contest(contest_pk).map_err(|_| ContestError::ContestNotFound)?;
+            Ok(TimeWindowGql {
+                start_at: participation.start_at,
+                end_at: participation.start_at + contest.duration(),
+            })
+        } else {
+            Err(ContestError::NotLoggedIn)
+        }
+    }
+
+    async fn ask_clarification<'ctx>(
+        &self,
+        ctx: &'ctx async_graphql::Context<'_>,
+        contest_pk: Uuid,
+        task_pk: Option<Uuid>,
+        question: String,
+    ) -> Result<ClarificationGql, ContestError> {
+        if let Some(pk) = ctx.data_opt::<String>() {
+            let user_pk = pk.parse::<Uuid>().unwrap();
+            contest_api::ask_clarification(user_pk, contest_pk, task_pk, question)
+                .map(|x| ClarificationGql::new(x, user_pk, false))
+        } else {
+            Err(ContestError::NotLoggedIn)
+        }
+    }
+
+    async fn answer_clarification<'ctx>(
+        &self,
+        ctx: &'ctx async_graphql::Context<'_>,
+        clarification_pk: Uuid,
+        answer: String,
+        #[graphql(default = false)] broadcast: bool,
+    ) -> Result<ClarificationGql, ContestError> {
+        if let Some(pk) = ctx.data_opt::<String>() {
+            let user_pk = pk.parse::<Uuid>().unwrap();
+            contest_api::answer(user_pk, clarification_pk, answer, broadcast)
+                .map(|x| ClarificationGql::new(x, user_pk, true))
+        } else {
+            Err(ContestError::NotLoggedIn)
+        }
+    }
+
+    async fn announce<'ctx>(
+        &self,
+        ctx: &'ctx async_graphql::Context<'_>,
+        contest_pk: Uuid,
+        task_pk: Option<Uuid>,
+        text: String,
+    ) -> Result<ClarificationGql, ContestError> {
+        if let Some(pk) = ctx.data_opt::<String>() {
+            let user_pk = pk.parse::<Uuid>().unwrap();
+            contest_api::announce(user_pk, contest_pk, task_pk, text)
+                .map(|x| ClarificationGql::new(x, user_pk, true))
+        } else {
+            Err(ContestError::NotLoggedIn)
+        }
+    }
+
+    async fn check_plagiarism<'ctx>(
+        &self,
+        ctx: &'ctx async_graphql::Context<'_>,
+        task_pk: Option<Uuid>,
+        contest_pk: Option<Uuid>,
+    ) -> ResponseBlock<JudgeError> {
+        let viewer = ctx
+            .data_opt::<String>()
+            .map(|pk| pk.parse::<Uuid>().unwrap());
+        if !viewer.map(judge_api::is_admin).unwrap_or(false) {
+            return ResponseBlock {
+                status: false,
+                body: JudgeError::PermissionDenied,
+            };
+        }
+        if let Some(contest_pk) = contest_pk {
+            APPDATA
+                .plagiarism_addr
+                .do_send(PlagiarismMessage::CheckContest(contest_pk));
+        } else if let Some(task_pk) = task_pk {
+            APPDATA
+                .plagiarism_addr
+                .do_send(PlagiarismMessage::CheckTask(task_pk));
+        } else {
+            return ResponseBlock {
+                status: false,
+                body: JudgeError::TaskNotFound,
+            };
+        }
+        ResponseBlock {
+            status: true,
+            body: JudgeError::None,
         }
     }
 }
@@ -143,11 +998,33 @@ impl SubscriptionRoot {
         }
     }
 
-    async fn ws_state(&self, judge_uuid: Uuid) -> impl Stream<Item = Submission> {
-        APPDATA
-            .judge_addr
-            .send(SubscribeMessage::Subscribe(judge_uuid))
-            .await
-            .unwrap()
+    // states of the viewer's own submission while it is judged
+    async fn ws_state<'ctx>(
+        &self,
+        ctx: &'ctx async_graphql::Context<'_>,
+        judge_uuid: Uuid,
+    ) -> Result<impl Stream<Item = Submission>, JudgeError> {
+        let viewer = ctx
+            .data_opt::<String>()
+            .map(|pk| pk.parse::<Uuid>().unwrap())
+            .ok_or(JudgeError::NotLoggedIn)?;
+        judge_api::subscribe_state(viewer, judge_uuid).await
+    }
+
+    // new questions, answers and announcements visible to the viewer
+    async fn clarifications<'ctx>(
+        &self,
+        ctx: &'ctx async_graphql::Context<'_>,
+        contest_pk: Uuid,
+    ) -> Result<impl Stream<Item = ClarificationGql>, ContestError> {
+        let viewer = ctx
+            .data_opt::<String>()
+            .map(|pk| pk.parse::<Uuid>().unwrap())
+            .ok_or(ContestError::NotLoggedIn)?;
+        let contest = find_contest(contest_pk).map_err(|_| ContestError::ContestNotFound)?;
+        let jury = contest_api::is_jury(viewer, &contest);
+        Ok(contest_api::subscribe_clarifications(viewer, contest_pk)
+            .await?
+            .map(move |x| ClarificationGql::new(x, viewer, jury)))
     }
 }
@@ -0,0 +1,100 @@
+use super::submissions::SourceGql;
+use crate::db::models::Similarity;
+use crate::plagiarism::winnowing::Match;
+use async_graphql::*;
+use chrono::prelude::*;
+use uuid::Uuid;
+
+pub struct SimilarityGql {
+    pub task_pk: Uuid,
+    pub contest_pk: Option<Uuid>,
+    pub submission_a: Uuid,
+    pub submission_b: Uuid,
+    pub score: f64,
+    pub computed_at: DateTime<Utc>,
+}
+
+impl From<Similarity> for SimilarityGql {
+    fn from(x: Similarity) -> Self {
+        Self {
+            task_pk: x.task_pk,
+            contest_pk: x.contest_pk,
+            submission_a: x.submission_a,
+            submission_b: x.submission_b,
+            score: x.score,
+            computed_at: x.computed_at,
+        }
+    }
+}
+
+#[Object]
+impl SimilarityGql {
+    async fn task_pk(&self) -> Uuid {
+        self.task_pk
+    }
+
+    async fn contest_pk(&self) -> Option<Uuid> {
+        self.contest_pk
+    }
+
+    async fn submission_a(&self) -> Uuid {
+        self.submission_a
+    }
+
+    async fn submission_b(&self) -> Uuid {
+        self.submission_b
+    }
+
+    async fn score(&self) -> f64 {
+        self.score
+    }
+
+    async fn computed_at(&self) -> DateTime<Utc> {
+        self.computed_at
+    }
+}
+
+#[derive(Clone, Copy, Debug, SimpleObject)]
+pub struct MatchGql {
+    pub left_start: usize,
+    pub left_end: usize,
+    pub right_start: usize,
+    pub right_end: usize,
+}
+
+impl From<Match> for MatchGql {
+    fn from(x: Match) -> Self {
+        Self {
+            left_start: x.left_start,
+            left_end: x.left_end,
+            right_start: x.right_start,
+            right_end: x.right_end,
+        }
+    }
+}
+
+pub struct SideBySideGql {
+    pub left: SourceGql,
+    pub right: SourceGql,
+    pub score: f64,
+    pub matches: Vec<MatchGql>,
+}
+
+#[Object]
+impl SideBySideGql {
+    async fn left(&self) -> &SourceGql {
+        &self.left
+    }
+
+    async fn right(&self) -> &SourceGql {
+        &self.right
+    }
+
+    async fn score(&self) -> f64 {
+        self.score
+    }
+
+    async fn matches(&self) -> Vec<MatchGql> {
+        self.matches.clone()
+    }
+}
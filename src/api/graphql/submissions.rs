@@ -0,0 +1,202 @@
+use super::languages::LanguageGql;
+use crate::api::judge::list_submissions;
+use crate::constants::*;
+use crate::db::judge::SubmissionQuery;
+use crate::db::models;
+use crate::judge::api::{Submission, SubmissionState};
+use async_graphql::connection::*;
+use async_graphql::*;
+use chrono::prelude::*;
+use uuid::Uuid;
+
+#[derive(Clone, Debug, Default, InputObject)]
+pub struct SubmissionFilter {
+    pub user_pk: Option<Uuid>,
+    pub task_pk: Option<Uuid>,
+    pub contest_pk: Option<Uuid>,
+    pub lang_uuid: Option<Uuid>,
+    pub state: Option<SubmissionState>,
+    pub issued_after: Option<DateTime<Utc>>,
+    pub issued_before: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Debug, InputObject)]
+pub struct OutputFile {
+    pub name: String,
+    pub content: String,
+}
+
+pub struct SubmissionGql {
+    pub pk: Uuid,
+    pub user_pk: Uuid,
+    pub task_pk: Uuid,
+    pub lang_uuid: Uuid,
+    pub issued_at: DateTime<Utc>,
+    pub state: Option<Submission>,
+}
+
+impl From<(models::Submission, Option<Submission>)> for SubmissionGql {
+    fn from((submission, state): (models::Submission, Option<Submission>)) -> Self {
+        Self {
+            pk: submission.pk,
+            user_pk: submission.user_pk,
+            task_pk: submission.task_pk,
+            lang_uuid: submission.lang_uuid,
+            issued_at: submission.issued_at,
+            state,
+        }
+    }
+}
+
+#[Object]
+impl SubmissionGql {
+    async fn pk(&self) -> Uuid {
+        self.pk
+    }
+
+    async fn user_pk(&self) -> Uuid {
+        self.user_pk
+    }
+
+    async fn task_pk(&self) -> Uuid {
+        self.task_pk
+    }
+
+    async fn lang_uuid(&self) -> Uuid {
+        self.lang_uuid
+    }
+
+    async fn issued_at(&self) -> DateTime<Utc> {
+        self.issued_at
+    }
+
+    async fn state(&self) -> Option<Submission> {
+        self.state.clone()
+    }
+}
+
+pub struct SourceGql {
+    pub pk: Uuid,
+    pub language: LanguageGql,
+    pub code: String,
+}
+
+#[Object]
+impl SourceGql {
+    async fn pk(&self) -> Uuid {
+        self.pk
+    }
+
+    async fn language(&self) -> &LanguageGql {
+        &self.language
+    }
+
+    async fn code(&self) -> String {
+        self.code.clone()
+    }
+}
+
+// Position of a submission in the newest-first list, as `{issued_at}/{pk}`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SubmissionCursor {
+    pub issued_at: DateTime<Utc>,
+    pub pk: Uuid,
+}
+
+impl SubmissionCursor {
+    fn key(&self) -> (DateTime<Utc>, Uuid) {
+        (self.issued_at, self.pk)
+    }
+}
+
+impl CursorType for SubmissionCursor {
+    type Error = String;
+
+    fn decode_cursor(s: &str) -> Result<Self, Self::Error> {
+        let (issued_at, pk) = s
+            .split_once('/')
+            .ok_or_else(|| String::from("invalid cursor"))?;
+        Ok(Self {
+            issued_at: DateTime::parse_from_rfc3339(issued_at)
+                .map_err(|err| err.to_string())?
+                .with_timezone(&Utc),
+            pk: Uuid::parse_str(pk).map_err(|err| err.to_string())?,
+        })
+    }
+
+    fn encode_cursor(&self) -> String {
+        format!(
+            "{}/{}",
+            self.issued_at.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+            self.pk
+        )
+    }
+}
+
+pub type SubmissionConnection =
+    Connection<SubmissionCursor, SubmissionGql, EmptyFields, EmptyFields>;
+
+// Keyset cursors over the newest-first list, read from the database page by page.
+// `last` without `first` reads backwards from `before`, or from the oldest submission.
+// Pages hold SUBMISSION_PAGE_SIZE submissions unless asked for, and at most SUBMISSION_PAGE_MAX.
+pub async fn paginate(
+    viewer: Option<Uuid>,
+    mut submissions: SubmissionQuery,
+    filter: SubmissionFilter,
+    after: Option<String>,
+    before: Option<String>,
+    first: Option<i32>,
+    last: Option<i32>,
+) -> Result<SubmissionConnection> {
+    query(
+        after,
+        before,
+        first,
+        last,
+        |after: Option<SubmissionCursor>, before: Option<SubmissionCursor>, first, last| async move {
+            if first.into_iter().chain(last).any(|count| count > SUBMISSION_PAGE_MAX) {
+                return Err(Error::new(format!(
+                    "at most {} submissions can be requested at once",
+                    SUBMISSION_PAGE_MAX
+                )));
+            }
+            let backward = first.is_none() && last.is_some();
+            let count = if backward { last } else { first };
+            submissions.older_than = after.map(|x| x.key());
+            submissions.newer_than = before.map(|x| x.key());
+            submissions.oldest_first = backward;
+            let (mut items, more) = list_submissions(
+                viewer,
+                submissions,
+                filter.contest_pk,
+                filter.state,
+                Some(count.unwrap_or(SUBMISSION_PAGE_SIZE)),
+            )
+            .await?;
+            let (mut has_previous, has_next) = if backward {
+                items.reverse();
+                (more, before.is_some())
+            } else {
+                (after.is_some(), more)
+            };
+            if let Some(last) = last.filter(|_| !backward) {
+                let skip = items.len().saturating_sub(last);
+                has_previous |= skip > 0;
+                items.drain(..skip);
+            }
+            let mut connection = Connection::new(has_previous, has_next);
+            connection.edges.extend(items.into_iter().map(|item| {
+                let item = SubmissionGql::from(item);
+                Edge::new(
+                    SubmissionCursor {
+                        issued_at: item.issued_at,
+                        pk: item.pk,
+                    },
+                    item,
+                )
+            }));
+            Ok::<_, Error>(connection)
+        },
+    )
+    .await
+}
@@ -0,0 +1,240 @@
+use crate::tasks::statements::*;
+use crate::tasks::subtasks::Limits;
+use crate::tasks::*;
+use async_graphql::*;
+use async_std::fs::read_to_string;
+use async_std::path::PathBuf;
+use uuid::Uuid;
+
+pub struct TaskGql {
+    pub pk: Uuid,
+    pub loader: TaskLoader,
+}
+
+pub struct SubtaskGql {
+    pub name: String,
+    pub score: f64,
+    pub testcases: Vec<String>,
+    pub dependencies: Vec<String>,
+    pub time_limit: u64,
+    pub memory_limit: u64,
+}
+
+#[Object]
+impl SubtaskGql {
+    async fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    async fn score(&self) -> f64 {
+        self.score
+    }
+
+    async fn testcases(&self) -> Vec<String> {
+        self.testcases.clone()
+    }
+
+    async fn dependencies(&self) -> Vec<String> {
+        self.dependencies.clone()
+    }
+
+    // in ms
+    async fn time_limit(&self) -> u64 {
+        self.time_limit
+    }
+
+    // in kB
+    async fn memory_limit(&self) -> u64 {
+        self.memory_limit
+    }
+}
+
+pub struct SampleGql {
+    pub name: String,
+    pub input: String,
+    pub output: String,
+}
+
+impl From<Sample> for SampleGql {
+    fn from(sample: Sample) -> Self {
+        Self {
+            name: sample.name,
+            input: sample.input,
+            output: sample.output,
+        }
+    }
+}
+
+#[Object]
+impl SampleGql {
+    async fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    async fn input(&self) -> String {
+        self.input.clone()
+    }
+
+    async fn output(&self) -> String {
+        self.output.clone()
+    }
+}
+
+pub struct StatementPartGql {
+    pub format: StatementFormat,
+    pub path: PathBuf,
+}
+
+#[Object]
+impl StatementPartGql {
+    async fn format(&self) -> StatementFormat {
+        self.format
+    }
+
+    // None for PDF statements
+    async fn content(&self) -> Option<String> {
+        if self.format == StatementFormat::Pdf {
+            None
+        } else {
+            read_to_string(self.path.clone()).await.ok()
+        }
+    }
+}
+
+pub struct StatementGql {
+    pub language: String,
+    pub statement: Statement,
+    pub path: PathBuf,
+    pub samples: Vec<SampleGql>,
+}
+
+impl StatementGql {
+    fn part(&self, name: &str, format: StatementFormat) -> StatementPartGql {
+        StatementPartGql {
+            format,
+            path: self.path.join(name).with_extension(format.extension()),
+        }
+    }
+}
+
+#[Object]
+impl StatementGql {
+    async fn language(&self) -> String {
+        self.language.clone()
+    }
+
+    async fn name(&self) -> StatementPartGql {
+        self.part("name", self.statement.name)
+    }
+
+    async fn legend(&self) -> StatementPartGql {
+        self.part("legend", self.statement.legend)
+    }
+
+    async fn input(&self) -> StatementPartGql {
+        self.part("input", self.statement.input)
+    }
+
+    async fn output(&self) -> StatementPartGql {
+        self.part("output", self.statement.output)
+    }
+
+    async fn notes(&self) -> StatementPartGql {
+        self.part("notes", self.statement.notes)
+    }
+
+    async fn samples(&self) -> &Vec<SampleGql> {
+        &self.samples
+    }
+}
+
+#[Object]
+impl TaskGql {
+    async fn pk(&self) -> Uuid {
+        self.pk
+    }
+
+    async fn name(&self) -> String {
+        self.loader.task.name.clone()
+    }
+
+    async fn code(&self) -> String {
+        self.loader.task.code.clone()
+    }
+
+    async fn task_type(&self) -> String {
+        self.loader.task.task_type.name()
+    }
+
+    async fn time_limit(&self) -> f64 {
+        self.loader.task.time_limit
+    }
+
+    async fn memory_limit(&self) -> u64 {
+        self.loader.task.memory_limit
+    }
+
+    async fn time_multiplier(&self, language: Uuid) -> f64 {
+        self.loader
+            .task
+            .time_multipliers
+            .get(&language)
+            .copied()
+            .unwrap_or(1.0)
+    }
+
+    // effective limits of each subtask for the language, if given
+    async fn subtasks(&self, language: Option<Uuid>) -> Vec<SubtaskGql> {
+        let mut subtasks: Vec<SubtaskGql> = self
+            .loader
+            .subtasks
+            .subtasks
+            .values()
+            .map(|(subtask, _)| {
+                let limits = Limits {
+                    time_limit: subtask.time_limit,
+                    memory_limit: subtask.memory_limit,
+                };
+                let (time_limit, memory_limit) =
+                    self.loader.task.effective_limits(limits, language);
+                SubtaskGql {
+                    name: subtask.name.clone(),
+                    score: subtask.score,
+                    testcases: subtask.testcases.clone(),
+                    dependencies: subtask.dependencies.clone(),
+                    time_limit,
+                    memory_limit,
+                }
+            })
+            .collect();
+        subtasks.sort_by(|a, b| a.name.cmp(&b.name));
+        subtasks
+    }
+
+    async fn description(&self) -> Option<String> {
+        self.loader.task.description.clone()
+    }
+
+    async fn statement_languages(&self) -> Vec<String> {
+        self.loader.statement_languages()
+    }
+
+    async fn statement(&self, language: String) -> Result<Option<StatementGql>> {
+        if let Some((statement, path)) = self.loader.statement(&language) {
+            let samples = self.loader.samples().await?;
+            Ok(Some(StatementGql {
+                language,
+                statement: statement.clone(),
+                path: path.clone(),
+                samples: samples.into_iter().map(SampleGql::from).collect(),
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn samples(&self) -> Result<Vec<SampleGql>> {
+        let samples = self.loader.samples().await?;
+        Ok(samples.into_iter().map(SampleGql::from).collect())
+    }
+}
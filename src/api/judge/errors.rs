@@ -0,0 +1,48 @@
+use async_graphql::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Error, Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Enum)]
+pub enum JudgeError {
+    #[error("Ok")]
+    None,
+    #[error("You aren't logged in")]
+    NotLoggedIn,
+    #[error("Some database error occurred")]
+    DatabaseError,
+    #[error("You don't have permission")]
+    PermissionDenied,
+    #[error("Requested submission does not exist")]
+    SubmissionNotFound,
+    #[error("Source code of requested submission does not exist")]
+    SourceNotFound,
+    #[error("Requested task does not exist")]
+    TaskNotFound,
+    #[error("Requested task package cannot be loaded")]
+    TaskNotLoaded,
+    #[error("Requested contest does not exist")]
+    ContestNotFound,
+    #[error("Your time window of the contest is not open")]
+    OutsideContestWindow,
+    #[error("Requested task package is missing files required by its task type")]
+    InvalidTaskPackage,
+    #[error("Requested task does not accept this kind of submission")]
+    InvalidTaskType,
+    #[error("Submitted output does not match any testcase")]
+    UnknownTestcase,
+    #[error("Requested language does not exist")]
+    LanguageNotFound,
+    #[error("Requested language is disabled")]
+    LanguageDisabled,
+    #[error("Requested language is not supported by this task")]
+    LanguageNotAllowed,
+    #[error("Too many submissions, wait a moment")]
+    TooManySubmissions,
+}
+
+impl ErrorExtensions for JudgeError {
+    fn extend(&self) -> FieldError {
+        self.extend_with(|err, e| match err {
+            _ => {}
+        })
+    }
+}
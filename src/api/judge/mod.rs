@@ -1 +1,504 @@
+pub mod errors;
 
+use actix_identity::Identity;
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use actix_web::{get, web, HttpResponse};
+use async_std::path::PathBuf;
+use chrono::prelude::*;
+use futures_util::{Stream, StreamExt};
+use std::collections::HashMap;
+use std::future::Future;
+use uuid::Uuid;
+
+use super::ResponseBlock;
+use crate::api::accounts::api_tokens::admin_scope_allowed;
+use crate::api::accounts::limits::submit_limit;
+use crate::api::accounts::AccountPerm;
+use crate::api::contests::{can_participate, participant_window};
+use crate::constants::*;
+use crate::db::accounts::find_user;
+use crate::db::contests::*;
+use crate::db::judge::*;
+use crate::db::keydb::*;
+use crate::db::languages::find_language;
+use crate::db::models::{self, Language};
+use crate::db::tasks::find_task;
+use crate::judge::api::{Submission, SubmissionState};
+use crate::judge::{JudgeMessage, SubscribeMessage};
+use crate::tasks::types::*;
+use crate::tasks::*;
+use crate::{APPDATA, CONFIG};
+
+pub use errors::*;
+
+pub async fn load_task_of(task_pk: Uuid) -> Result<TaskLoader, JudgeError> {
+    let task = find_task(task_pk).map_err(|_| JudgeError::TaskNotFound)?;
+    load_task(PathBuf::from(TASKS).join(task.internal_task_uuid.to_string()))
+        .await
+        .map_err(|_| JudgeError::TaskNotLoaded)
+}
+
+pub fn validate_language(task: &TaskLoader, lang_uuid: Uuid) -> Result<Language, JudgeError> {
+    let lang = find_language(lang_uuid).map_err(|_| JudgeError::LanguageNotFound)?;
+    if !lang.is_enabled {
+        Err(JudgeError::LanguageDisabled)
+    } else if !task.accepts_language(&lang_uuid) {
+        Err(JudgeError::LanguageNotAllowed)
+    } else {
+        Ok(lang)
+    }
+}
+
+// Stores the submission and hands it to the task type of the task to be judged.
+async fn enqueue(
+    user_pk: Uuid,
+    task_pk: Uuid,
+    task: &TaskLoader,
+    data: SubmissionData,
+) -> Result<Uuid, JudgeError> {
+    let task_type = task.task.task_type.name();
+    let handler = task_type_handler(&task_type).ok_or(JudgeError::InvalidTaskType)?;
+    if !handler.validate(task) {
+        return Err(JudgeError::InvalidTaskPackage);
+    }
+    if !handler.accepts(&data) {
+        return Err(JudgeError::InvalidTaskType);
+    }
+    let lang_uuid = match &data {
+        SubmissionData::Source(lang_uuid, _) => *lang_uuid,
+        SubmissionData::Outputs(_) => Uuid::nil(), // output-only submissions have no language
+    };
+    // the row is stored only once the request is ready, so that no submission is left
+    // without a state when the package cannot be read
+    let submission_pk = Uuid::new_v4();
+    let (request, mut state) = handler
+        .make_request(submission_pk, task, &data)
+        .await
+        .map_err(|_| JudgeError::TaskNotLoaded)?;
+    state.task_type = task_type;
+    let submission = new_submission(models::Submission {
+        pk: submission_pk,
+        user_pk,
+        task_pk,
+        lang_uuid,
+        issued_at: Utc::now(),
+    })
+    .map_err(|_| JudgeError::DatabaseError)?;
+    APPDATA
+        .source_db
+        .send(KeyDbMessage::Insert(submission.pk, data.to_stored()))
+        .await
+        .ok();
+    APPDATA
+        .judge_db
+        .send(KeyDbMessage::Insert(submission.pk, state.to_json()))
+        .await
+        .ok();
+    if let Some(request) = request {
+        APPDATA.judge_addr.do_send(JudgeMessage::Enqueue(request));
+    }
+    Ok(submission.pk)
+}
+
+// Whether tasks of the contests take submissions at `now`. Running contests take them only
+// from those admitted to them and, if windowed, only while their own window, given with each
+// contest, is open. Ended contests do not lift that, so that a task reused from an old contest
+// is no way around it. Whether the task may be seen at all is left to the caller.
+pub fn contest_window_open(
+    contests: &[(
+        &models::Contest,
+        bool,
+        Option<(DateTime<Utc>, DateTime<Utc>)>,
+    )],
+    now: DateTime<Utc>,
+) -> bool {
+    let mut running = contests
+        .iter()
+        .filter(|(contest, _, _)| contest.start_at <= now && now < contest.end_at)
+        .peekable();
+    running.peek().is_none()
+        || running.any(|(contest, allowed, window)| {
+            *allowed
+                && (!contest.is_windowed()
+                    || window.map_or(false, |(start, end)| start <= now && now < end))
+        })
+}
+
+// Public tasks and admins are not restricted by contests. Anyone else has to be able to see
+// the task, and to take part in a running contest with it if there is one.
+pub fn check_contest_window(user_pk: Uuid, task_pk: Uuid) -> Result<(), JudgeError> {
+    let task = find_task(task_pk).map_err(|_| JudgeError::TaskNotFound)?;
+    if task.is_public || is_admin(user_pk) {
+        return Ok(());
+    }
+    if !can_view_task(Some(user_pk), &task) {
+        return Err(JudgeError::PermissionDenied);
+    }
+    let contests = contests_of_task(task_pk).map_err(|_| JudgeError::DatabaseError)?;
+    let mut windows = Vec::with_capacity(contests.len());
+    for contest in &contests {
+        let (allowed, window) = if contest.is_running() {
+            let allowed =
+                can_participate(user_pk, contest).map_err(|_| JudgeError::DatabaseError)?;
+            let window = if allowed && contest.is_windowed() {
+                participant_window(contest, user_pk)
+            } else {
+                None
+            };
+            (allowed, window)
+        } else {
+            (false, None)
+        };
+        windows.push((contest, allowed, window));
+    }
+    if contest_window_open(&windows, Utc::now()) {
+        Ok(())
+    } else {
+        Err(JudgeError::OutsideContestWindow)
+    }
+}
+
+// Submissions are throttled per account if configured, except for admins.
+fn check_submit_rate(user_pk: Uuid) -> Result<(), JudgeError> {
+    if let Some(limit) = submit_limit() {
+        if !is_admin(user_pk) && limit.hit(&user_pk.to_string()).is_some() {
+            return Err(JudgeError::TooManySubmissions);
+        }
+    }
+    Ok(())
+}
+
+pub async fn submit(
+    user_pk: Uuid,
+    task_pk: Uuid,
+    lang_uuid: Uuid,
+    source: String,
+) -> Result<Uuid, JudgeError> {
+    check_contest_window(user_pk, task_pk)?;
+    let task = load_task_of(task_pk).await?;
+    validate_language(&task, lang_uuid)?;
+    check_submit_rate(user_pk)?;
+    enqueue(
+        user_pk,
+        task_pk,
+        &task,
+        SubmissionData::Source(lang_uuid, source),
+    )
+    .await
+}
+
+// Outputs are kept in source_db as a JSON object from testcase name to its content.
+pub async fn submit_outputs(
+    user_pk: Uuid,
+    task_pk: Uuid,
+    outputs: HashMap<String, String>,
+) -> Result<Uuid, JudgeError> {
+    check_contest_window(user_pk, task_pk)?;
+    let task = load_task_of(task_pk).await?;
+    if outputs.keys().any(|name| !task.has_testcase(name)) {
+        return Err(JudgeError::UnknownTestcase);
+    }
+    check_submit_rate(user_pk)?;
+    enqueue(user_pk, task_pk, &task, SubmissionData::Outputs(outputs)).await
+}
+
+pub async fn judge_state(judge_uuid: Uuid) -> Option<Submission> {
+    if let KeyDbResponse::Data(data) = APPDATA
+        .judge_db
+        .send(KeyDbMessage::Get(judge_uuid))
+        .await
+        .ok()?
+    {
+        Some(Submission::from_json(data))
+    } else {
+        None
+    }
+}
+
+// States of a batch of submissions in one round trip, missing ones left out
+pub async fn judge_states(judge_uuids: Vec<Uuid>) -> HashMap<Uuid, Submission> {
+    get_many(&APPDATA.judge_db, judge_uuids)
+        .await
+        .into_iter()
+        .map(|(uuid, data)| (uuid, Submission::from_json(data)))
+        .collect()
+}
+
+// Requests authenticated by a token need its admin scope as well, and admins may be
+// required to enable two-factor authentication first.
+pub fn is_admin(user_pk: Uuid) -> bool {
+    if let Ok(user) = find_user(user_pk) {
+        user.permission == AccountPerm::Admin as i32
+            && (user.totp_enabled || !CONFIG.accounts.require_admin_2fa)
+            && admin_scope_allowed()
+    } else {
+        false
+    }
+}
+
+// Whether a contest shows its tasks at `now`, to a viewer who may take part in it if
+// `allowed`. Running windowed contests show them only once the viewer's `window` started.
+pub fn contest_shows_tasks(
+    contest: &models::Contest,
+    allowed: bool,
+    window: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    now: DateTime<Utc>,
+) -> bool {
+    if !allowed || now < contest.start_at {
+        return false;
+    }
+    if contest.is_windowed() && now < contest.end_at {
+        window.map_or(false, |(start, _)| start <= now)
+    } else {
+        true
+    }
+}
+
+// Tasks are shown when public, to admins, or once a contest containing them has started,
+// to those who may take part in it.
+pub fn can_view_task(viewer: Option<Uuid>, task: &models::Task) -> bool {
+    if task.is_public || viewer.map(is_admin).unwrap_or(false) {
+        return true;
+    }
+    let now = Utc::now();
+    if let Ok(contests) = contests_of_task(task.pk) {
+        contests.iter().any(|contest| {
+            let allowed = match viewer {
+                Some(viewer) => can_participate(viewer, contest).unwrap_or(false),
+                None => contest.is_public,
+            };
+            let window = if allowed && contest.is_windowed() {
+                viewer.and_then(|viewer| participant_window(contest, viewer))
+            } else {
+                None
+            };
+            contest_shows_tasks(contest, allowed, window, now)
+        })
+    } else {
+        false
+    }
+}
+
+pub async fn read_task(viewer: Option<Uuid>, task_pk: Uuid) -> Result<TaskLoader, JudgeError> {
+    let task = find_task(task_pk).map_err(|_| JudgeError::TaskNotFound)?;
+    if !can_view_task(viewer, &task) {
+        return Err(JudgeError::PermissionDenied);
+    }
+    load_task(PathBuf::from(TASKS).join(task.internal_task_uuid.to_string()))
+        .await
+        .map_err(|_| JudgeError::TaskNotLoaded)
+}
+
+// Others' submissions are visible when the task is public or once a public contest
+// containing the task has ended.
+fn is_task_open(task_pk: Uuid) -> bool {
+    if let Ok(task) = find_task(task_pk) {
+        if task.is_public {
+            return true;
+        }
+    }
+    if let Ok(contests) = contests_of_task(task_pk) {
+        contests
+            .iter()
+            .any(|contest| contest.is_public && contest.is_ended())
+    } else {
+        false
+    }
+}
+
+// Submissions the viewer may see, at most `count` of them in the order of the query,
+// and whether more of them remain past those.
+pub async fn list_submissions(
+    viewer: Option<Uuid>,
+    mut query: SubmissionQuery,
+    contest_pk: Option<Uuid>,
+    state: Option<SubmissionState>,
+    count: Option<usize>,
+) -> Result<(Vec<(models::Submission, Option<Submission>)>, bool), JudgeError> {
+    if let Some(contest_pk) = contest_pk {
+        let contest = find_contest(contest_pk).map_err(|_| JudgeError::ContestNotFound)?;
+        let task_pks = contest_task_pks(contest_pk).map_err(|_| JudgeError::DatabaseError)?;
+        query.task_pks = Some(match query.task_pks {
+            Some(pks) => pks.into_iter().filter(|pk| task_pks.contains(pk)).collect(),
+            None => task_pks,
+        });
+        query.issued_after = Some(match query.issued_after {
+            Some(at) => at.max(contest.start_at),
+            None => contest.start_at,
+        });
+        query.issued_before = Some(match query.issued_before {
+            Some(at) => at.min(contest.end_at),
+            None => contest.end_at,
+        });
+    }
+    let admin = viewer.map(is_admin).unwrap_or(false);
+    let mut open_tasks = HashMap::new();
+    scan_submissions(query, count, search_submissions, |rows| {
+        let visible: Vec<models::Submission> = rows
+            .into_iter()
+            .filter(|submission| {
+                admin
+                    || Some(submission.user_pk) == viewer
+                    || *open_tasks
+                        .entry(submission.task_pk)
+                        .or_insert_with(|| is_task_open(submission.task_pk))
+            })
+            .collect();
+        async move {
+            let mut states = judge_states(visible.iter().map(|x| x.pk).collect()).await;
+            visible
+                .into_iter()
+                .filter_map(|submission| {
+                    let cur_state = states.remove(&submission.pk);
+                    if state.is_some() && cur_state.as_ref().map(|x| x.submission_state) != state {
+                        return None;
+                    }
+                    Some((
+                        submission,
+                        if admin {
+                            cur_state
+                        } else {
+                            cur_state.map(Submission::redacted)
+                        },
+                    ))
+                })
+                .collect()
+        }
+    })
+    .await
+}
+
+// Reads rows in batches, moving the keyset bound of the query past every batch, until
+// `keep` has kept `count` of them. Also tells whether kept rows remain past those.
+pub async fn scan_submissions<T, F, K, Fut>(
+    mut query: SubmissionQuery,
+    count: Option<usize>,
+    mut fetch: F,
+    mut keep: K,
+) -> Result<(Vec<T>, bool), JudgeError>
+where
+    F: FnMut(SubmissionQuery) -> diesel::QueryResult<Vec<models::Submission>>,
+    K: FnMut(Vec<models::Submission>) -> Fut,
+    Fut: Future<Output = Vec<T>>,
+{
+    query.limit = count.map(|count| (count + 1).max(SUBMISSION_SCAN_BATCH) as i64);
+    let mut items = vec![];
+    loop {
+        let rows = fetch(query.clone()).map_err(|_| JudgeError::DatabaseError)?;
+        let exhausted = match query.limit {
+            Some(limit) => (rows.len() as i64) < limit,
+            None => true,
+        };
+        if let Some(last) = rows.last() {
+            let bound = Some((last.issued_at, last.pk));
+            if query.oldest_first {
+                query.newer_than = bound;
+            } else {
+                query.older_than = bound;
+            }
+        }
+        items.extend(keep(rows).await);
+        if let Some(count) = count {
+            if items.len() > count {
+                items.truncate(count);
+                return Ok((items, true));
+            }
+        }
+        if exhausted {
+            return Ok((items, false));
+        }
+    }
+}
+
+// Live states of a submission for its owner and admins
+pub async fn subscribe_state(
+    user_pk: Uuid,
+    judge_uuid: Uuid,
+) -> Result<impl Stream<Item = Submission>, JudgeError> {
+    let submission = find_submission(judge_uuid).map_err(|_| JudgeError::SubmissionNotFound)?;
+    let admin = is_admin(user_pk);
+    if !admin && submission.user_pk != user_pk {
+        return Err(JudgeError::PermissionDenied);
+    }
+    let states = APPDATA
+        .judge_addr
+        .send(SubscribeMessage::Subscribe(judge_uuid))
+        .await
+        .map_err(|_| JudgeError::DatabaseError)?;
+    Ok(states.map(move |state| if admin { state } else { state.redacted() }))
+}
+
+// Source code is shown to its owner and admins, to others who could take part in the
+// contest the submission was made in once it has ended, and to those who solved the task
+// by themselves.
+pub async fn can_view_source(viewer: Uuid, submission: &models::Submission) -> bool {
+    if viewer == submission.user_pk || is_admin(viewer) {
+        return true;
+    }
+    if let Ok(contests) = contests_of_task(submission.task_pk) {
+        if contests.iter().any(|contest| {
+            contest.start_at <= submission.issued_at
+                && submission.issued_at < contest.end_at
+                && contest.is_ended()
+                && (contest.is_public || can_participate(viewer, contest).unwrap_or(false))
+        }) {
+            return true;
+        }
+    }
+    let query = SubmissionQuery {
+        user_pk: Some(viewer),
+        task_pks: Some(vec![submission.task_pk]),
+        ..Default::default()
+    };
+    if let Ok(own) = search_submissions(query) {
+        for batch in own.chunks(SUBMISSION_SCAN_BATCH) {
+            let states = judge_states(batch.iter().map(|x| x.pk).collect()).await;
+            if states.values().any(Submission::is_solved) {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+pub async fn read_source(
+    viewer: Option<Uuid>,
+    judge_uuid: Uuid,
+) -> Result<(models::Submission, Language, String), JudgeError> {
+    let viewer = viewer.ok_or(JudgeError::NotLoggedIn)?;
+    let submission = find_submission(judge_uuid).map_err(|_| JudgeError::SubmissionNotFound)?;
+    if !can_view_source(viewer, &submission).await {
+        return Err(JudgeError::PermissionDenied);
+    }
+    let lang = find_language(submission.lang_uuid).map_err(|_| JudgeError::LanguageNotFound)?;
+    if let Ok(KeyDbResponse::Data(source)) =
+        APPDATA.source_db.send(KeyDbMessage::Get(judge_uuid)).await
+    {
+        Ok((submission, lang, source))
+    } else {
+        Err(JudgeError::SourceNotFound)
+    }
+}
+
+#[get("/source/{judge_uuid}")]
+pub async fn download_source(user: Option<Identity>, path: web::Path<Uuid>) -> HttpResponse {
+    let viewer = user.map(|user| user.id().unwrap().parse::<Uuid>().unwrap());
+    match read_source(viewer, path.into_inner()).await {
+        Ok((submission, lang, source)) => HttpResponse::Ok()
+            .content_type("text/plain; charset=utf-8")
+            .insert_header(ContentDisposition {
+                disposition: DispositionType::Attachment,
+                parameters: vec![DispositionParam::Filename(format!(
+                    "{}.{}",
+                    submission.pk, lang.file_extension
+                ))],
+            })
+            .body(source),
+        Err(err) => HttpResponse::Ok()
+            .content_type("application/json")
+            .json(ResponseBlock {
+                status: false,
+                body: err,
+            }),
+    }
+}
@@ -1,5 +1,6 @@
 pub mod accounts;
 pub mod constants;
+pub mod contests;
 pub mod graphql;
 pub mod handshake;
 pub mod judge;
@@ -1,5 +1,6 @@
 use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
 use uuid::Uuid;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -12,6 +13,8 @@ pub struct Host {
 pub struct Web {
     pub host: String,
     pub enable_gql_playground: bool,
+    #[serde(default)]
+    pub trusted_proxies: Vec<IpAddr>, // whose forwarded headers give the client address
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -26,10 +29,115 @@ pub struct General {
     pub db_threads: Option<usize>,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum MailTransportKind {
+    Smtp,
+    File, // writes mails into `file_dir` instead of sending them, for testing
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Mail {
+    pub transport: MailTransportKind,
+    pub from: String,
+    pub smtp_host: Option<String>,
+    pub smtp_port: Option<u16>,
+    pub smtp_user: Option<String>,
+    pub smtp_pass: Option<String>,
+    pub file_dir: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct Accounts {
+    pub secret: Option<String>, // signs tokens sent by mail, random on every start if not set
+    pub public_url: Option<String>, // frontend address put in links of mails
+    #[serde(default)]
+    pub require_email_verification: bool,
+    #[serde(default)]
+    pub require_admin_2fa: bool, // admin rights are withheld until TOTP is enabled
+    pub totp_issuer: Option<String>, // shown in authenticator apps
+}
+
+fn default_oidc_scopes() -> Vec<String> {
+    vec![String::from("email"), String::from("profile")]
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OidcProvider {
+    pub name: String, // used in the login and callback paths
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    pub redirect_url: String, // should point to /api/accounts/oidc/{name}/callback
+    #[serde(default = "default_oidc_scopes")]
+    pub scopes: Vec<String>, // asked for in addition to `openid`
+    #[serde(default = "default_true")]
+    pub auto_provision: bool, // creates accounts for unknown identities
+    #[serde(default)]
+    pub link_by_email: bool, // trusts verified emails of the provider to find existing users
+}
+
+// Counts per client address or account. Fields left out take the defaults.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateLimits {
+    pub login_per_minute: i64, // per client address
+    pub lockout_failures: i64, // wrong passwords or codes before the account is locked
+    pub lockout_minutes: usize,
+    pub register_per_hour: i64, // per client address
+    pub reset_per_hour: i64,    // per client address
+    pub reset_per_account_hour: i64,
+    pub verify_per_hour: i64, // verification mails sent again, per client address
+    pub verify_per_account_hour: i64,
+    pub submit_per_minute: Option<i64>, // per account, unlimited if not set
+}
+
+impl Default for RateLimits {
+    fn default() -> Self {
+        Self {
+            login_per_minute: 20,
+            lockout_failures: 5,
+            lockout_minutes: 15,
+            register_per_hour: 10,
+            reset_per_hour: 10,
+            reset_per_account_hour: 3,
+            verify_per_hour: 10,
+            verify_per_account_hour: 3,
+            submit_per_minute: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Plagiarism {
+    pub check_interval_minutes: u64, // for contests that ended meanwhile, 0 turns it off
+}
+
+impl Default for Plagiarism {
+    fn default() -> Self {
+        Self {
+            check_interval_minutes: 10,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Config {
     pub general: General,
     pub host: Host,
     pub web: Web,
     pub redis: Redis,
+    #[serde(default)]
+    pub accounts: Accounts,
+    pub mail: Option<Mail>,
+    #[serde(default)]
+    pub oidc: Vec<OidcProvider>,
+    #[serde(default)]
+    pub rate_limits: RateLimits,
+    #[serde(default)]
+    pub plagiarism: Plagiarism,
 }
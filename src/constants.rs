@@ -5,3 +5,7 @@ pub const SOURCE_DATABASE: &'static str = "data/source_db";
 pub const TASKS: &'static str = "data/tasks";
 pub const VACUUM_INTERVAL_SEC: f64 = 2.0;
 pub const MAX_FILE_SIZE_KB: f64 = 128000.0;
+pub const FEEDBACK_PREVIEW_LENGTH: usize = 256;
+pub const SUBMISSION_SCAN_BATCH: usize = 100;
+pub const SUBMISSION_PAGE_SIZE: usize = 20;
+pub const SUBMISSION_PAGE_MAX: usize = 100;
@@ -1,2 +0,0 @@
-use actix::prelude::*;
-use serde::{Deserialize, Serialize};
@@ -0,0 +1,227 @@
+use chrono::prelude::*;
+use serde_json::{json, Value};
+
+use super::export::*;
+use super::standings::{StandingRow, PENALTY_MINUTES};
+
+// Objects of the CLICS Contest API, https://ccs-specs.icpc.io/2022-07/contest_api
+
+pub const JUDGEMENT_TYPES: &[(&str, &str, bool, bool)] = &[
+    // id, name, penalty, solved
+    ("AC", "correct", false, true),
+    ("WA", "wrong answer", true, false),
+    ("TLE", "time limit exceeded", true, false),
+    ("MLE", "memory limit exceeded", true, false),
+    ("RTE", "run-time error", true, false),
+    ("CE", "compiler error", false, false),
+];
+
+pub fn reltime(seconds: i64) -> String {
+    let sign = if seconds < 0 { "-" } else { "" };
+    let seconds = seconds.abs();
+    format!(
+        "{}{}:{:02}:{:02}.000",
+        sign,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+pub fn abstime(at: DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::Millis, false)
+}
+
+// Contest times are measured from the contest start, so the duration spans the whole
+// contest even when every participant only gets a shorter personal window. Standings are
+// ranked by scores, penalty time only breaking ties.
+pub fn contest(contest: &ExportContest) -> Value {
+    json!({
+        "id": contest.pk,
+        "name": contest.name,
+        "formal_name": contest.name,
+        "start_time": abstime(contest.start_at),
+        "duration": reltime((contest.end_at - contest.start_at).num_seconds()),
+        "scoreboard_type": "score",
+        "penalty_time": PENALTY_MINUTES,
+    })
+}
+
+pub fn state(contest: &ExportContest, now: DateTime<Utc>) -> Value {
+    let ended = contest.end_at <= now;
+    json!({
+        "started": Some(abstime(contest.start_at)).filter(|_| contest.start_at <= now),
+        "ended": Some(abstime(contest.end_at)).filter(|_| ended),
+        "finalized": Some(abstime(contest.end_at)).filter(|_| ended),
+        "end_of_updates": Some(abstime(contest.end_at)).filter(|_| ended),
+    })
+}
+
+pub fn judgement_types() -> Vec<Value> {
+    JUDGEMENT_TYPES
+        .iter()
+        .map(|(id, name, penalty, solved)| {
+            json!({ "id": id, "name": name, "penalty": penalty, "solved": solved })
+        })
+        .collect()
+}
+
+pub fn problems(tasks: &[ExportTask]) -> Vec<Value> {
+    tasks
+        .iter()
+        .enumerate()
+        .map(|(ordinal, task)| {
+            json!({
+                "id": task.pk,
+                "label": task.label,
+                "name": task.name,
+                "ordinal": ordinal,
+                "max_score": task.max_score,
+            })
+        })
+        .collect()
+}
+
+pub fn teams(users: &[ExportUser]) -> Vec<Value> {
+    users
+        .iter()
+        .map(|user| {
+            json!({
+                "id": user.pk,
+                "name": user.id,
+                "display_name": user.name,
+            })
+        })
+        .collect()
+}
+
+pub fn submission(contest: &ExportContest, submission: &ExportSubmission) -> Value {
+    json!({
+        "id": submission.pk,
+        "language_id": submission.language,
+        "problem_id": submission.task_pk,
+        "team_id": submission.user_pk,
+        "time": abstime(submission.issued_at),
+        "contest_time": reltime((submission.issued_at - contest.start_at).num_seconds()),
+    })
+}
+
+// Judging times are not kept, so judgements are stamped with the submission time.
+pub fn judgement(contest: &ExportContest, submission: &ExportSubmission) -> Value {
+    let time = abstime(submission.issued_at);
+    let contest_time = reltime((submission.issued_at - contest.start_at).num_seconds());
+    json!({
+        "id": submission.pk,
+        "submission_id": submission.pk,
+        "judgement_type_id": submission.judgement,
+        "score": submission.score,
+        "start_time": time,
+        "start_contest_time": contest_time,
+        "end_time": time,
+        "end_contest_time": contest_time,
+    })
+}
+
+pub fn runs(contest: &ExportContest, submission: &ExportSubmission) -> Vec<Value> {
+    let time = abstime(submission.issued_at);
+    let contest_time = reltime((submission.issued_at - contest.start_at).num_seconds());
+    submission
+        .runs
+        .iter()
+        .enumerate()
+        .map(|(idx, run)| {
+            json!({
+                "id": format!("{}-{}", submission.pk, idx + 1),
+                "judgement_id": submission.pk,
+                "ordinal": idx + 1,
+                "judgement_type_id": run.judgement,
+                "time": time,
+                "contest_time": contest_time,
+                "run_time": run.run_time,
+            })
+        })
+        .collect()
+}
+
+pub fn scoreboard(results: &ContestResults, now: DateTime<Utc>) -> Value {
+    let rows: Vec<Value> = results
+        .standings
+        .iter()
+        .map(|row: &StandingRow| {
+            let problems: Vec<Value> = row
+                .tasks
+                .iter()
+                .map(|x| {
+                    let mut problem = json!({
+                        "problem_id": x.task_pk,
+                        "num_judged": x.attempts,
+                        "num_pending": 0,
+                        "solved": x.solved_at.is_some(),
+                        "score": x.score,
+                    });
+                    if let Some(at) = x.solved_at {
+                        problem["time"] = json!(at / 60);
+                    }
+                    problem
+                })
+                .collect();
+            json!({
+                "rank": row.rank,
+                "team_id": row.user_pk,
+                "score": {
+                    "num_solved": row.solved,
+                    "total_time": row.penalty,
+                    "score": row.total,
+                },
+                "problems": problems,
+            })
+        })
+        .collect();
+    let at = now.min(results.contest.end_at);
+    json!({
+        "time": abstime(at),
+        "contest_time": reltime((at - results.contest.start_at).num_seconds()),
+        "state": state(&results.contest, now),
+        "rows": rows,
+    })
+}
+
+fn event(kind: &str, data: Value) -> Value {
+    json!({ "type": kind, "id": data["id"].clone(), "data": data })
+}
+
+// NDJSON lines of the whole contest in the order a live feed would have sent them
+pub fn event_feed(results: &ContestResults) -> Vec<Value> {
+    let now = Utc::now();
+    let mut events = vec![json!({
+        "type": "contest",
+        "id": null,
+        "data": contest(&results.contest),
+    })];
+    events.extend(
+        judgement_types()
+            .into_iter()
+            .map(|x| event("judgement-types", x)),
+    );
+    events.extend(
+        problems(&results.tasks)
+            .into_iter()
+            .map(|x| event("problems", x)),
+    );
+    events.extend(teams(&results.users).into_iter().map(|x| event("teams", x)));
+    for x in results.submissions.iter() {
+        events.push(event("submissions", submission(&results.contest, x)));
+        events.push(event("judgements", judgement(&results.contest, x)));
+        events.extend(
+            runs(&results.contest, x)
+                .into_iter()
+                .map(|run| event("runs", run)),
+        );
+    }
+    events.push(json!({
+        "type": "state",
+        "id": null,
+        "data": state(&results.contest, now),
+    }));
+    events
+}
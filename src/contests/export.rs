@@ -0,0 +1,214 @@
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use super::clics;
+use super::standings::StandingRow;
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+    Clics, // event feed of the CLICS contest package, as NDJSON
+    Cms,   // dump for the CMS ranking web server
+}
+
+impl ExportFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "csv" => Some(Self::Csv),
+            "json" => Some(Self::Json),
+            "clics" => Some(Self::Clics),
+            "cms" => Some(Self::Cms),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Csv => "text/csv; charset=utf-8",
+            Self::Json | Self::Cms => "application/json",
+            Self::Clics => "application/x-ndjson",
+        }
+    }
+
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            Self::Csv => "standings.csv",
+            Self::Json => "results.json",
+            Self::Clics => "event-feed.ndjson",
+            Self::Cms => "ranking.json",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExportContest {
+    pub pk: Uuid,
+    pub name: String,
+    pub start_at: DateTime<Utc>,
+    pub end_at: DateTime<Utc>,
+    pub duration: i64, // in seconds
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExportTask {
+    pub pk: Uuid,
+    pub label: String, // A, B, ... in order of the contest
+    pub name: String,
+    pub max_score: f64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExportUser {
+    pub pk: Uuid,
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExportSubmission {
+    pub pk: Uuid,
+    pub user_pk: Uuid,
+    pub task_pk: Uuid,
+    pub language: String,
+    pub issued_at: DateTime<Utc>,
+    pub elapsed: i64, // in seconds since the start of the participant's window
+    pub score: f64,
+    pub judgement: String, // CLICS judgement type
+    #[serde(default)]
+    pub runs: Vec<ExportRun>, // in order of testcase names
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExportRun {
+    pub judgement: String,
+    pub run_time: f64, // in seconds
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ContestResults {
+    pub contest: ExportContest,
+    pub tasks: Vec<ExportTask>,
+    pub users: Vec<ExportUser>,
+    pub standings: Vec<StandingRow>,
+    pub submissions: Vec<ExportSubmission>, // oldest first
+}
+
+pub fn task_label(idx: usize) -> String {
+    let mut label = String::new();
+    let mut idx = idx + 1;
+    while idx > 0 {
+        idx -= 1;
+        label.insert(0, (b'A' + (idx % 26) as u8) as char);
+        idx /= 26;
+    }
+    label
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains(|x| x == ',' || x == '"' || x == '\n' || x == '\r') {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn to_csv(results: &ContestResults) -> String {
+    let users: HashMap<Uuid, &ExportUser> = results.users.iter().map(|x| (x.pk, x)).collect();
+    let mut header = vec![
+        String::from("rank"),
+        String::from("id"),
+        String::from("name"),
+    ];
+    header.extend(results.tasks.iter().map(|x| x.label.clone()));
+    header.extend(["total", "solved", "penalty"].iter().map(|x| x.to_string()));
+    let mut lines = vec![header.join(",")];
+    for row in results.standings.iter() {
+        let (id, name) = users
+            .get(&row.user_pk)
+            .map(|x| (x.id.clone(), x.name.clone()))
+            .unwrap_or_default();
+        let mut fields = vec![row.rank.to_string(), csv_field(&id), csv_field(&name)];
+        fields.extend(row.tasks.iter().map(|x| x.score.to_string()));
+        fields.push(row.total.to_string());
+        fields.push(row.solved.to_string());
+        fields.push(row.penalty.to_string());
+        lines.push(fields.join(","));
+    }
+    lines.join("\n") + "\n"
+}
+
+// https://github.com/cms-dev/cms/blob/master/cmsranking
+fn to_cms(results: &ContestResults) -> Value {
+    let contest = &results.contest;
+    let mut tasks = serde_json::Map::new();
+    for (order, task) in results.tasks.iter().enumerate() {
+        tasks.insert(
+            task.pk.to_string(),
+            json!({
+                "name": task.name,
+                "short_name": task.label,
+                "contest": contest.pk,
+                "order": order,
+                "max_score": task.max_score,
+                "extra_headers": [],
+                "score_precision": 2,
+                "score_mode": "max",
+            }),
+        );
+    }
+    let mut users = serde_json::Map::new();
+    for user in results.users.iter() {
+        users.insert(
+            user.pk.to_string(),
+            json!({ "f_name": user.name, "l_name": "", "team": null }),
+        );
+    }
+    let mut submissions = serde_json::Map::new();
+    let mut subchanges = serde_json::Map::new();
+    for submission in results.submissions.iter() {
+        let time = submission.issued_at.timestamp();
+        submissions.insert(
+            submission.pk.to_string(),
+            json!({ "user": submission.user_pk, "task": submission.task_pk, "time": time }),
+        );
+        subchanges.insert(
+            format!("{}{}", time, submission.pk),
+            json!({
+                "submission": submission.pk,
+                "time": time,
+                "score": submission.score,
+                "extra": [],
+            }),
+        );
+    }
+    json!({
+        "contests": {
+            contest.pk.to_string(): {
+                "name": contest.name,
+                "begin": contest.start_at.timestamp(),
+                "end": contest.end_at.timestamp(),
+                "score_precision": 2,
+            }
+        },
+        "tasks": tasks,
+        "users": users,
+        "submissions": submissions,
+        "subchanges": subchanges,
+    })
+}
+
+pub fn export(results: &ContestResults, format: ExportFormat) -> String {
+    match format {
+        ExportFormat::Csv => to_csv(results),
+        ExportFormat::Json => serde_json::to_string(results).unwrap(),
+        ExportFormat::Clics => clics::event_feed(results)
+            .iter()
+            .map(|x| x.to_string() + "\n")
+            .collect(),
+        ExportFormat::Cms => to_cms(results).to_string(),
+    }
+}
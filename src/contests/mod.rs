@@ -0,0 +1,134 @@
+pub mod clics;
+pub mod export;
+pub mod standings;
+
+use actix::prelude::*;
+use async_std::channel::{unbounded, Receiver, Sender};
+use async_std::task::spawn;
+use futures_util::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::db::models::Clarification;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Subscriber {
+    pub user_pk: Uuid,
+    pub is_jury: bool, // jury members also receive questions of everyone
+}
+
+impl Subscriber {
+    pub fn can_see(&self, clarification: &Clarification) -> bool {
+        self.is_jury || clarification.is_public || clarification.user_pk == self.user_pk
+    }
+}
+
+#[derive(Clone, Debug, Message)]
+#[rtype(result = "()")]
+pub enum ClarificationMessage {
+    Publish(Clarification),
+}
+
+#[derive(Clone, Debug, Message)]
+#[rtype(result = "ClarificationStream")]
+pub enum ClarificationSubscribeMessage {
+    Subscribe(Uuid, Subscriber), // contest
+}
+
+#[derive(Clone, Debug, Message)]
+#[rtype(result = "()")]
+pub enum ClarificationDropMessage {
+    Drop(Uuid, usize),
+}
+
+#[derive(MessageResponse)]
+pub struct ClarificationStream(
+    Uuid,
+    Addr<ClarificationService>,
+    usize,
+    Receiver<Clarification>,
+);
+
+impl Drop for ClarificationStream {
+    fn drop(&mut self) {
+        self.1
+            .do_send(ClarificationDropMessage::Drop(self.0, self.2));
+    }
+}
+
+impl Stream for ClarificationStream {
+    type Item = Clarification;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        self.3.poll_next_unpin(cx)
+    }
+}
+
+// Pushes new questions, answers and announcements to subscribers of each contest
+#[derive(Default)]
+pub struct ClarificationService {
+    pub subscribers: HashMap<Uuid, Vec<Option<(Subscriber, Sender<Clarification>)>>>,
+}
+
+impl Actor for ClarificationService {
+    type Context = Context<Self>;
+
+    fn started(&mut self, _ctx: &mut Context<Self>) {
+        info!("Started clarification service");
+    }
+}
+
+impl Handler<ClarificationSubscribeMessage> for ClarificationService {
+    type Result = ClarificationStream;
+
+    fn handle(
+        &mut self,
+        msg: ClarificationSubscribeMessage,
+        ctx: &mut Self::Context,
+    ) -> Self::Result {
+        match msg {
+            ClarificationSubscribeMessage::Subscribe(contest_pk, subscriber) => {
+                let (tx, rx) = unbounded();
+                let subscribers = self.subscribers.entry(contest_pk).or_insert_with(Vec::new);
+                subscribers.push(Some((subscriber, tx)));
+                ClarificationStream(contest_pk, ctx.address(), subscribers.len() - 1, rx)
+            }
+        }
+    }
+}
+
+impl Handler<ClarificationDropMessage> for ClarificationService {
+    type Result = ();
+
+    fn handle(&mut self, msg: ClarificationDropMessage, _ctx: &mut Self::Context) -> Self::Result {
+        if let ClarificationDropMessage::Drop(contest_pk, idx) = msg {
+            if let Some(v) = self.subscribers.get_mut(&contest_pk) {
+                v[idx] = None;
+            }
+        }
+    }
+}
+
+impl Handler<ClarificationMessage> for ClarificationService {
+    type Result = ();
+
+    fn handle(&mut self, msg: ClarificationMessage, _ctx: &mut Self::Context) -> Self::Result {
+        match msg {
+            ClarificationMessage::Publish(clarification) => {
+                if let Some(v) = self.subscribers.get(&clarification.contest_pk) {
+                    for (subscriber, tx) in v.iter().flatten() {
+                        if subscriber.can_see(&clarification) {
+                            let tx = tx.clone();
+                            let item = clarification.clone();
+                            spawn(async move { tx.send(item).await });
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
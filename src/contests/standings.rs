@@ -0,0 +1,120 @@
+use async_graphql::Enum;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+// minutes added to the penalty for every rejected attempt before the first full score
+pub const PENALTY_MINUTES: i64 = 20;
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, Enum)]
+pub enum ParticipationKind {
+    Official,
+    Virtual,   // replay of an ended contest in a personal window
+    Upsolving, // after the contest, outside of any window
+}
+
+#[derive(Clone, Debug)]
+pub struct Attempt {
+    pub user_pk: Uuid,
+    pub kind: ParticipationKind,
+    pub task_pk: Uuid,
+    pub elapsed: i64, // in seconds since the start of the user's window
+    pub score: f64,
+    pub solved: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct TaskResult {
+    pub task_pk: Uuid,
+    pub score: f64,
+    pub attempts: usize,        // up to the first full score
+    pub solved_at: Option<i64>, // in seconds
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct StandingRow {
+    pub rank: usize,
+    pub user_pk: Uuid,
+    pub kind: ParticipationKind,
+    pub total: f64,
+    pub solved: usize,
+    pub penalty: i64,           // in minutes
+    pub tasks: Vec<TaskResult>, // in order of `task_pks`
+}
+
+impl TaskResult {
+    fn penalty(&self) -> i64 {
+        match self.solved_at {
+            Some(at) => at / 60 + PENALTY_MINUTES * (self.attempts as i64 - 1),
+            None => 0,
+        }
+    }
+}
+
+// Ranks by total score, then by penalty. Attempts at or after `cutoff` seconds are
+// ignored, which freezes every row at the same elapsed time for virtual scoreboards.
+pub fn build_standings(
+    task_pks: &[Uuid],
+    mut attempts: Vec<Attempt>,
+    cutoff: Option<i64>,
+) -> Vec<StandingRow> {
+    attempts.retain(|x| cutoff.map_or(true, |cutoff| x.elapsed < cutoff));
+    attempts.sort_by_key(|x| x.elapsed);
+    let mut results: HashMap<(Uuid, ParticipationKind), Vec<TaskResult>> = HashMap::new();
+    for attempt in attempts {
+        let idx = match task_pks.iter().position(|x| *x == attempt.task_pk) {
+            Some(idx) => idx,
+            None => continue,
+        };
+        let tasks = results
+            .entry((attempt.user_pk, attempt.kind))
+            .or_insert_with(|| {
+                task_pks
+                    .iter()
+                    .map(|task_pk| TaskResult {
+                        task_pk: *task_pk,
+                        score: 0.0,
+                        attempts: 0,
+                        solved_at: None,
+                    })
+                    .collect()
+            });
+        let result = &mut tasks[idx];
+        result.score = result.score.max(attempt.score);
+        if result.solved_at.is_none() {
+            result.attempts += 1;
+            if attempt.solved {
+                result.solved_at = Some(attempt.elapsed);
+            }
+        }
+    }
+    let mut rows: Vec<StandingRow> = results
+        .into_iter()
+        .map(|((user_pk, kind), tasks)| StandingRow {
+            rank: 0,
+            user_pk,
+            kind,
+            total: tasks.iter().map(|x| x.score).sum(),
+            solved: tasks.iter().filter(|x| x.solved_at.is_some()).count(),
+            penalty: tasks.iter().map(|x| x.penalty()).sum(),
+            tasks,
+        })
+        .collect();
+    rows.sort_by(|a, b| {
+        b.total
+            .total_cmp(&a.total)
+            .then(a.penalty.cmp(&b.penalty))
+            .then(a.user_pk.cmp(&b.user_pk))
+    });
+    for idx in 0..rows.len() {
+        rows[idx].rank = if idx > 0
+            && rows[idx - 1].total == rows[idx].total
+            && rows[idx - 1].penalty == rows[idx].penalty
+        {
+            rows[idx - 1].rank
+        } else {
+            idx + 1
+        };
+    }
+    rows
+}
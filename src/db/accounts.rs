@@ -1,25 +1,35 @@
+use chrono::prelude::*;
+use diesel::pg::Pg;
 use diesel::prelude::*;
-use sha3::{Digest, Sha3_256};
 use uuid::Uuid;
 
 use super::models::*;
 use super::schema::*;
 use crate::api::accounts::errors::AccountError;
 use crate::middlewares::postgresql::establish_connection;
+use crate::CONFIG;
 
+diesel::sql_function!(fn lower(x: diesel::sql_types::Text) -> diesel::sql_types::Text);
+
+// Name of the unique index on `lower(email)`
+pub const EMAIL_KEY: &'static str = "users_email_lower_key";
+
+// `pass` is already hashed
 pub fn login(id: String, pass: String) -> QueryResult<(AccountError, Uuid)> {
     let mut db = establish_connection();
-    let mut hasher = Sha3_256::new();
-    hasher.update(pass.as_bytes());
-    let pass_hashed = hasher.finalize();
     let mut items = users::table
         .filter(users::dsl::id.eq(id))
+        .filter(users::dsl::deleted_at.is_null())
         .load::<User>(&mut db)?;
     if let Some(user) = items.pop() {
-        if hex::encode(pass_hashed) == user.pass {
-            Ok((AccountError::None, user.pk))
-        } else {
+        if pass != user.pass {
             Ok((AccountError::PassNotMatched, Uuid::nil()))
+        } else if user.is_banned {
+            Ok((AccountError::Banned, Uuid::nil()))
+        } else if CONFIG.accounts.require_email_verification && !user.email_verified {
+            Ok((AccountError::EmailNotVerified, Uuid::nil()))
+        } else {
+            Ok((AccountError::None, user.pk))
         }
     } else {
         Ok((AccountError::UserNotExists, Uuid::nil()))
@@ -35,6 +45,7 @@ pub fn by_id(id: String) -> QueryResult<User> {
     let mut db = establish_connection();
     users::table
         .filter(users::dsl::id.eq(id))
+        .filter(users::dsl::deleted_at.is_null())
         .first::<User>(&mut db)
 }
 
@@ -44,3 +55,320 @@ pub fn register(form: NewUser) -> QueryResult<User> {
         .values(&form)
         .get_result(&mut db)
 }
+
+// Addresses are compared case-insensitively, as they are unique that way.
+pub fn by_email(email: String) -> QueryResult<User> {
+    let mut db = establish_connection();
+    users::table
+        .filter(lower(users::dsl::email).eq(email.to_lowercase()))
+        .filter(users::dsl::deleted_at.is_null())
+        .first::<User>(&mut db)
+}
+
+// only while the address is still the one the token was issued for
+pub fn verify_email(pk: Uuid, email: String) -> QueryResult<usize> {
+    let mut db = establish_connection();
+    diesel::update(users::table.find(pk).filter(users::dsl::email.eq(email)))
+        .set(users::dsl::email_verified.eq(true))
+        .execute(&mut db)
+}
+
+// `pass` is already hashed
+pub fn set_password(pk: Uuid, pass: String) -> QueryResult<usize> {
+    let mut db = establish_connection();
+    diesel::update(users::table.find(pk))
+        .set(users::dsl::pass.eq(pass))
+        .execute(&mut db)
+}
+
+pub fn update_profile(pk: Uuid, changes: UserChanges) -> QueryResult<User> {
+    let mut db = establish_connection();
+    diesel::update(users::table.find(pk))
+        .set(&changes)
+        .get_result(&mut db)
+}
+
+// Personal data is wiped but the row stays, so that submissions and standings keep
+// pointing at a user.
+pub fn anonymize_user(pk: Uuid) -> QueryResult<usize> {
+    let mut db = establish_connection();
+    diesel::update(users::table.find(pk))
+        .set((
+            users::dsl::id.eq(format!("deleted-{}", pk.as_simple())),
+            users::dsl::pass.eq(""),
+            users::dsl::email.eq(""),
+            users::dsl::email_verified.eq(false),
+            users::dsl::first_name.eq(""),
+            users::dsl::last_name.eq(""),
+            users::dsl::deleted_at.eq(Some(Utc::now())),
+        ))
+        .execute(&mut db)
+}
+
+pub fn teams_of(user_pk: Uuid) -> QueryResult<Vec<Team>> {
+    let mut db = establish_connection();
+    teams::table
+        .inner_join(team_users::table.on(team_users::dsl::team_pk.eq(teams::dsl::pk)))
+        .filter(team_users::dsl::user_pk.eq(user_pk))
+        .select(teams::all_columns)
+        .load::<Team>(&mut db)
+}
+
+pub fn contest_accesses_of(user_pk: Uuid) -> QueryResult<Vec<ContestAccessibleUser>> {
+    let mut db = establish_connection();
+    contest_accessible_users::table
+        .filter(contest_accessible_users::dsl::user_pk.eq(user_pk))
+        .load::<ContestAccessibleUser>(&mut db)
+}
+
+pub fn virtual_participations_by(user_pk: Uuid) -> QueryResult<Vec<VirtualParticipation>> {
+    let mut db = establish_connection();
+    virtual_participations::table
+        .filter(virtual_participations::dsl::user_pk.eq(user_pk))
+        .load::<VirtualParticipation>(&mut db)
+}
+
+fn user_filter(keyword: &Option<String>) -> users::BoxedQuery<'static, Pg> {
+    let mut items = users::table
+        .filter(users::dsl::deleted_at.is_null())
+        .into_boxed();
+    if let Some(keyword) = keyword {
+        let pattern = format!(
+            "%{}%",
+            keyword
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        );
+        items = items.filter(
+            users::dsl::id
+                .ilike(pattern.clone())
+                .or(users::dsl::email.ilike(pattern.clone()))
+                .or(users::dsl::first_name.ilike(pattern.clone()))
+                .or(users::dsl::last_name.ilike(pattern)),
+        );
+    }
+    items
+}
+
+// Users whose id, email or name contains `keyword`, with the number of all matches
+pub fn search_users(
+    keyword: Option<String>,
+    offset: i64,
+    limit: i64,
+) -> QueryResult<(i64, Vec<User>)> {
+    let mut db = establish_connection();
+    let total = user_filter(&keyword).count().get_result(&mut db)?;
+    let items = user_filter(&keyword)
+        .order(users::dsl::id.asc())
+        .offset(offset)
+        .limit(limit)
+        .load::<User>(&mut db)?;
+    Ok((total, items))
+}
+
+// Account changes made by admins
+pub enum AdminChange {
+    Banned(bool),
+    Permission(i32),
+    Password(String), // already hashed
+    RevokeApiTokens,
+}
+
+// Applies the changes of an admin action together with its audit log, so that no action is
+// done without a record of it.
+pub fn apply_admin_changes(
+    user_pk: Uuid,
+    changes: Vec<AdminChange>,
+    log: AuditLog,
+) -> QueryResult<()> {
+    let mut db = establish_connection();
+    db.transaction(|db| {
+        for change in changes {
+            match change {
+                AdminChange::Banned(is_banned) => diesel::update(users::table.find(user_pk))
+                    .set(users::dsl::is_banned.eq(is_banned))
+                    .execute(db)?,
+                AdminChange::Permission(permission) => diesel::update(users::table.find(user_pk))
+                    .set(users::dsl::permission.eq(permission))
+                    .execute(db)?,
+                AdminChange::Password(pass) => diesel::update(users::table.find(user_pk))
+                    .set(users::dsl::pass.eq(pass))
+                    .execute(db)?,
+                AdminChange::RevokeApiTokens => {
+                    diesel::delete(api_tokens::table.filter(api_tokens::dsl::user_pk.eq(user_pk)))
+                        .execute(db)?
+                }
+            };
+        }
+        diesel::insert_into(audit_logs::table)
+            .values(&log)
+            .execute(db)
+            .map(|_| ())
+    })
+}
+
+pub fn audit_logs(target_pk: Option<Uuid>, offset: i64, limit: i64) -> QueryResult<Vec<AuditLog>> {
+    let mut db = establish_connection();
+    let mut items = audit_logs::table.into_boxed();
+    if let Some(target_pk) = target_pk {
+        items = items.filter(audit_logs::dsl::target_pk.eq(target_pk));
+    }
+    items
+        .order(audit_logs::dsl::created_at.desc())
+        .offset(offset)
+        .limit(limit)
+        .load::<AuditLog>(&mut db)
+}
+
+pub fn find_external_identity(provider: String, subject: String) -> QueryResult<ExternalIdentity> {
+    let mut db = establish_connection();
+    external_identities::table
+        .filter(external_identities::dsl::provider.eq(provider))
+        .filter(external_identities::dsl::subject.eq(subject))
+        .first(&mut db)
+}
+
+pub fn external_identities_of(user_pk: Uuid) -> QueryResult<Vec<ExternalIdentity>> {
+    let mut db = establish_connection();
+    external_identities::table
+        .filter(external_identities::dsl::user_pk.eq(user_pk))
+        .load::<ExternalIdentity>(&mut db)
+}
+
+pub fn link_external_identity(form: ExternalIdentity) -> QueryResult<ExternalIdentity> {
+    let mut db = establish_connection();
+    diesel::insert_into(external_identities::table)
+        .values(&form)
+        .get_result(&mut db)
+}
+
+pub fn unlink_all_identities(user_pk: Uuid) -> QueryResult<usize> {
+    let mut db = establish_connection();
+    diesel::delete(external_identities::table.filter(external_identities::dsl::user_pk.eq(user_pk)))
+        .execute(&mut db)
+}
+
+pub fn unlink_external_identity(user_pk: Uuid, provider: String) -> QueryResult<usize> {
+    let mut db = establish_connection();
+    diesel::delete(
+        external_identities::table
+            .filter(external_identities::dsl::user_pk.eq(user_pk))
+            .filter(external_identities::dsl::provider.eq(provider)),
+    )
+    .execute(&mut db)
+}
+
+pub fn new_api_token(form: ApiToken) -> QueryResult<ApiToken> {
+    let mut db = establish_connection();
+    diesel::insert_into(api_tokens::table)
+        .values(&form)
+        .get_result(&mut db)
+}
+
+pub fn find_api_token(pk: Uuid) -> QueryResult<ApiToken> {
+    let mut db = establish_connection();
+    api_tokens::table.find(pk).first(&mut db)
+}
+
+pub fn api_token_by_hash(token_hash: String) -> QueryResult<ApiToken> {
+    let mut db = establish_connection();
+    api_tokens::table
+        .filter(api_tokens::dsl::token_hash.eq(token_hash))
+        .first(&mut db)
+}
+
+pub fn api_tokens_of(user_pk: Uuid) -> QueryResult<Vec<ApiToken>> {
+    let mut db = establish_connection();
+    api_tokens::table
+        .filter(api_tokens::dsl::user_pk.eq(user_pk))
+        .order(api_tokens::dsl::created_at.desc())
+        .load::<ApiToken>(&mut db)
+}
+
+pub fn touch_api_token(pk: Uuid) -> QueryResult<usize> {
+    let mut db = establish_connection();
+    diesel::update(api_tokens::table.find(pk))
+        .set(api_tokens::dsl::last_used_at.eq(Some(Utc::now())))
+        .execute(&mut db)
+}
+
+pub fn delete_api_token(pk: Uuid) -> QueryResult<usize> {
+    let mut db = establish_connection();
+    diesel::delete(api_tokens::table.find(pk)).execute(&mut db)
+}
+
+pub fn delete_api_tokens_of(user_pk: Uuid) -> QueryResult<usize> {
+    let mut db = establish_connection();
+    diesel::delete(api_tokens::table.filter(api_tokens::dsl::user_pk.eq(user_pk))).execute(&mut db)
+}
+
+// A new secret starts disabled until a code of it is confirmed.
+pub fn set_totp(pk: Uuid, secret: Option<String>, enabled: bool) -> QueryResult<usize> {
+    let mut db = establish_connection();
+    diesel::update(users::table.find(pk))
+        .set((
+            users::dsl::totp_secret.eq(secret),
+            users::dsl::totp_enabled.eq(enabled),
+            users::dsl::totp_last_step.eq(None::<i64>),
+        ))
+        .execute(&mut db)
+}
+
+// only moves forward, so that a code is accepted once even by concurrent logins
+pub fn use_totp_step(pk: Uuid, step: i64) -> QueryResult<usize> {
+    let mut db = establish_connection();
+    diesel::update(
+        users::table.find(pk).filter(
+            users::dsl::totp_last_step
+                .is_null()
+                .or(users::dsl::totp_last_step.lt(step)),
+        ),
+    )
+    .set(users::dsl::totp_last_step.eq(Some(step)))
+    .execute(&mut db)
+}
+
+pub fn replace_recovery_codes(user_pk: Uuid, code_hashes: Vec<String>) -> QueryResult<usize> {
+    let mut db = establish_connection();
+    db.transaction(|db| {
+        diesel::delete(recovery_codes::table.filter(recovery_codes::dsl::user_pk.eq(user_pk)))
+            .execute(db)?;
+        let items: Vec<RecoveryCode> = code_hashes
+            .into_iter()
+            .map(|code_hash| RecoveryCode {
+                pk: Uuid::new_v4(),
+                user_pk,
+                code_hash,
+                used_at: None,
+            })
+            .collect();
+        if items.is_empty() {
+            return Ok(0);
+        }
+        diesel::insert_into(recovery_codes::table)
+            .values(&items)
+            .execute(db)
+    })
+}
+
+pub fn use_recovery_code(user_pk: Uuid, code_hash: String) -> QueryResult<usize> {
+    let mut db = establish_connection();
+    diesel::update(
+        recovery_codes::table
+            .filter(recovery_codes::dsl::user_pk.eq(user_pk))
+            .filter(recovery_codes::dsl::code_hash.eq(code_hash))
+            .filter(recovery_codes::dsl::used_at.is_null()),
+    )
+    .set(recovery_codes::dsl::used_at.eq(Some(Utc::now())))
+    .execute(&mut db)
+}
+
+pub fn recovery_codes_left(user_pk: Uuid) -> QueryResult<i64> {
+    let mut db = establish_connection();
+    recovery_codes::table
+        .filter(recovery_codes::dsl::user_pk.eq(user_pk))
+        .filter(recovery_codes::dsl::used_at.is_null())
+        .count()
+        .get_result(&mut db)
+}
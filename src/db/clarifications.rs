@@ -0,0 +1,65 @@
+use chrono::prelude::*;
+use diesel::prelude::*;
+use uuid::Uuid;
+
+use super::models::*;
+use super::schema::*;
+use crate::middlewares::postgresql::establish_connection;
+
+pub fn new_clarification(form: Clarification) -> QueryResult<Clarification> {
+    let mut db = establish_connection();
+    diesel::insert_into(clarifications::table)
+        .values(&form)
+        .get_result(&mut db)
+}
+
+pub fn find_clarification(pk: Uuid) -> QueryResult<Clarification> {
+    let mut db = establish_connection();
+    clarifications::table.find(pk).first(&mut db)
+}
+
+pub fn answer_clarification(
+    pk: Uuid,
+    answer: String,
+    answered_by: Uuid,
+    is_public: bool,
+) -> QueryResult<Clarification> {
+    let mut db = establish_connection();
+    diesel::update(clarifications::table.find(pk))
+        .set((
+            clarifications::dsl::answer.eq(Some(answer)),
+            clarifications::dsl::answered_by.eq(Some(answered_by)),
+            clarifications::dsl::is_public.eq(is_public),
+            clarifications::dsl::answered_at.eq(Some(Utc::now())),
+        ))
+        .get_result(&mut db)
+}
+
+// Public clarifications and the questions of `user_pk`, or everything when it is None
+pub fn contest_clarifications(
+    contest_pk: Uuid,
+    user_pk: Option<Uuid>,
+) -> QueryResult<Vec<Clarification>> {
+    let mut db = establish_connection();
+    let mut items = clarifications::table
+        .filter(clarifications::dsl::contest_pk.eq(contest_pk))
+        .into_boxed();
+    if let Some(user_pk) = user_pk {
+        items = items.filter(
+            clarifications::dsl::is_public
+                .eq(true)
+                .or(clarifications::dsl::user_pk.eq(user_pk)),
+        );
+    }
+    items
+        .order(clarifications::dsl::asked_at.desc())
+        .load::<Clarification>(&mut db)
+}
+
+pub fn clarifications_by(user_pk: Uuid) -> QueryResult<Vec<Clarification>> {
+    let mut db = establish_connection();
+    clarifications::table
+        .filter(clarifications::dsl::user_pk.eq(user_pk))
+        .order(clarifications::dsl::asked_at.asc())
+        .load::<Clarification>(&mut db)
+}
@@ -0,0 +1,198 @@
+use chrono::prelude::*;
+use diesel::prelude::*;
+use uuid::Uuid;
+
+use super::models::*;
+use super::schema::*;
+use crate::middlewares::postgresql::establish_connection;
+
+pub fn find_contest(pk: Uuid) -> QueryResult<Contest> {
+    let mut db = establish_connection();
+    contests::table.find(pk).first(&mut db)
+}
+
+// oldest first
+pub fn all_contests() -> QueryResult<Vec<Contest>> {
+    let mut db = establish_connection();
+    contests::table
+        .order(contests::dsl::start_at.asc())
+        .load::<Contest>(&mut db)
+}
+
+pub fn contest_task_pks(contest_pk: Uuid) -> QueryResult<Vec<Uuid>> {
+    let mut db = establish_connection();
+    contest_tasks::table
+        .filter(contest_tasks::dsl::contest_pk.eq(contest_pk))
+        .select(contest_tasks::dsl::task_pk)
+        .load::<Uuid>(&mut db)
+}
+
+pub fn contests_of_task(task_pk: Uuid) -> QueryResult<Vec<Contest>> {
+    let mut db = establish_connection();
+    contests::table
+        .inner_join(contest_tasks::table.on(contest_tasks::dsl::contest_pk.eq(contests::dsl::pk)))
+        .filter(contest_tasks::dsl::task_pk.eq(task_pk))
+        .select(contests::all_columns)
+        .load::<Contest>(&mut db)
+}
+
+// Users are allowed either directly or through one of their teams
+pub fn is_contest_participant(contest_pk: Uuid, user_pk: Uuid) -> QueryResult<bool> {
+    let mut db = establish_connection();
+    let direct = contest_accessible_users::table
+        .filter(contest_accessible_users::dsl::contest_pk.eq(contest_pk))
+        .filter(contest_accessible_users::dsl::user_pk.eq(user_pk))
+        .count()
+        .get_result::<i64>(&mut db)?;
+    if direct > 0 {
+        return Ok(true);
+    }
+    let by_team = contest_accessible_teams::table
+        .inner_join(
+            team_users::table
+                .on(team_users::dsl::team_pk.eq(contest_accessible_teams::dsl::team_pk)),
+        )
+        .filter(contest_accessible_teams::dsl::contest_pk.eq(contest_pk))
+        .filter(team_users::dsl::user_pk.eq(user_pk))
+        .count()
+        .get_result::<i64>(&mut db)?;
+    Ok(by_team > 0)
+}
+
+// Users allowed directly or through their teams
+pub fn contest_user_pks(contest_pk: Uuid) -> QueryResult<Vec<Uuid>> {
+    let mut db = establish_connection();
+    let mut user_pks = contest_accessible_users::table
+        .filter(contest_accessible_users::dsl::contest_pk.eq(contest_pk))
+        .select(contest_accessible_users::dsl::user_pk)
+        .load::<Uuid>(&mut db)?;
+    let by_team = contest_accessible_teams::table
+        .inner_join(
+            team_users::table
+                .on(team_users::dsl::team_pk.eq(contest_accessible_teams::dsl::team_pk)),
+        )
+        .filter(contest_accessible_teams::dsl::contest_pk.eq(contest_pk))
+        .select(team_users::dsl::user_pk)
+        .load::<Uuid>(&mut db)?;
+    for user_pk in by_team {
+        if !user_pks.contains(&user_pk) {
+            user_pks.push(user_pk);
+        }
+    }
+    Ok(user_pks)
+}
+
+pub fn personal_starts(contest_pk: Uuid) -> QueryResult<Vec<(Uuid, DateTime<Utc>)>> {
+    let mut db = establish_connection();
+    contest_accessible_users::table
+        .filter(contest_accessible_users::dsl::contest_pk.eq(contest_pk))
+        .filter(contest_accessible_users::dsl::started_at.is_not_null())
+        .select((
+            contest_accessible_users::dsl::user_pk,
+            contest_accessible_users::dsl::started_at.assume_not_null(),
+        ))
+        .load::<(Uuid, DateTime<Utc>)>(&mut db)
+}
+
+pub fn find_personal_start(contest_pk: Uuid, user_pk: Uuid) -> QueryResult<Option<DateTime<Utc>>> {
+    let mut db = establish_connection();
+    contest_accessible_users::table
+        .filter(contest_accessible_users::dsl::contest_pk.eq(contest_pk))
+        .filter(contest_accessible_users::dsl::user_pk.eq(user_pk))
+        .filter(contest_accessible_users::dsl::started_at.is_not_null())
+        .select(contest_accessible_users::dsl::started_at)
+        .first::<Option<DateTime<Utc>>>(&mut db)
+        .optional()
+        .map(Option::flatten)
+}
+
+// Users allowed through public access or teams get their own row on start. Returns 0 if
+// the user already started, so that concurrent starts cannot move the window.
+pub fn save_personal_start(
+    contest_pk: Uuid,
+    user_pk: Uuid,
+    started_at: DateTime<Utc>,
+) -> QueryResult<usize> {
+    let mut db = establish_connection();
+    diesel::insert_into(contest_accessible_users::table)
+        .values(&ContestAccessibleUser {
+            pk: Uuid::new_v4(),
+            user_pk,
+            contest_pk,
+            started_at: None,
+        })
+        .on_conflict((
+            contest_accessible_users::dsl::contest_pk,
+            contest_accessible_users::dsl::user_pk,
+        ))
+        .do_nothing()
+        .execute(&mut db)?;
+    diesel::update(
+        contest_accessible_users::table
+            .filter(contest_accessible_users::dsl::contest_pk.eq(contest_pk))
+            .filter(contest_accessible_users::dsl::user_pk.eq(user_pk))
+            .filter(contest_accessible_users::dsl::started_at.is_null()),
+    )
+    .set(contest_accessible_users::dsl::started_at.eq(Some(started_at)))
+    .execute(&mut db)
+}
+
+pub fn new_virtual_participation(form: VirtualParticipation) -> QueryResult<VirtualParticipation> {
+    let mut db = establish_connection();
+    diesel::insert_into(virtual_participations::table)
+        .values(&form)
+        .get_result(&mut db)
+}
+
+pub fn find_virtual_participation(
+    contest_pk: Uuid,
+    user_pk: Uuid,
+) -> QueryResult<VirtualParticipation> {
+    let mut db = establish_connection();
+    virtual_participations::table
+        .filter(virtual_participations::dsl::contest_pk.eq(contest_pk))
+        .filter(virtual_participations::dsl::user_pk.eq(user_pk))
+        .first(&mut db)
+}
+
+pub fn virtual_participations_of(contest_pk: Uuid) -> QueryResult<Vec<VirtualParticipation>> {
+    let mut db = establish_connection();
+    virtual_participations::table
+        .filter(virtual_participations::dsl::contest_pk.eq(contest_pk))
+        .load::<VirtualParticipation>(&mut db)
+}
+
+impl Contest {
+    pub fn is_ended(&self) -> bool {
+        self.end_at <= Utc::now()
+    }
+
+    // length of a single participation
+    pub fn duration(&self) -> chrono::Duration {
+        match self.participant_duration {
+            Some(minutes) => chrono::Duration::minutes(minutes as i64),
+            None => self.end_at - self.start_at,
+        }
+    }
+
+    pub fn is_windowed(&self) -> bool {
+        self.participant_duration.is_some()
+    }
+
+    // (start, end) of a participant who started at `started_at`, cut at the end of the contest
+    pub fn personal_window(&self, started_at: DateTime<Utc>) -> (DateTime<Utc>, DateTime<Utc>) {
+        match self.participant_duration {
+            Some(minutes) => (
+                started_at,
+                self.end_at
+                    .min(started_at + chrono::Duration::minutes(minutes as i64)),
+            ),
+            None => (self.start_at, self.end_at),
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        let now = Utc::now();
+        self.start_at <= now && now < self.end_at
+    }
+}
@@ -1,3 +1,4 @@
+use chrono::prelude::*;
 use diesel::prelude::*;
 use uuid::Uuid;
 
@@ -5,7 +6,66 @@ use super::models::*;
 use super::schema::*;
 use crate::middlewares::postgresql::establish_connection;
 
+#[derive(Clone, Debug, Default)]
+pub struct SubmissionQuery {
+    pub user_pk: Option<Uuid>,
+    pub task_pks: Option<Vec<Uuid>>,
+    pub lang_uuid: Option<Uuid>,
+    pub issued_after: Option<DateTime<Utc>>,
+    pub issued_before: Option<DateTime<Utc>>,
+    // keyset bounds on (issued_at, pk), both exclusive
+    pub older_than: Option<(DateTime<Utc>, Uuid)>,
+    pub newer_than: Option<(DateTime<Utc>, Uuid)>,
+    pub oldest_first: bool,
+    pub limit: Option<i64>,
+}
+
 pub fn find_submission(uuid: Uuid) -> QueryResult<Submission> {
     let mut db = establish_connection();
     submissions::table.find(uuid).first(&mut db)
 }
+
+pub fn new_submission(form: Submission) -> QueryResult<Submission> {
+    let mut db = establish_connection();
+    diesel::insert_into(submissions::table)
+        .values(&form)
+        .get_result(&mut db)
+}
+
+// newest first unless asked otherwise, ties broken by pk
+pub fn search_submissions(query: SubmissionQuery) -> QueryResult<Vec<Submission>> {
+    use submissions::dsl::{issued_at, pk};
+
+    let mut db = establish_connection();
+    let mut items = submissions::table.into_boxed();
+    if let Some(user_pk) = query.user_pk {
+        items = items.filter(submissions::dsl::user_pk.eq(user_pk));
+    }
+    if let Some(task_pks) = query.task_pks {
+        items = items.filter(submissions::dsl::task_pk.eq_any(task_pks));
+    }
+    if let Some(lang_uuid) = query.lang_uuid {
+        items = items.filter(submissions::dsl::lang_uuid.eq(lang_uuid));
+    }
+    if let Some(issued_after) = query.issued_after {
+        items = items.filter(issued_at.ge(issued_after));
+    }
+    if let Some(issued_before) = query.issued_before {
+        items = items.filter(issued_at.lt(issued_before));
+    }
+    if let Some((at, key)) = query.older_than {
+        items = items.filter(issued_at.lt(at).or(issued_at.eq(at).and(pk.lt(key))));
+    }
+    if let Some((at, key)) = query.newer_than {
+        items = items.filter(issued_at.gt(at).or(issued_at.eq(at).and(pk.gt(key))));
+    }
+    items = if query.oldest_first {
+        items.order((issued_at.asc(), pk.asc()))
+    } else {
+        items.order((issued_at.desc(), pk.desc()))
+    };
+    if let Some(limit) = query.limit {
+        items = items.limit(limit);
+    }
+    items.load::<Submission>(&mut db)
+}
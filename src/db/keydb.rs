@@ -1,6 +1,7 @@
 use crate::constants::*;
 use actix::prelude::*;
 use ckydb::{connect, controller::Ckydb, Controller};
+use std::collections::HashMap;
 use std::path::PathBuf;
 use uuid::Uuid;
 
@@ -8,6 +9,7 @@ use uuid::Uuid;
 pub enum KeyDbResponse {
     None,
     Data(String),
+    Many(Vec<Option<String>>), // in order of the requested keys
 }
 
 #[derive(Clone, Debug, Message)]
@@ -16,6 +18,7 @@ pub enum KeyDbResponse {
 pub enum KeyDbMessage {
     Insert(Uuid, String),
     Get(Uuid),
+    GetMany(Vec<Uuid>), // one round trip for a batch of keys
 }
 
 pub struct KeyDbService {
@@ -56,6 +59,12 @@ impl Handler<KeyDbMessage> for KeyDbService {
                     KeyDbResponse::None
                 }
             }
+            KeyDbMessage::GetMany(uuids) => KeyDbResponse::Many(
+                uuids
+                    .iter()
+                    .map(|uuid| self.raw.get(&uuid.to_string()).ok())
+                    .collect(),
+            ),
             KeyDbMessage::Insert(uuid, data) => {
                 self.raw.set(&uuid.to_string(), &data).ok();
                 KeyDbResponse::None
@@ -63,3 +72,15 @@ impl Handler<KeyDbMessage> for KeyDbService {
         }
     }
 }
+
+// Values of a batch of keys in one round trip, missing keys left out
+pub async fn get_many(db: &Addr<KeyDbService>, uuids: Vec<Uuid>) -> HashMap<Uuid, String> {
+    match db.send(KeyDbMessage::GetMany(uuids.clone())).await {
+        Ok(KeyDbResponse::Many(values)) => uuids
+            .into_iter()
+            .zip(values)
+            .filter_map(|(uuid, value)| value.map(|value| (uuid, value)))
+            .collect(),
+        _ => HashMap::new(),
+    }
+}
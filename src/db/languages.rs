@@ -0,0 +1,25 @@
+use diesel::prelude::*;
+use uuid::Uuid;
+
+use super::models::*;
+use super::schema::*;
+use crate::middlewares::postgresql::establish_connection;
+
+pub fn find_language(pk: Uuid) -> QueryResult<Language> {
+    let mut db = establish_connection();
+    languages::table.find(pk).first(&mut db)
+}
+
+pub fn all_languages(enabled_only: bool) -> QueryResult<Vec<Language>> {
+    let mut db = establish_connection();
+    if enabled_only {
+        languages::table
+            .filter(languages::dsl::is_enabled.eq(true))
+            .order(languages::dsl::name.asc())
+            .load::<Language>(&mut db)
+    } else {
+        languages::table
+            .order(languages::dsl::name.asc())
+            .load::<Language>(&mut db)
+    }
+}
@@ -1,5 +1,10 @@
 pub mod accounts;
+pub mod clarifications;
+pub mod contests;
 pub mod judge;
 pub mod keydb;
+pub mod languages;
 pub mod models;
+pub mod plagiarism;
 pub mod schema;
+pub mod tasks;
@@ -26,11 +26,29 @@ pub struct User {
     pub pass: String,
     pub permission: i32,
     pub timezone: String,
+    pub email: String,
     pub first_name: String,
     pub last_name: String,
-    pub email: String,
     pub preferred_language: Uuid,
     pub pk: Uuid,
+    pub email_verified: bool,
+    pub deleted_at: Option<DateTime<Utc>>, // set once the account is anonymized
+    pub is_banned: bool,
+    pub totp_secret: Option<String>, // base32, set from the enrollment on
+    pub totp_enabled: bool,
+    pub totp_last_step: Option<i64>, // codes of this step and before are not accepted again
+}
+
+// Profile fields to be changed, None leaves the field as it is
+#[derive(Clone, Debug, Default, AsChangeset)]
+#[table_name = "users"]
+pub struct UserChanges {
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    pub email: Option<String>,
+    pub email_verified: Option<bool>,
+    pub timezone: Option<String>,
+    pub preferred_language: Option<Uuid>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Insertable, Queryable)]
@@ -74,6 +92,7 @@ pub struct Contest {
     pub start_at: DateTime<Utc>,
     pub end_at: DateTime<Utc>,
     pub is_public: bool,
+    pub participant_duration: Option<i32>, // in minutes, for windowed contests
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Insertable, Queryable)]
@@ -82,6 +101,7 @@ pub struct ContestAccessibleUser {
     pub pk: Uuid,
     pub user_pk: Uuid,
     pub contest_pk: Uuid,
+    pub started_at: Option<DateTime<Utc>>, // personal start in windowed contests
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Insertable, Queryable)]
@@ -102,6 +122,108 @@ pub struct Submission {
     pub issued_at: DateTime<Utc>,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize, Insertable, Queryable)]
+#[table_name = "languages"]
+pub struct Language {
+    pub pk: Uuid,
+    pub name: String,
+    pub version: String,
+    pub compile_command: Option<String>,
+    pub run_command: String,
+    pub file_extension: String,
+    pub is_enabled: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Insertable, Queryable)]
+#[table_name = "similarities"]
+pub struct Similarity {
+    pub pk: Uuid,
+    pub task_pk: Uuid,
+    pub contest_pk: Option<Uuid>,
+    pub submission_a: Uuid,
+    pub submission_b: Uuid,
+    pub score: f64,
+    pub computed_at: DateTime<Utc>,
+}
+
+// When the similarities of an ended contest were last computed in the background
+#[derive(Clone, Debug, Serialize, Deserialize, Insertable, Queryable)]
+#[table_name = "plagiarism_checks"]
+pub struct PlagiarismCheck {
+    pub contest_pk: Uuid,
+    pub checked_at: DateTime<Utc>,
+}
+
+// A personal replay of an ended contest, lasting as long as the contest did
+#[derive(Clone, Debug, Serialize, Deserialize, Insertable, Queryable)]
+#[table_name = "virtual_participations"]
+pub struct VirtualParticipation {
+    pub pk: Uuid,
+    pub user_pk: Uuid,
+    pub contest_pk: Uuid,
+    pub start_at: DateTime<Utc>,
+}
+
+// Announcements are posted by jury members without a question
+#[derive(Clone, Debug, Serialize, Deserialize, Insertable, Queryable)]
+#[table_name = "clarifications"]
+pub struct Clarification {
+    pub pk: Uuid,
+    pub contest_pk: Uuid,
+    pub task_pk: Option<Uuid>,
+    pub user_pk: Uuid,
+    pub question: Option<String>,
+    pub answer: Option<String>,
+    pub answered_by: Option<Uuid>,
+    pub is_public: bool,
+    pub asked_at: DateTime<Utc>,
+    pub answered_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Insertable, Queryable)]
+#[table_name = "audit_logs"]
+pub struct AuditLog {
+    pub pk: Uuid,
+    pub admin_pk: Uuid,
+    pub action: String,
+    pub target_pk: Option<Uuid>,
+    pub detail: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub impersonator_pk: Option<Uuid>, // set when the admin acted while being impersonated
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Insertable, Queryable)]
+#[table_name = "external_identities"]
+pub struct ExternalIdentity {
+    pub pk: Uuid,
+    pub user_pk: Uuid,
+    pub provider: String,
+    pub subject: String, // `sub` claim of the provider
+    pub linked_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Insertable, Queryable)]
+#[table_name = "api_tokens"]
+pub struct ApiToken {
+    pub pk: Uuid,
+    pub user_pk: Uuid,
+    pub name: String,
+    pub token_hash: String, // the token itself is shown only once
+    pub scopes: i32,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Insertable, Queryable)]
+#[table_name = "recovery_codes"]
+pub struct RecoveryCode {
+    pub pk: Uuid,
+    pub user_pk: Uuid,
+    pub code_hash: String,
+    pub used_at: Option<DateTime<Utc>>,
+}
+
 impl User {
     pub fn timezone(&self) -> Result<Tz, String> {
         self.timezone.parse::<Tz>()
@@ -0,0 +1,72 @@
+use diesel::prelude::*;
+use uuid::Uuid;
+
+use super::models::*;
+use super::schema::*;
+use crate::middlewares::postgresql::establish_connection;
+
+// Drops what an earlier check of the whole task, or of the contest's part of it, stored and
+// saves what this one found. Checks of the task and of its contests are kept apart.
+pub fn replace_similarities(
+    task_pk: Uuid,
+    contest_pk: Option<Uuid>,
+    forms: Vec<Similarity>,
+) -> QueryResult<usize> {
+    let mut db = establish_connection();
+    db.transaction(|db| {
+        let of_task = similarities::table.filter(similarities::dsl::task_pk.eq(task_pk));
+        match contest_pk {
+            Some(contest_pk) => {
+                diesel::delete(of_task.filter(similarities::dsl::contest_pk.eq(contest_pk)))
+                    .execute(db)?
+            }
+            None => diesel::delete(of_task.filter(similarities::dsl::contest_pk.is_null()))
+                .execute(db)?,
+        };
+        // a batch has to stay within the bind parameters of one statement
+        for chunk in forms.chunks(1000) {
+            diesel::insert_into(similarities::table)
+                .values(chunk)
+                .execute(db)?;
+        }
+        Ok(forms.len())
+    })
+}
+
+pub fn plagiarism_checks() -> QueryResult<Vec<PlagiarismCheck>> {
+    let mut db = establish_connection();
+    plagiarism_checks::table.load::<PlagiarismCheck>(&mut db)
+}
+
+pub fn save_plagiarism_check(form: PlagiarismCheck) -> QueryResult<PlagiarismCheck> {
+    let mut db = establish_connection();
+    diesel::insert_into(plagiarism_checks::table)
+        .values(&form)
+        .on_conflict(plagiarism_checks::dsl::contest_pk)
+        .do_update()
+        .set(plagiarism_checks::dsl::checked_at.eq(form.checked_at))
+        .get_result(&mut db)
+}
+
+// most suspicious first
+pub fn find_similarities(
+    task_pk: Option<Uuid>,
+    contest_pk: Option<Uuid>,
+    min_score: f64,
+    limit: i64,
+) -> QueryResult<Vec<Similarity>> {
+    let mut db = establish_connection();
+    let mut items = similarities::table
+        .filter(similarities::dsl::score.ge(min_score))
+        .into_boxed();
+    if let Some(task_pk) = task_pk {
+        items = items.filter(similarities::dsl::task_pk.eq(task_pk));
+    }
+    if let Some(contest_pk) = contest_pk {
+        items = items.filter(similarities::dsl::contest_pk.eq(contest_pk));
+    }
+    items
+        .order(similarities::dsl::score.desc())
+        .limit(limit)
+        .load::<Similarity>(&mut db)
+}
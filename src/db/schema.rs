@@ -1,5 +1,45 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    api_tokens (pk) {
+        pk -> Uuid,
+        user_pk -> Uuid,
+        name -> Varchar,
+        token_hash -> Varchar,
+        scopes -> Int4,
+        created_at -> Timestamptz,
+        expires_at -> Nullable<Timestamptz>,
+        last_used_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    audit_logs (pk) {
+        pk -> Uuid,
+        admin_pk -> Uuid,
+        action -> Varchar,
+        target_pk -> Nullable<Uuid>,
+        detail -> Nullable<Text>,
+        created_at -> Timestamptz,
+        impersonator_pk -> Nullable<Uuid>,
+    }
+}
+
+diesel::table! {
+    clarifications (pk) {
+        pk -> Uuid,
+        contest_pk -> Uuid,
+        task_pk -> Nullable<Uuid>,
+        user_pk -> Uuid,
+        question -> Nullable<Text>,
+        answer -> Nullable<Text>,
+        answered_by -> Nullable<Uuid>,
+        is_public -> Bool,
+        asked_at -> Timestamptz,
+        answered_at -> Nullable<Timestamptz>,
+    }
+}
+
 diesel::table! {
     contest_accessible_teams (pk) {
         pk -> Uuid,
@@ -13,6 +53,7 @@ diesel::table! {
         pk -> Uuid,
         user_pk -> Uuid,
         contest_pk -> Uuid,
+        started_at -> Nullable<Timestamptz>,
     }
 }
 
@@ -30,7 +71,58 @@ diesel::table! {
         name -> Varchar,
         start_at -> Timestamptz,
         end_at -> Timestamptz,
-        is_public -> Nullable<Bool>,
+        is_public -> Bool,
+        participant_duration -> Nullable<Int4>,
+    }
+}
+
+diesel::table! {
+    external_identities (pk) {
+        pk -> Uuid,
+        user_pk -> Uuid,
+        provider -> Varchar,
+        subject -> Varchar,
+        linked_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    languages (pk) {
+        pk -> Uuid,
+        name -> Varchar,
+        version -> Varchar,
+        compile_command -> Nullable<Varchar>,
+        run_command -> Varchar,
+        file_extension -> Varchar,
+        is_enabled -> Bool,
+    }
+}
+
+diesel::table! {
+    plagiarism_checks (contest_pk) {
+        contest_pk -> Uuid,
+        checked_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    recovery_codes (pk) {
+        pk -> Uuid,
+        user_pk -> Uuid,
+        code_hash -> Varchar,
+        used_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    similarities (pk) {
+        pk -> Uuid,
+        task_pk -> Uuid,
+        contest_pk -> Nullable<Uuid>,
+        submission_a -> Uuid,
+        submission_b -> Uuid,
+        score -> Float8,
+        computed_at -> Timestamptz,
     }
 }
 
@@ -50,7 +142,7 @@ diesel::table! {
         internal_task_uuid -> Uuid,
         name -> Varchar,
         code -> Varchar,
-        is_public -> Nullable<Bool>,
+        is_public -> Bool,
     }
 }
 
@@ -80,17 +172,41 @@ diesel::table! {
         last_name -> Varchar,
         preferred_language -> Uuid,
         pk -> Uuid,
+        email_verified -> Bool,
+        deleted_at -> Nullable<Timestamptz>,
+        is_banned -> Bool,
+        totp_secret -> Nullable<Varchar>,
+        totp_enabled -> Bool,
+        totp_last_step -> Nullable<Int8>,
+    }
+}
+
+diesel::table! {
+    virtual_participations (pk) {
+        pk -> Uuid,
+        user_pk -> Uuid,
+        contest_pk -> Uuid,
+        start_at -> Timestamptz,
     }
 }
 
 diesel::allow_tables_to_appear_in_same_query!(
+    api_tokens,
+    audit_logs,
+    clarifications,
     contest_accessible_teams,
     contest_accessible_users,
     contest_tasks,
     contests,
+    external_identities,
+    languages,
+    plagiarism_checks,
+    recovery_codes,
+    similarities,
     submissions,
     tasks,
     team_users,
     teams,
     users,
+    virtual_participations,
 );
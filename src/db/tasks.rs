@@ -0,0 +1,11 @@
+use diesel::prelude::*;
+use uuid::Uuid;
+
+use super::models::*;
+use super::schema::*;
+use crate::middlewares::postgresql::establish_connection;
+
+pub fn find_task(pk: Uuid) -> QueryResult<Task> {
+    let mut db = establish_connection();
+    tasks::table.find(pk).first(&mut db)
+}
@@ -1,4 +1,7 @@
 use async_graphql::*;
+use judge_protocol::judge::*;
+
+use crate::tasks::checker::Verdict;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use uuid::Uuid;
@@ -9,6 +12,8 @@ pub enum TestState {
     Ready,
     Pending,
     Success,
+    WrongAnswer,
+    Partial,
     RuntimeErr,
     DiedOnSignal,
     TimeLimitExceed,
@@ -37,6 +42,25 @@ pub struct Submission {
     pub compile_output: String,
     pub score: f64,
     pub cases: HashMap<Uuid, TestCase>,
+    #[serde(default)]
+    pub task_type: String,
+    #[serde(default)]
+    pub subtasks: HashMap<String, SubtaskState>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SubtaskState {
+    pub max_score: f64,
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+}
+
+#[derive(Clone, Debug, SimpleObject)]
+pub struct SubtaskResult {
+    pub name: String,
+    pub score: f64,
+    pub max_score: f64,
+    pub passed: bool,
 }
 
 impl Submission {
@@ -47,6 +71,198 @@ impl Submission {
     pub fn from_json(raw: String) -> Self {
         serde_json::from_str(&raw).unwrap()
     }
+
+    pub fn is_solved(&self) -> bool {
+        self.number_of_cases > 0
+            && self.number_of_done == self.number_of_cases
+            && self
+                .cases
+                .values()
+                .all(|case| case.status == TestState::Success && case.score.unwrap_or(0.0) >= 1.0)
+    }
+
+    // Leaves out details of tests the task does not reveal, for anyone but admins
+    pub fn redacted(mut self) -> Self {
+        for case in self.cases.values_mut() {
+            if !case.revealed {
+                case.exit_code = None;
+                case.exit_sig = None;
+                case.message = None;
+                case.input_preview = None;
+                case.output_preview = None;
+            }
+        }
+        self
+    }
+
+    // the worst test decides the ratio of a subtask, and a subtask without tests scores nothing
+    fn subtask_ratio(&self, name: &str) -> f64 {
+        self.cases
+            .values()
+            .filter(|case| case.subtasks.iter().any(|x| x == name))
+            .map(|case| case.score.unwrap_or(0.0))
+            .reduce(f64::min)
+            .unwrap_or(0.0)
+    }
+
+    fn dependencies_passed(&self, name: &str, visited: &mut Vec<String>) -> bool {
+        let subtask = match self.subtasks.get(name) {
+            Some(subtask) => subtask,
+            None => return true,
+        };
+        subtask.dependencies.iter().all(|x| {
+            if visited.contains(x) {
+                return true;
+            }
+            visited.push(x.clone());
+            self.subtask_ratio(x) >= 1.0 && self.dependencies_passed(x, visited)
+        })
+    }
+
+    // a subtask scores nothing unless all of its dependencies passed, transitively
+    pub fn subtask_results(&self) -> Vec<SubtaskResult> {
+        let mut names: Vec<&String> = self.subtasks.keys().collect();
+        names.sort();
+        names
+            .into_iter()
+            .map(|name| {
+                let max_score = self.subtasks[name].max_score;
+                let ratio = self.subtask_ratio(name);
+                let score = if self.dependencies_passed(name, &mut vec![name.clone()]) {
+                    ratio * max_score
+                } else {
+                    0.0
+                };
+                SubtaskResult {
+                    name: name.clone(),
+                    score,
+                    max_score,
+                    passed: score >= max_score,
+                }
+            })
+            .collect()
+    }
+
+    pub fn record_verdict(&mut self, test_uuid: Uuid, verdict: Verdict) {
+        if let Some(data) = self.cases.get_mut(&test_uuid) {
+            data.status = if verdict.score >= 1.0 {
+                TestState::Success
+            } else if verdict.score <= 0.0 {
+                TestState::WrongAnswer
+            } else {
+                TestState::Partial
+            };
+            data.score = Some(verdict.score);
+            data.message = Some(verdict.message);
+        } else {
+            panic!("Judgement database is corrupted")
+        }
+    }
+
+    // updates the state with a result from judges except for the total score,
+    // which is up to the task type
+    pub fn apply(&mut self, state: JudgeState) {
+        match state {
+            JudgeState::CompleteCompile(out) => {
+                self.submission_state = SubmissionState::CompileComplete;
+                self.compile_output = out;
+            }
+            JudgeState::UnknownError
+            | JudgeState::JudgeNotFound
+            | JudgeState::LanguageNotFound
+            | JudgeState::LockedSlave => {
+                // EOJ
+                self.submission_state = SubmissionState::Failed;
+            }
+            JudgeState::DiedOnSignal(test_uuid, exit_sig) => {
+                // EOJ for Test
+                if let Some(data) = self.cases.get_mut(&test_uuid) {
+                    data.exit_sig = Some(exit_sig);
+                    data.status = TestState::DiedOnSignal;
+                } else {
+                    panic!("Judgement database is corrupted")
+                }
+                self.number_of_done += 1;
+            }
+            JudgeState::Accepted(test_uuid, time, mem) => {
+                // EOJ for Test
+                if let Some(data) = self.cases.get_mut(&test_uuid) {
+                    data.time = Some(time);
+                    data.mem = Some(mem);
+                    data.status = TestState::Success;
+                    data.score = Some(1.0);
+                } else {
+                    panic!("Judgement database is corrupted")
+                }
+                self.number_of_done += 1;
+            }
+            JudgeState::WrongAnswer(test_uuid, time, mem) => {
+                // EOJ for Test
+                if let Some(data) = self.cases.get_mut(&test_uuid) {
+                    data.time = Some(time);
+                    data.mem = Some(mem);
+                    data.status = TestState::WrongAnswer;
+                    data.score = Some(0.0);
+                } else {
+                    panic!("Judgement database is corrupted")
+                }
+                self.number_of_done += 1;
+            }
+            JudgeState::GeneralError(out) => {
+                error!(
+                    "Judgement service has received JudgeState::GeneralError: {}",
+                    out
+                );
+                self.submission_state = SubmissionState::Failed;
+            }
+            JudgeState::InternalError(test_uuid) => {
+                // EOJ for Test
+                if let Some(data) = self.cases.get_mut(&test_uuid) {
+                    data.status = TestState::Failed;
+                } else {
+                    panic!("Judgement database is corrupted")
+                }
+                self.number_of_done += 1;
+            }
+            JudgeState::TimeLimitExceed(test_uuid) => {
+                // EOJ for Test
+                if let Some(data) = self.cases.get_mut(&test_uuid) {
+                    data.status = TestState::TimeLimitExceed;
+                } else {
+                    panic!("Judgement database is corrupted")
+                }
+                self.number_of_done += 1;
+            }
+            JudgeState::MemLimitExceed(test_uuid) => {
+                // EOJ for Test
+                if let Some(data) = self.cases.get_mut(&test_uuid) {
+                    data.status = TestState::MemLimitExceed;
+                } else {
+                    panic!("Judgement database is corrupted")
+                }
+                self.number_of_done += 1;
+            }
+            JudgeState::RuntimeError(test_uuid, exit_code) => {
+                // EOJ for Test
+                if let Some(data) = self.cases.get_mut(&test_uuid) {
+                    data.exit_code = Some(exit_code);
+                    data.status = TestState::RuntimeErr;
+                } else {
+                    panic!("Judgement database is corrupted")
+                }
+                self.number_of_done += 1;
+            }
+            JudgeState::CompileError(stderr) => {
+                // EOJ
+                self.submission_state = SubmissionState::CompileError;
+                self.compile_output = stderr;
+            }
+            JudgeState::DoCompile => {
+                self.submission_state = SubmissionState::DoCompile;
+            }
+            _ => {}
+        }
+    }
 }
 
 #[Object]
@@ -74,17 +290,35 @@ impl Submission {
     async fn cases(&self) -> HashMap<Uuid, TestCase> {
         self.cases.clone()
     }
+
+    async fn subtasks(&self) -> Vec<SubtaskResult> {
+        self.subtask_results()
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TestCase {
     pub test_uuid: Uuid,
+    #[serde(default)]
+    pub subtasks: Vec<String>, // every subtask sharing this test
+    #[serde(default)]
+    pub name: String,
     pub status: TestState,
     pub score: Option<f64>,
     pub exit_code: Option<i32>,
     pub exit_sig: Option<i32>,
     pub time: Option<u64>, // in ms
     pub mem: Option<u64>,  // in kB
+    #[serde(default)]
+    pub message: Option<String>, // comment of the checker
+    #[serde(default)]
+    pub sample: bool,
+    #[serde(default)]
+    pub revealed: bool,
+    #[serde(default)]
+    pub input_preview: Option<String>,
+    #[serde(default)]
+    pub output_preview: Option<String>, // of the expected output
 }
 
 #[Object]
@@ -93,6 +327,14 @@ impl TestCase {
         self.test_uuid
     }
 
+    async fn subtasks(&self) -> Vec<String> {
+        self.subtasks.clone()
+    }
+
+    async fn name(&self) -> String {
+        self.name.clone()
+    }
+
     async fn status(&self) -> TestState {
         self.status
     }
@@ -101,19 +343,41 @@ impl TestCase {
         self.score
     }
 
+    async fn time(&self) -> Option<u64> {
+        self.time
+    }
+
+    async fn mem(&self) -> Option<u64> {
+        self.mem
+    }
+
+    async fn sample(&self) -> bool {
+        self.sample
+    }
+
+    async fn revealed(&self) -> bool {
+        self.revealed
+    }
+
+    // details below are hidden unless the test is a sample or one of its subtasks reveals them
+
     async fn exit_code(&self) -> Option<i32> {
-        self.exit_code
+        self.exit_code.filter(|_| self.revealed)
     }
 
     async fn exit_sig(&self) -> Option<i32> {
-        self.exit_sig
+        self.exit_sig.filter(|_| self.revealed)
     }
 
-    async fn time(&self) -> Option<u64> {
-        self.time
+    async fn message(&self) -> Option<String> {
+        self.message.clone().filter(|_| self.revealed)
     }
 
-    async fn mem(&self) -> Option<u64> {
-        self.mem
+    async fn input_preview(&self) -> Option<String> {
+        self.input_preview.clone().filter(|_| self.revealed)
+    }
+
+    async fn output_preview(&self) -> Option<String> {
+        self.output_preview.clone().filter(|_| self.revealed)
     }
 }
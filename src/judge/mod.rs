@@ -18,9 +18,11 @@ use std::collections::HashMap;
 
 use crate::db::keydb::*;
 use crate::judge::api::*;
+use crate::tasks::types::*;
 use crate::APPDATA;
 
 pub mod api;
+pub mod request;
 
 #[derive(Clone, Debug)]
 pub struct Test {
@@ -126,108 +128,9 @@ impl Handler<EventMessage> for JudgeService {
                         .unwrap()
                     {
                         let mut cur_state = Submission::from_json(data);
-                        match state {
-                            JudgeState::CompleteCompile(out) => {
-                                cur_state.submission_state = SubmissionState::CompileComplete;
-                                cur_state.compile_output = out;
-                            }
-                            JudgeState::UnknownError
-                            | JudgeState::JudgeNotFound
-                            | JudgeState::LanguageNotFound
-                            | JudgeState::LockedSlave => {
-                                // EOJ
-                                cur_state.submission_state = SubmissionState::Failed;
-                            }
-                            JudgeState::DiedOnSignal(test_uuid, exit_sig) => {
-                                // EOJ for Test
-                                if let Some(data) = cur_state.cases.get_mut(&test_uuid) {
-                                    data.exit_sig = Some(exit_sig);
-                                    data.status = TestState::DiedOnSignal;
-                                } else {
-                                    panic!("Judgement database is corrupted")
-                                }
-                                cur_state.number_of_done += 1;
-                            }
-                            JudgeState::Accepted(test_uuid, time, mem) => {
-                                // EOJ for Test
-                                // TODO: score
-                                if let Some(data) = cur_state.cases.get_mut(&test_uuid) {
-                                    data.time = Some(time);
-                                    data.mem = Some(mem);
-                                    data.status = TestState::Success;
-                                    data.score = Some(1.0);
-                                } else {
-                                    panic!("Judgement database is corrupted")
-                                }
-                                cur_state.score += 1.0;
-                                cur_state.number_of_done += 1;
-                            }
-                            JudgeState::WrongAnswer(test_uuid, time, mem) => {
-                                // EOJ for Test
-                                // TODO: score
-                                if let Some(data) = cur_state.cases.get_mut(&test_uuid) {
-                                    data.time = Some(time);
-                                    data.mem = Some(mem);
-                                    data.status = TestState::Success;
-                                } else {
-                                    panic!("Judgement database is corrupted")
-                                }
-                                cur_state.number_of_done += 1;
-                            }
-                            JudgeState::GeneralError(out) => {
-                                error!(
-                                    "Judgement service has received JudgeState::GeneralError: {}",
-                                    out
-                                );
-                                cur_state.submission_state = SubmissionState::Failed;
-                            }
-                            JudgeState::InternalError(test_uuid) => {
-                                // EOJ for Test
-                                if let Some(data) = cur_state.cases.get_mut(&test_uuid) {
-                                    data.status = TestState::Failed;
-                                } else {
-                                    panic!("Judgement database is corrupted")
-                                }
-                                cur_state.number_of_done += 1;
-                            }
-                            JudgeState::TimeLimitExceed(test_uuid) => {
-                                // EOJ for Test
-                                if let Some(data) = cur_state.cases.get_mut(&test_uuid) {
-                                    data.status = TestState::TimeLimitExceed;
-                                } else {
-                                    panic!("Judgement database is corrupted")
-                                }
-                                cur_state.number_of_done += 1;
-                            }
-                            JudgeState::MemLimitExceed(test_uuid) => {
-                                // EOJ for Test
-                                if let Some(data) = cur_state.cases.get_mut(&test_uuid) {
-                                    data.status = TestState::MemLimitExceed;
-                                } else {
-                                    panic!("Judgement database is corrupted")
-                                }
-                                cur_state.number_of_done += 1;
-                            }
-                            JudgeState::RuntimeError(test_uuid, exit_code) => {
-                                // EOJ for Test
-                                if let Some(data) = cur_state.cases.get_mut(&test_uuid) {
-                                    data.exit_code = Some(exit_code);
-                                    data.status = TestState::RuntimeErr;
-                                } else {
-                                    panic!("Judgement database is corrupted")
-                                }
-                                cur_state.number_of_done += 1;
-                            }
-                            JudgeState::CompileError(stderr) => {
-                                // EOJ
-                                cur_state.submission_state = SubmissionState::CompileError;
-                                cur_state.compile_output = stderr;
-                            }
-                            JudgeState::DoCompile => {
-                                cur_state.submission_state = SubmissionState::DoCompile;
-                            }
-                            _ => {}
-                        }
+                        let handler = task_type_handler(&cur_state.task_type)
+                            .unwrap_or_else(|| task_type_handler(BATCH).unwrap());
+                        handler.apply(&mut cur_state, state);
                         for tx in set {
                             if let Some(tx) = tx {
                                 let s = cur_state.clone();
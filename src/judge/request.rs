@@ -0,0 +1,178 @@
+use async_std::fs::{read, read_to_string};
+use async_std::io;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use judge_protocol::judge::*;
+
+use crate::judge::api::*;
+use crate::tasks::checker::Verdict;
+use crate::tasks::loader::SharedTest;
+use crate::tasks::subtasks::Limits;
+use crate::tasks::*;
+
+// Managers of Communication and TwoSteps tasks go to judges with the submission. Grader
+// stubs cannot, since the judge protocol has no fields for them.
+async fn manager(task: &TaskLoader) -> io::Result<(Option<Uuid>, Option<Vec<u8>>)> {
+    match task.graders.manager_file.clone() {
+        Some(path) if task.task.task_type.requires_stub() => Ok((
+            task.graders.grader.manager_language,
+            Some(read(path).await?),
+        )),
+        _ => Ok((None, None)),
+    }
+}
+
+fn new_case(test_uuid: Uuid, task: &TaskLoader, shared: &SharedTest) -> TestCase {
+    let sample = shared.subtasks.iter().any(|x| x.is_sample(shared.name));
+    TestCase {
+        test_uuid,
+        subtasks: shared.subtasks.iter().map(|x| x.name.clone()).collect(),
+        name: shared.name.clone(),
+        status: TestState::Ready,
+        score: None,
+        exit_code: None,
+        exit_sig: None,
+        time: None,
+        mem: None,
+        message: None,
+        sample,
+        revealed: sample
+            || shared
+                .subtasks
+                .iter()
+                .any(|x| task.task.feedback.reveals(&x.name)),
+        input_preview: None,
+        output_preview: None,
+    }
+}
+
+// Revealed tests show the start of their input and of the expected output, whatever the
+// kind of the submission is
+pub fn fill_previews(case: &mut TestCase, feedback: &Feedback, stdin: &[u8], stdout: &[u8]) {
+    if case.revealed {
+        case.input_preview = Some(feedback.preview(stdin));
+        case.output_preview = Some(feedback.preview(stdout));
+    }
+}
+
+// Judges only run checker programs, built-in checkers are evaluated here
+async fn checker_program(task: &TaskLoader) -> io::Result<(Uuid, Vec<u8>)> {
+    match (
+        task.checker.checker.checker_language,
+        task.checker.checker_file.clone(),
+    ) {
+        (Some(lang), Some(path)) => Ok((lang, read(path).await?)),
+        _ => Err(io::Error::from(io::ErrorKind::InvalidData)),
+    }
+}
+
+fn new_state(judge_uuid: Uuid, task: &TaskLoader, cases: HashMap<Uuid, TestCase>) -> Submission {
+    Submission {
+        judge_uuid,
+        submission_state: SubmissionState::Ready,
+        number_of_cases: cases.len(),
+        number_of_done: 0,
+        compile_output: String::new(),
+        score: 0.0,
+        cases,
+        task_type: String::new(),
+        subtasks: task
+            .subtasks
+            .subtasks
+            .values()
+            .map(|(subtask, _)| {
+                let state = SubtaskState {
+                    max_score: subtask.score,
+                    dependencies: subtask.dependencies.clone(),
+                };
+                (subtask.name.clone(), state)
+            })
+            .collect(),
+    }
+}
+
+// Builds a judge request for the submission and the initial state stored in judge_db.
+// Every test gets its own UUID so that results from judges can be mapped back to it.
+pub async fn make_request(
+    judge_uuid: Uuid,
+    task: &TaskLoader,
+    lang_uuid: Uuid,
+    source: String,
+) -> io::Result<(RequestJudge, Submission)> {
+    let mut cases = HashMap::new();
+    let mut testcases = vec![];
+    for shared in task.subtasks.shared_tests() {
+        let test_uuid = Uuid::new_v4();
+        let mut case = new_case(test_uuid, task, &shared);
+        let stdin = read(shared.test.stdin.clone()).await?;
+        let stdout = read(shared.test.stdout.clone()).await?;
+        fill_previews(&mut case, &task.task.feedback, &stdin, &stdout);
+        cases.insert(test_uuid, case);
+        let (time_limit, mem_limit) = task.task.effective_limits(shared.limits(), Some(lang_uuid));
+        testcases.push((test_uuid, stdin, stdout, time_limit, mem_limit));
+    }
+    let (manager_lang, manager_code) = manager(task).await?;
+    let (checker_lang, checker_code) = checker_program(task).await?;
+    let (time_limit, mem_limit) = task
+        .task
+        .effective_limits(Limits::default(), Some(lang_uuid));
+    let request = RequestJudge {
+        uuid: judge_uuid,
+        main_lang: lang_uuid,
+        main_code: source.into_bytes(),
+        checker_lang,
+        checker_code,
+        manager_lang,
+        manager_code,
+        time_limit,
+        mem_limit,
+        testcases,
+    };
+    Ok((request, new_state(judge_uuid, task, cases)))
+}
+
+// Output-only submissions are not executed, and the judge protocol cannot carry their
+// outputs either, so they are checked here at once by the built-in checker of the task.
+// Tests without a submitted output are wrong answers.
+pub async fn make_output_state(
+    judge_uuid: Uuid,
+    task: &TaskLoader,
+    outputs: &HashMap<String, String>,
+) -> io::Result<Submission> {
+    let checker = task
+        .checker
+        .checker
+        .builtin
+        .ok_or_else(|| io::Error::from(io::ErrorKind::InvalidData))?;
+    let mut cases = HashMap::new();
+    let mut verdicts = vec![];
+    for shared in task.subtasks.shared_tests() {
+        let (testcase, test) = (shared.name, shared.test);
+        let test_uuid = Uuid::new_v4();
+        let mut case = new_case(test_uuid, task, &shared);
+        if case.revealed {
+            let stdin = read(test.stdin.clone()).await?;
+            let stdout = read(test.stdout.clone()).await?;
+            fill_previews(&mut case, &task.task.feedback, &stdin, &stdout);
+        }
+        match outputs.get(testcase) {
+            Some(output) => {
+                let expected = read_to_string(test.stdout.clone()).await?;
+                verdicts.push((test_uuid, checker.check(&expected, output)));
+            }
+            None => verdicts.push((
+                test_uuid,
+                Verdict::wrong(String::from("no output submitted")),
+            )),
+        }
+        cases.insert(test_uuid, case);
+    }
+    let mut state = new_state(judge_uuid, task, cases);
+    for (test_uuid, verdict) in verdicts {
+        state.record_verdict(test_uuid, verdict);
+    }
+    state.number_of_done = state.number_of_cases;
+    state.submission_state = SubmissionState::Success;
+    Ok(state)
+}
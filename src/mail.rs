@@ -0,0 +1,142 @@
+use async_std::sync::Arc;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use std::fs::{create_dir_all, write};
+use std::path::PathBuf;
+use std::sync::RwLock;
+use uuid::Uuid;
+
+use crate::config::{Mail as MailConfig, MailTransportKind};
+use crate::CONFIG;
+
+#[derive(Clone, Debug)]
+pub struct Mail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+#[derive(Debug, Error)]
+pub enum MailError {
+    #[error("Mail transport is not configured")]
+    NotConfigured,
+    #[error("Invalid mail: {0}")]
+    Invalid(String),
+    #[error("Cannot send mail: {0}")]
+    Transport(String),
+}
+
+// Everything that sends mails goes through the transport set here, so deployments and
+// tests can plug their own in with `set_transport`.
+pub trait MailTransport: Send + Sync {
+    fn send(&self, mail: &Mail) -> Result<(), MailError>;
+}
+
+pub struct Smtp {
+    pub from: String,
+    pub transport: SmtpTransport,
+}
+
+impl Smtp {
+    pub fn new(config: &MailConfig) -> Result<Self, MailError> {
+        let host = config.smtp_host.clone().ok_or(MailError::NotConfigured)?;
+        let mut builder =
+            SmtpTransport::relay(&host).map_err(|err| MailError::Transport(err.to_string()))?;
+        if let Some(port) = config.smtp_port {
+            builder = builder.port(port);
+        }
+        if let (Some(user), Some(pass)) = (config.smtp_user.clone(), config.smtp_pass.clone()) {
+            builder = builder.credentials(Credentials::new(user, pass));
+        }
+        Ok(Self {
+            from: config.from.clone(),
+            transport: builder.build(),
+        })
+    }
+}
+
+impl MailTransport for Smtp {
+    fn send(&self, mail: &Mail) -> Result<(), MailError> {
+        let message = Message::builder()
+            .from(
+                self.from
+                    .parse()
+                    .map_err(|_| MailError::Invalid(self.from.clone()))?,
+            )
+            .to(mail
+                .to
+                .parse()
+                .map_err(|_| MailError::Invalid(mail.to.clone()))?)
+            .subject(mail.subject.clone())
+            .body(mail.body.clone())
+            .map_err(|err| MailError::Invalid(err.to_string()))?;
+        self.transport
+            .send(&message)
+            .map(|_| ())
+            .map_err(|err| MailError::Transport(err.to_string()))
+    }
+}
+
+// one file per mail, named `{UUID}.eml`
+pub struct FileSink {
+    pub from: String,
+    pub dir: PathBuf,
+}
+
+impl MailTransport for FileSink {
+    fn send(&self, mail: &Mail) -> Result<(), MailError> {
+        create_dir_all(&self.dir).map_err(|err| MailError::Transport(err.to_string()))?;
+        let content = format!(
+            "From: {}\r\nTo: {}\r\nSubject: {}\r\n\r\n{}\r\n",
+            self.from, mail.to, mail.subject, mail.body
+        );
+        write(self.dir.join(format!("{}.eml", Uuid::new_v4())), content)
+            .map_err(|err| MailError::Transport(err.to_string()))
+    }
+}
+
+pub struct Disabled;
+
+impl MailTransport for Disabled {
+    fn send(&self, _mail: &Mail) -> Result<(), MailError> {
+        Err(MailError::NotConfigured)
+    }
+}
+
+fn from_config() -> Arc<dyn MailTransport> {
+    match &CONFIG.mail {
+        Some(config) => match config.transport {
+            MailTransportKind::Smtp => match Smtp::new(config) {
+                Ok(smtp) => Arc::new(smtp),
+                Err(err) => {
+                    error!("{}", err);
+                    Arc::new(Disabled)
+                }
+            },
+            MailTransportKind::File => Arc::new(FileSink {
+                from: config.from.clone(),
+                dir: PathBuf::from(
+                    config
+                        .file_dir
+                        .clone()
+                        .unwrap_or_else(|| String::from("data/mails")),
+                ),
+            }),
+        },
+        None => Arc::new(Disabled),
+    }
+}
+
+lazy_static! {
+    static ref TRANSPORT: RwLock<Arc<dyn MailTransport>> = RwLock::new(from_config());
+}
+
+pub fn set_transport(transport: Arc<dyn MailTransport>) {
+    *TRANSPORT.write().unwrap() = transport;
+}
+
+// Transports block, so mails are sent off the async executor
+pub async fn send_mail(mail: Mail) -> Result<(), MailError> {
+    let transport = TRANSPORT.read().unwrap().clone();
+    async_std::task::spawn_blocking(move || transport.send(&mail)).await
+}
@@ -13,7 +13,9 @@ mod constants;
 mod contests;
 mod db;
 mod judge;
+mod mail;
 mod middlewares;
+mod plagiarism;
 mod tasks;
 
 #[cfg(test)]
@@ -23,6 +25,7 @@ use actix::dev::ToEnvelope;
 use actix::prelude::*;
 use actix_identity::IdentityMiddleware;
 use actix_session::{storage::RedisSessionStore, SessionMiddleware};
+use actix_web::dev::Service;
 use actix_web::{cookie::Key, guard, middleware, web, App, HttpServer};
 use async_graphql::*;
 use async_std::channel::{unbounded, Sender};
@@ -39,8 +42,10 @@ use uuid::Uuid;
 
 use crate::config::*;
 use crate::constants::*;
+use crate::contests::*;
 use crate::db::keydb::*;
 use crate::judge::*;
+use crate::plagiarism::*;
 
 lazy_static! {
     static ref CONFIG: Config = {
@@ -72,10 +77,23 @@ lazy_static! {
             state: None,
         };
         let handler_addr = handler_service.start();
+        let plagiarism_addr = PlagiarismService::start();
+        if CONFIG.plagiarism.check_interval_minutes > 0 {
+            PlagiarismScheduler {
+                service: plagiarism_addr.clone(),
+                interval: std::time::Duration::from_secs(
+                    CONFIG.plagiarism.check_interval_minutes * 60,
+                ),
+            }
+            .start();
+        }
+        let clarification_addr = ClarificationService::default().start();
         let state = WebState { handler_addr };
         Arc::new(WebData {
             state,
             judge_addr: judge_addr,
+            plagiarism_addr,
+            clarification_addr,
             source_db,
             judge_db,
         })
@@ -91,6 +109,8 @@ pub struct WebState {
 pub struct WebData {
     pub state: WebState,
     pub judge_addr: Addr<JudgeService>,
+    pub plagiarism_addr: Addr<PlagiarismService>,
+    pub clarification_addr: Addr<ClarificationService>,
     pub source_db: Addr<KeyDbService>,
     pub judge_db: Addr<KeyDbService>,
 }
@@ -119,6 +139,14 @@ async fn main() -> std::io::Result<()> {
         ));
     HttpServer::new(move || {
         App::new()
+            .wrap_fn(|req, srv| {
+                let auth = api::accounts::api_tokens::login_by_token(&req);
+                api::accounts::api_tokens::with_token(auth, srv.call(req))
+            })
+            .wrap_fn(|req, srv| {
+                api::accounts::password::check_session(&req);
+                srv.call(req)
+            })
             .wrap(middleware::Logger::default())
             .wrap(IdentityMiddleware::default())
             .wrap(SessionMiddleware::new(
@@ -130,20 +158,44 @@ async fn main() -> std::io::Result<()> {
                     .app_data(web::Data::new(Schema::new(
                         api::graphql::QueryRoot,
                         api::graphql::Mutation,
-                        EmptySubscription,
+                        api::graphql::SubscriptionRoot,
                     )))
                     .service(
                         web::scope("/accounts")
                             .service(api::accounts::login)
+                            .service(api::accounts::two_factor::login_totp)
                             .service(api::accounts::get_self)
+                            .service(api::accounts::profile::export_self)
+                            .service(api::accounts::admin::stop_impersonating)
+                            .service(api::accounts::admin::impersonate)
+                            .service(api::accounts::oidc::oidc_login)
+                            .service(api::accounts::oidc::oidc_callback)
                             .service(api::accounts::delete_self),
                     )
                     .service(web::scope("/handshake").service(api::handshake::ping))
+                    .service(web::scope("/judge").service(api::judge::download_source))
+                    .service(
+                        web::scope("/contests")
+                            .service(api::contests::clics::clics_contests)
+                            .service(api::contests::clics::clics_contest)
+                            .service(api::contests::clics::clics_state)
+                            .service(api::contests::clics::clics_scoreboard)
+                            .service(api::contests::clics::clics_event_feed)
+                            .service(api::contests::export_results)
+                            .service(api::contests::clics::clics_collection)
+                            .service(api::contests::clics::clics_item),
+                    )
                     .service(
                         web::resource("/gql")
                             .guard(guard::Post())
                             .to(api::graphql::gql_endpoint),
                     )
+                    .service(
+                        web::resource("/gql")
+                            .guard(guard::Get())
+                            .guard(guard::Header("upgrade", "websocket"))
+                            .to(api::graphql::gql_ws_endpoint),
+                    )
                     .service(
                         web::resource("/gql")
                             .guard(guard::Get())
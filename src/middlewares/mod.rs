@@ -1 +1,3 @@
 pub mod postgresql;
+pub mod rate_limit;
+pub mod redis;
@@ -0,0 +1,210 @@
+use actix_identity::IdentityExt;
+use actix_web::body::EitherBody;
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{RETRY_AFTER, X_FORWARDED_FOR};
+use actix_web::{Error, HttpRequest, HttpResponse};
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use redis::{Commands, RedisResult};
+use std::net::{IpAddr, SocketAddr};
+use std::rc::Rc;
+
+use super::redis::establish_redis;
+use crate::CONFIG;
+
+// At most `limit` hits for each subject in a fixed window of `window_secs`
+#[derive(Clone, Copy, Debug)]
+pub struct RateLimit {
+    pub name: &'static str,
+    pub limit: i64,
+    pub window_secs: usize,
+}
+
+impl RateLimit {
+    pub fn key(&self, subject: &str) -> String {
+        format!("rate_limit:{}:{}", self.name, subject)
+    }
+
+    // Seconds to wait once `count` hits are over the limit, with `ttl` left of the window
+    pub fn retry_after(&self, count: i64, ttl: i64) -> Option<usize> {
+        if count > self.limit {
+            Some(ttl.max(1) as usize)
+        } else {
+            None
+        }
+    }
+
+    fn try_hit(&self, subject: &str) -> RedisResult<Option<usize>> {
+        let mut redis = establish_redis()?;
+        let key = self.key(subject);
+        // the window starts with the first hit and is not extended by later ones
+        let (count, ttl): (i64, i64) = redis::pipe()
+            .atomic()
+            .cmd("SET")
+            .arg(&key)
+            .arg(0)
+            .arg("EX")
+            .arg(self.window_secs)
+            .arg("NX")
+            .ignore()
+            .incr(&key, 1)
+            .ttl(&key)
+            .query(&mut *redis)?;
+        Ok(self.retry_after(count, ttl))
+    }
+
+    // Counts a hit, returning the seconds to wait if the limit is exceeded. Requests are let
+    // through while redis is unreachable.
+    pub fn hit(&self, subject: &str) -> Option<usize> {
+        self.try_hit(subject).unwrap_or_else(|err| {
+            warn!("Rate limit {} is not checked: {}", self.name, err);
+            None
+        })
+    }
+
+    // Whether the limit is exceeded already, without counting a hit
+    pub fn is_exceeded(&self, subject: &str) -> bool {
+        establish_redis()
+            .and_then(|mut redis| redis.get::<_, Option<i64>>(self.key(subject)))
+            .map(|count| count.unwrap_or(0) >= self.limit)
+            .unwrap_or(false)
+    }
+
+    pub fn clear(&self, subject: &str) {
+        if let Ok(mut redis) = establish_redis() {
+            redis.del::<_, ()>(self.key(subject)).ok();
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum LimitBy {
+    ClientAddr,
+    User, // the client address for anonymous requests
+}
+
+fn subject_of(req: &ServiceRequest, by: LimitBy) -> String {
+    if let LimitBy::User = by {
+        if let Some(id) = req.get_identity().ok().and_then(|x| x.id().ok()) {
+            return format!("user:{}", id);
+        }
+    }
+    format!(
+        "ip:{}",
+        client_addr(req.request()).unwrap_or_else(|| String::from("unknown"))
+    )
+}
+
+// Address of the client. Proxies append the address they got a request from to
+// X-Forwarded-For, and anything left of that may have been made up by the client, so the
+// header is read from the right and the first address that is not a trusted proxy is taken.
+// Requests not coming from a trusted proxy are not believed at all.
+pub fn forwarded_client(
+    peer: IpAddr,
+    forwarded_for: Option<&str>,
+    trusted_proxies: &[IpAddr],
+) -> IpAddr {
+    let mut client = peer;
+    if let Some(forwarded_for) = forwarded_for {
+        for hop in forwarded_for.rsplit(',') {
+            if !trusted_proxies.contains(&client) {
+                break;
+            }
+            let hop = hop.trim();
+            match hop
+                .parse::<IpAddr>()
+                .or_else(|_| hop.parse::<SocketAddr>().map(|x| x.ip()))
+            {
+                Ok(addr) => client = addr,
+                Err(_) => break,
+            }
+        }
+    }
+    client
+}
+
+pub fn client_addr(req: &HttpRequest) -> Option<String> {
+    let forwarded_for = req
+        .headers()
+        .get_all(X_FORWARDED_FOR)
+        .filter_map(|x| x.to_str().ok())
+        .collect::<Vec<_>>()
+        .join(",");
+    let forwarded_for = Some(forwarded_for.as_str()).filter(|x| !x.is_empty());
+    let peer = req.peer_addr()?.ip();
+    Some(forwarded_client(peer, forwarded_for, &CONFIG.web.trusted_proxies).to_string())
+}
+
+// Middleware answering `429 Too Many Requests` over the limit, for example
+// `#[post("/login", wrap = "RateLimiter::new(limit, LimitBy::ClientAddr)")]`
+pub struct RateLimiter {
+    limit: RateLimit,
+    by: LimitBy,
+    hit: HitFn,
+}
+
+// Counts a hit of a subject, returning the seconds to wait if the limit is exceeded
+pub type HitFn = fn(&RateLimit, &str) -> Option<usize>;
+
+impl RateLimiter {
+    pub fn new(limit: RateLimit, by: LimitBy) -> Self {
+        Self::with_counter(limit, by, RateLimit::hit)
+    }
+
+    // Counts hits somewhere else than redis, for testing
+    pub fn with_counter(limit: RateLimit, by: LimitBy, hit: HitFn) -> Self {
+        Self { limit, by, hit }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimiter
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = RateLimiterMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RateLimiterMiddleware {
+            service: Rc::new(service),
+            limit: self.limit,
+            by: self.by,
+            hit: self.hit,
+        }))
+    }
+}
+
+pub struct RateLimiterMiddleware<S> {
+    service: Rc<S>,
+    limit: RateLimit,
+    by: LimitBy,
+    hit: HitFn,
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimiterMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        if let Some(retry_after) = (self.hit)(&self.limit, &subject_of(&req, self.by)) {
+            let res = HttpResponse::TooManyRequests()
+                .insert_header((RETRY_AFTER, retry_after.to_string()))
+                .finish();
+            return Box::pin(ready(Ok(req.into_response(res).map_into_right_body())));
+        }
+        let fut = self.service.call(req);
+        Box::pin(async move { fut.await.map(ServiceResponse::map_into_left_body) })
+    }
+}
@@ -0,0 +1,55 @@
+use redis::{Client, Connection, ConnectionLike, RedisResult};
+use std::ops::{Deref, DerefMut};
+use std::sync::Mutex;
+
+use crate::CONFIG;
+
+// Idle connections kept around for later requests
+const REDIS_IDLE_CONNECTIONS: usize = 16;
+
+lazy_static! {
+    static ref REDIS_CLIENT: Option<Client> = Client::open(CONFIG.redis.url.clone()).ok();
+    static ref REDIS_IDLE: Mutex<Vec<Connection>> = Mutex::new(Vec::new());
+}
+
+// Connection taken from the idle ones, or a new one if there are none. It goes back to them
+// when dropped, unless it has been closed in the meantime.
+pub struct RedisConnection(Option<Connection>);
+
+impl Deref for RedisConnection {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.0.as_ref().unwrap()
+    }
+}
+
+impl DerefMut for RedisConnection {
+    fn deref_mut(&mut self) -> &mut Connection {
+        self.0.as_mut().unwrap()
+    }
+}
+
+impl Drop for RedisConnection {
+    fn drop(&mut self) {
+        if let Some(conn) = self.0.take().filter(|conn| conn.is_open()) {
+            if let Ok(mut idle) = REDIS_IDLE.lock() {
+                if idle.len() < REDIS_IDLE_CONNECTIONS {
+                    idle.push(conn);
+                }
+            }
+        }
+    }
+}
+
+pub fn establish_redis() -> RedisResult<RedisConnection> {
+    let idle = REDIS_IDLE.lock().ok().and_then(|mut idle| idle.pop());
+    let conn = match idle {
+        Some(conn) => conn,
+        None => match &*REDIS_CLIENT {
+            Some(client) => client.get_connection()?,
+            None => Client::open(CONFIG.redis.url.clone())?.get_connection()?,
+        },
+    };
+    Ok(RedisConnection(Some(conn)))
+}
@@ -0,0 +1,205 @@
+pub mod winnowing;
+
+use actix::prelude::*;
+use chrono::prelude::*;
+use std::collections::HashMap;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::db::contests::*;
+use crate::db::judge::*;
+use crate::db::keydb::*;
+use crate::db::languages::find_language;
+use crate::db::models::{Contest, PlagiarismCheck, Similarity, Submission};
+use crate::db::plagiarism::*;
+use crate::APPDATA;
+
+use winnowing::*;
+
+// pairs below this score are not stored
+pub const SIMILARITY_THRESHOLD: f64 = 0.3;
+
+#[derive(Clone, Debug, Message)]
+#[rtype(result = "()")]
+pub enum PlagiarismMessage {
+    CheckTask(Uuid),
+    CheckContest(Uuid),
+    CheckEndedContests, // contests that ended since their last check
+}
+
+pub struct PlagiarismService;
+
+impl PlagiarismService {
+    pub fn start() -> Addr<Self> {
+        SyncArbiter::start(1, || Self)
+    }
+}
+
+impl Actor for PlagiarismService {
+    type Context = SyncContext<Self>;
+
+    fn started(&mut self, _ctx: &mut Self::Context) {
+        debug!("Started plagiarism detection service");
+    }
+}
+
+// Asks the service to check ended contests every `interval`
+pub struct PlagiarismScheduler {
+    pub service: Addr<PlagiarismService>,
+    pub interval: Duration,
+}
+
+impl Actor for PlagiarismScheduler {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Context<Self>) {
+        info!(
+            "Checking ended contests for plagiarism every {:?}",
+            self.interval
+        );
+        ctx.run_interval(self.interval, |this, _| {
+            this.service.do_send(PlagiarismMessage::CheckEndedContests)
+        });
+    }
+}
+
+// Ended contests not checked since they ended, which also catches contests whose end
+// was moved after their check
+pub fn due_contests(
+    contests: &[Contest],
+    checks: &[PlagiarismCheck],
+    now: DateTime<Utc>,
+) -> Vec<Uuid> {
+    let checked: HashMap<Uuid, DateTime<Utc>> = checks
+        .iter()
+        .map(|x| (x.contest_pk, x.checked_at))
+        .collect();
+    contests
+        .iter()
+        .filter(|contest| contest.end_at <= now)
+        .filter(|contest| {
+            checked
+                .get(&contest.pk)
+                .map(|at| *at < contest.end_at)
+                .unwrap_or(true)
+        })
+        .map(|contest| contest.pk)
+        .collect()
+}
+
+// Pairs of submissions by different users in the same language that score at least
+// SIMILARITY_THRESHOLD, as (lower pk, higher pk, score)
+pub fn similar_pairs(items: &[(Submission, Vec<Fingerprint>)]) -> Vec<(Uuid, Uuid, f64)> {
+    let mut pairs = vec![];
+    for (i, (a, a_prints)) in items.iter().enumerate() {
+        for (b, b_prints) in items.iter().skip(i + 1) {
+            if a.lang_uuid != b.lang_uuid || a.user_pk == b.user_pk {
+                continue;
+            }
+            let score = similarity(a_prints, b_prints);
+            if score < SIMILARITY_THRESHOLD {
+                continue;
+            }
+            pairs.push((a.pk.min(b.pk), a.pk.max(b.pk), score));
+        }
+    }
+    pairs
+}
+
+async fn read_fingerprints(items: Vec<Submission>) -> Vec<(Submission, Vec<Fingerprint>)> {
+    let mut sources = get_many(&APPDATA.source_db, items.iter().map(|x| x.pk).collect()).await;
+    let mut styles: HashMap<Uuid, Option<CommentStyle>> = HashMap::new();
+    items
+        .into_iter()
+        .filter_map(|submission| {
+            let style = (*styles.entry(submission.lang_uuid).or_insert_with(|| {
+                find_language(submission.lang_uuid)
+                    .ok()
+                    .map(|lang| CommentStyle::from_extension(&lang.file_extension))
+            }))?;
+            let source = sources.remove(&submission.pk)?;
+            Some((submission, fingerprints(&tokenize(&source, style))))
+        })
+        .collect()
+}
+
+async fn check_submissions(task_pk: Uuid, contest_pk: Option<Uuid>, items: Vec<Submission>) {
+    let now = Utc::now();
+    let forms = similar_pairs(&read_fingerprints(items).await)
+        .into_iter()
+        .map(|(submission_a, submission_b, score)| Similarity {
+            pk: Uuid::new_v4(),
+            task_pk,
+            contest_pk,
+            submission_a,
+            submission_b,
+            score,
+            computed_at: now,
+        })
+        .collect();
+    if let Err(err) = replace_similarities(task_pk, contest_pk, forms) {
+        error!("Cannot save similarities of {}: {}", task_pk, err);
+    }
+}
+
+async fn check_contest(contest: &Contest) {
+    match contest_task_pks(contest.pk) {
+        Ok(task_pks) => {
+            for task_pk in task_pks {
+                let query = SubmissionQuery {
+                    task_pks: Some(vec![task_pk]),
+                    issued_after: Some(contest.start_at),
+                    issued_before: Some(contest.end_at),
+                    ..Default::default()
+                };
+                if let Ok(items) = search_submissions(query) {
+                    check_submissions(task_pk, Some(contest.pk), items).await;
+                }
+            }
+        }
+        Err(err) => error!("Cannot read tasks of {}: {}", contest.pk, err),
+    }
+}
+
+impl Handler<PlagiarismMessage> for PlagiarismService {
+    type Result = ();
+
+    fn handle(&mut self, msg: PlagiarismMessage, ctx: &mut Self::Context) -> Self::Result {
+        let sys = System::new();
+        sys.block_on(async move {
+            match msg {
+                PlagiarismMessage::CheckTask(task_pk) => {
+                    let query = SubmissionQuery {
+                        task_pks: Some(vec![task_pk]),
+                        ..Default::default()
+                    };
+                    if let Ok(items) = search_submissions(query) {
+                        check_submissions(task_pk, None, items).await;
+                    }
+                }
+                PlagiarismMessage::CheckContest(contest_pk) => {
+                    if let Ok(contest) = find_contest(contest_pk) {
+                        check_contest(&contest).await;
+                    }
+                }
+                PlagiarismMessage::CheckEndedContests => {
+                    let now = Utc::now();
+                    if let (Ok(contests), Ok(checks)) = (all_contests(), plagiarism_checks()) {
+                        for contest_pk in due_contests(&contests, &checks, now) {
+                            if let Some(contest) = contests.iter().find(|x| x.pk == contest_pk) {
+                                check_contest(contest).await;
+                            }
+                            if let Err(err) = save_plagiarism_check(PlagiarismCheck {
+                                contest_pk,
+                                checked_at: now,
+                            }) {
+                                error!("Cannot save plagiarism check of {}: {}", contest_pk, err);
+                            }
+                        }
+                    }
+                }
+            }
+            info!("Plagiarism check is done: {:?}", msg);
+        });
+    }
+}
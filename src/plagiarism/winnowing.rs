@@ -0,0 +1,205 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+// implementation of "Winnowing: Local Algorithms for Document Fingerprinting" (MOSS)
+pub const K_GRAM: usize = 5;
+pub const WINDOW: usize = 4;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Token {
+    pub text: String,
+    pub line: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Fingerprint {
+    pub hash: u64,
+    pub line_start: usize,
+    pub line_end: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Match {
+    pub left_start: usize,
+    pub left_end: usize,
+    pub right_start: usize,
+    pub right_end: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CommentStyle {
+    CLike, // //, /* */
+    Hash,  // #
+}
+
+impl CommentStyle {
+    pub fn from_extension(ext: &str) -> Self {
+        match ext {
+            "py" | "rb" | "sh" | "pl" => Self::Hash,
+            _ => Self::CLike,
+        }
+    }
+}
+
+// Identifiers, numbers and literals are normalized so that renaming does not hide copies.
+pub fn tokenize(source: &str, style: CommentStyle) -> Vec<Token> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = vec![];
+    let mut line = 1;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\n' {
+            line += 1;
+            i += 1;
+        } else if c.is_whitespace() {
+            i += 1;
+        } else if (style == CommentStyle::CLike && c == '/' && chars.get(i + 1) == Some(&'/'))
+            || (style == CommentStyle::Hash && c == '#')
+        {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if style == CommentStyle::CLike && c == '/' && chars.get(i + 1) == Some(&'*') {
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                if chars[i] == '\n' {
+                    line += 1;
+                }
+                i += 1;
+            }
+            i += 2;
+        } else if c == '"' || c == '\'' {
+            let start_line = line;
+            i += 1;
+            while i < chars.len() && chars[i] != c {
+                if chars[i] == '\\' {
+                    i += 1;
+                } else if chars[i] == '\n' {
+                    line += 1;
+                }
+                i += 1;
+            }
+            i += 1;
+            tokens.push(Token {
+                text: String::from("str"),
+                line: start_line,
+            });
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect();
+            let text = if is_keyword(&word) {
+                word
+            } else {
+                String::from("id")
+            };
+            tokens.push(Token { text, line });
+        } else if c.is_ascii_digit() {
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '.') {
+                i += 1;
+            }
+            tokens.push(Token {
+                text: String::from("num"),
+                line,
+            });
+        } else {
+            tokens.push(Token {
+                text: c.to_string(),
+                line,
+            });
+            i += 1;
+        }
+    }
+    tokens
+}
+
+fn is_keyword(word: &str) -> bool {
+    const KEYWORDS: &[&str] = &[
+        "if", "else", "for", "while", "do", "return", "break", "continue", "switch", "case", "int",
+        "long", "char", "double", "float", "bool", "void", "auto", "const", "struct", "class",
+        "def", "in", "and", "or", "not", "import", "fn", "let", "mut", "match",
+    ];
+    KEYWORDS.contains(&word)
+}
+
+fn hash_of<T: Hash>(value: T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+pub fn fingerprints(tokens: &[Token]) -> Vec<Fingerprint> {
+    if tokens.len() < K_GRAM {
+        return vec![];
+    }
+    let grams: Vec<Fingerprint> = tokens
+        .windows(K_GRAM)
+        .map(|gram| Fingerprint {
+            hash: hash_of(gram.iter().map(|x| x.text.as_str()).collect::<Vec<_>>()),
+            line_start: gram[0].line,
+            line_end: gram[K_GRAM - 1].line,
+        })
+        .collect();
+    let mut selected: Vec<(usize, Fingerprint)> = vec![];
+    for (offset, window) in grams.windows(WINDOW.min(grams.len())).enumerate() {
+        // rightmost minimal hash of the window
+        let (idx, min) = window
+            .iter()
+            .enumerate()
+            .rev()
+            .min_by_key(|(_, x)| x.hash)
+            .unwrap();
+        let pos = offset + idx;
+        if selected.last().map(|(last, _)| *last) != Some(pos) {
+            selected.push((pos, min.clone()));
+        }
+    }
+    selected.into_iter().map(|(_, x)| x).collect()
+}
+
+// ratio of shared fingerprints to the smaller fingerprint set
+pub fn similarity(left: &[Fingerprint], right: &[Fingerprint]) -> f64 {
+    let left_set: HashSet<u64> = left.iter().map(|x| x.hash).collect();
+    let right_set: HashSet<u64> = right.iter().map(|x| x.hash).collect();
+    let denom = left_set.len().min(right_set.len());
+    if denom == 0 {
+        0.0
+    } else {
+        left_set.intersection(&right_set).count() as f64 / denom as f64
+    }
+}
+
+// line ranges of shared fingerprints, merged when they overlap on both sides
+pub fn matches(left: &[Fingerprint], right: &[Fingerprint]) -> Vec<Match> {
+    let mut right_map: HashMap<u64, &Fingerprint> = HashMap::new();
+    for x in right {
+        right_map.entry(x.hash).or_insert(x);
+    }
+    let mut ret: Vec<Match> = vec![];
+    for x in left {
+        if let Some(y) = right_map.get(&x.hash) {
+            if let Some(last) = ret.last_mut() {
+                if x.line_start <= last.left_end + 1
+                    && y.line_start <= last.right_end + 1
+                    && y.line_end + 1 >= last.right_start
+                {
+                    last.left_end = last.left_end.max(x.line_end);
+                    last.right_start = last.right_start.min(y.line_start);
+                    last.right_end = last.right_end.max(y.line_end);
+                    continue;
+                }
+            }
+            ret.push(Match {
+                left_start: x.line_start,
+                left_end: x.line_end,
+                right_start: y.line_start,
+                right_end: y.line_end,
+            });
+        }
+    }
+    ret
+}
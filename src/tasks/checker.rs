@@ -1,8 +1,150 @@
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+// checker.toml either names a checker program, run by judges with testlib exit codes
+//  checker_language = "{language UUID}"
+//  checker_file = "{checker file}"
+// or one of built-in comparators, evaluated here for output-only submissions
+//  builtin = "Exact" | "Token" | "CaseInsensitive" | "UnorderedLines"
+//  builtin = { Float = { abs = 1e-6, rel = 1e-6 } }
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Checker {
-    pub checker_language: Uuid,
-    pub checker_file: String,
+    pub checker_language: Option<Uuid>,
+    pub checker_file: Option<String>,
+    pub builtin: Option<BuiltinChecker>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+pub enum BuiltinChecker {
+    Exact,
+    Token,
+    CaseInsensitive,
+    UnorderedLines,
+    Float { abs: f64, rel: f64 },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Verdict {
+    pub score: f64,
+    pub message: String,
+}
+
+impl Verdict {
+    pub fn accepted() -> Self {
+        Self {
+            score: 1.0,
+            message: String::from("ok"),
+        }
+    }
+
+    pub fn wrong(message: String) -> Self {
+        Self {
+            score: 0.0,
+            message,
+        }
+    }
+}
+
+fn tokens(s: &str) -> Vec<&str> {
+    s.split_whitespace().collect()
+}
+
+fn float_eq(expected: f64, found: f64, abs: f64, rel: f64) -> bool {
+    let diff = (expected - found).abs();
+    diff <= abs || diff <= rel * expected.abs()
+}
+
+impl BuiltinChecker {
+    pub fn check(&self, expected: &str, found: &str) -> Verdict {
+        match self {
+            Self::Exact => {
+                let expected = expected.trim_end().replace("\r\n", "\n");
+                let found = found.trim_end().replace("\r\n", "\n");
+                for (idx, (x, y)) in expected.lines().zip(found.lines()).enumerate() {
+                    if x != y {
+                        return Verdict::wrong(format!("line {} differs", idx + 1));
+                    }
+                }
+                if expected.lines().count() != found.lines().count() {
+                    Verdict::wrong(format!(
+                        "expected {} lines, found {}",
+                        expected.lines().count(),
+                        found.lines().count()
+                    ))
+                } else {
+                    Verdict::accepted()
+                }
+            }
+            Self::Token | Self::CaseInsensitive => {
+                let expected = tokens(expected);
+                let found = tokens(found);
+                for (idx, (x, y)) in expected.iter().zip(found.iter()).enumerate() {
+                    let same = if *self == Self::CaseInsensitive {
+                        x.to_lowercase() == y.to_lowercase()
+                    } else {
+                        x == y
+                    };
+                    if !same {
+                        return Verdict::wrong(format!(
+                            "expected {}, found {} on token {}",
+                            x,
+                            y,
+                            idx + 1
+                        ));
+                    }
+                }
+                if expected.len() != found.len() {
+                    Verdict::wrong(format!(
+                        "expected {} tokens, found {}",
+                        expected.len(),
+                        found.len()
+                    ))
+                } else {
+                    Verdict::accepted()
+                }
+            }
+            Self::UnorderedLines => {
+                let normalize = |s: &str| {
+                    let mut lines: Vec<String> = s
+                        .lines()
+                        .map(|x| x.trim().to_string())
+                        .filter(|x| !x.is_empty())
+                        .collect();
+                    lines.sort();
+                    lines
+                };
+                if normalize(expected) == normalize(found) {
+                    Verdict::accepted()
+                } else {
+                    Verdict::wrong(String::from("lines differ"))
+                }
+            }
+            Self::Float { abs, rel } => {
+                let expected = tokens(expected);
+                let found = tokens(found);
+                if expected.len() != found.len() {
+                    return Verdict::wrong(format!(
+                        "expected {} tokens, found {}",
+                        expected.len(),
+                        found.len()
+                    ));
+                }
+                for (idx, (x, y)) in expected.iter().zip(found.iter()).enumerate() {
+                    let same = match (x.parse::<f64>(), y.parse::<f64>()) {
+                        (Ok(x), Ok(y)) => float_eq(x, y, *abs, *rel),
+                        _ => x == y,
+                    };
+                    if !same {
+                        return Verdict::wrong(format!(
+                            "expected {}, found {} on token {}",
+                            x,
+                            y,
+                            idx + 1
+                        ));
+                    }
+                }
+                Verdict::accepted()
+            }
+        }
+    }
 }
@@ -25,11 +25,84 @@ pub struct TaskLoader {
     pub attachments: PathBuf,
 }
 
+#[derive(Clone, Debug)]
+pub struct Sample {
+    pub name: String,
+    pub input: String,
+    pub output: String,
+}
+
 #[derive(Clone, Debug)]
 pub struct SubtaskLoader {
     pub subtasks: HashMap<String, (Subtask, Vec<Test>)>,
 }
 
+// A testcase shared by several subtasks, which is judged only once
+#[derive(Clone, Debug)]
+pub struct SharedTest<'a> {
+    pub name: &'a String,
+    pub test: &'a Test,
+    pub subtasks: Vec<&'a Subtask>, // in order of names
+}
+
+impl<'a> SharedTest<'a> {
+    // the strictest limits among subtasks, since a single run is scored for all of them
+    pub fn limits(&self) -> Limits {
+        let limits: Vec<Limits> = self.subtasks.iter().map(|x| x.limits(self.name)).collect();
+        Limits {
+            time_limit: limits.iter().filter_map(|x| x.time_limit).reduce(f64::min),
+            memory_limit: limits.iter().filter_map(|x| x.memory_limit).min(),
+        }
+    }
+}
+
+impl SubtaskLoader {
+    pub fn names(&self) -> Vec<&String> {
+        let mut names: Vec<&String> = self.subtasks.keys().collect();
+        names.sort();
+        names
+    }
+
+    pub fn shared_tests(&self) -> Vec<SharedTest> {
+        let mut ret: Vec<SharedTest> = vec![];
+        for name in self.names() {
+            let (subtask, tests) = &self.subtasks[name];
+            for (testcase, test) in subtask.testcases.iter().zip(tests.iter()) {
+                match ret.iter_mut().find(|x| x.name == testcase) {
+                    Some(shared) => shared.subtasks.push(subtask),
+                    None => ret.push(SharedTest {
+                        name: testcase,
+                        test,
+                        subtasks: vec![subtask],
+                    }),
+                }
+            }
+        }
+        ret
+    }
+
+    // every dependency exists and there is no cycle
+    pub fn has_valid_dependencies(&self) -> bool {
+        fn visit(loader: &SubtaskLoader, name: &String, path: &mut Vec<String>) -> bool {
+            if path.contains(name) {
+                return false;
+            }
+            match loader.subtasks.get(name) {
+                Some((subtask, _)) => {
+                    path.push(name.clone());
+                    let ok = subtask.dependencies.iter().all(|x| visit(loader, x, path));
+                    path.pop();
+                    ok
+                }
+                None => false,
+            }
+        }
+        self.subtasks
+            .keys()
+            .all(|name| visit(self, name, &mut vec![]))
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct StatementLoader {
     pub statements: HashMap<LanguageId, (Statement, PathBuf)>,
@@ -45,7 +118,48 @@ pub struct GraderLoader {
 #[derive(Clone, Debug)]
 pub struct CheckerLoader {
     pub checker: Checker,
-    pub checker_file: PathBuf,
+    pub checker_file: Option<PathBuf>, // None for built-in checkers
+}
+
+impl TaskLoader {
+    pub fn accepts_language(&self, lang_uuid: &Uuid) -> bool {
+        !self.task.task_type.requires_stub() || self.graders.graders.contains_key(lang_uuid)
+    }
+
+    pub fn statement(&self, language: &str) -> Option<&(Statement, PathBuf)> {
+        let lang = TryInto::<&LanguageId>::try_into(language).ok()?;
+        self.statements.statements.get(lang)
+    }
+
+    pub fn statement_languages(&self) -> Vec<String> {
+        self.statements
+            .statements
+            .values()
+            .filter_map(|(_, path)| Some(path.file_name()?.to_str()?.to_string()))
+            .collect()
+    }
+
+    // in order of subtask names
+    pub async fn samples(&self) -> io::Result<Vec<Sample>> {
+        let mut samples: Vec<Sample> = vec![];
+        for shared in self.subtasks.shared_tests() {
+            if shared.subtasks.iter().any(|x| x.is_sample(shared.name)) {
+                samples.push(Sample {
+                    name: shared.name.clone(),
+                    input: read_to_string(shared.test.stdin.clone()).await?,
+                    output: read_to_string(shared.test.stdout.clone()).await?,
+                });
+            }
+        }
+        Ok(samples)
+    }
+
+    pub fn has_testcase(&self, name: &str) -> bool {
+        self.subtasks
+            .subtasks
+            .values()
+            .any(|(subtask, _)| subtask.testcases.iter().any(|x| x == name))
+    }
 }
 
 pub async fn load_task(path: PathBuf) -> io::Result<TaskLoader> {
@@ -137,8 +251,29 @@ pub async fn load_statements(path: PathBuf) -> io::Result<StatementLoader> {
     Ok(StatementLoader { statements })
 }
 
+// stems of `*.in` files in the tests directory
+async fn testcase_names(path: PathBuf) -> io::Result<Vec<String>> {
+    let mut names = vec![];
+    if !path.is_dir().await {
+        return Ok(names);
+    }
+    let mut entries = read_dir(path).await?;
+    while let Some(entry) = entries.next().await {
+        let path = entry?.path();
+        if path.extension().map_or(false, |x| x == "in") {
+            if let Some(stem) = path.file_stem().and_then(|x| x.to_str()) {
+                names.push(stem.to_string());
+            }
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
 pub async fn load_subtasks(path: PathBuf) -> io::Result<SubtaskLoader> {
     assert_eq!(path.is_dir().await, true);
+    let tests_path = path.parent().unwrap().join(TESTS);
+    let available = testcase_names(tests_path.clone()).await?;
     let mut subtasks = HashMap::new();
     let mut entries = read_dir(path.clone()).await?;
     while let Some(entry) = entries.next().await {
@@ -146,29 +281,29 @@ pub async fn load_subtasks(path: PathBuf) -> io::Result<SubtaskLoader> {
         if let Ok(file_t) = entry.file_type().await {
             if file_t.is_file() && entry.path().extension().unwrap() == "toml" {
                 let f = read_to_string(entry.path()).await?;
-                let subtask: Subtask = toml::from_str(&f).expect("Cannot read {subtask name}.toml");
+                let mut subtask: Subtask =
+                    toml::from_str(&f).expect("Cannot read {subtask name}.toml");
+                if let Err(pattern) = subtask.expand_testcases(&available) {
+                    warn!("No testcase of {} matches {:?}", subtask.name, pattern);
+                    return Err(io::Error::from(io::ErrorKind::InvalidData));
+                }
                 let mut tests = vec![];
                 for x in subtask.testcases.clone() {
                     tests.push(Test {
-                        stdin: path
-                            .parent()
-                            .unwrap()
-                            .join(TESTS)
-                            .join(x.clone())
-                            .with_extension("in"),
-                        stdout: path
-                            .parent()
-                            .unwrap()
-                            .join(TESTS)
-                            .join(x.clone())
-                            .with_extension("out"),
+                        stdin: tests_path.join(x.clone()).with_extension("in"),
+                        stdout: tests_path.join(x.clone()).with_extension("out"),
                     });
                 }
                 subtasks.insert(subtask.name.clone(), (subtask, tests));
             }
         }
     }
-    Ok(SubtaskLoader { subtasks })
+    let loader = SubtaskLoader { subtasks };
+    if loader.has_valid_dependencies() {
+        Ok(loader)
+    } else {
+        Err(io::Error::from(io::ErrorKind::InvalidData))
+    }
 }
 
 pub async fn load_checker(path: PathBuf) -> io::Result<CheckerLoader> {
@@ -189,18 +324,23 @@ pub async fn load_checker(path: PathBuf) -> io::Result<CheckerLoader> {
             }
         }
     }
-    if let Some(checker) = checker {
-        let checker_path = path.join(checker.checker_file.clone());
-        if checker_path.is_file().await {
-            Ok(CheckerLoader {
-                checker,
-                checker_file: checker_path,
-            })
-        } else {
-            Err(io::Error::from(io::ErrorKind::NotFound))
+    match checker {
+        Some(checker) if checker.builtin.is_some() => Ok(CheckerLoader {
+            checker,
+            checker_file: None,
+        }),
+        Some(checker) if checker.checker_language.is_some() => {
+            let checker_path = path.join(checker.checker_file.clone().unwrap_or_default());
+            if checker_path.is_file().await {
+                Ok(CheckerLoader {
+                    checker,
+                    checker_file: Some(checker_path),
+                })
+            } else {
+                Err(io::Error::from(io::ErrorKind::NotFound))
+            }
         }
-    } else {
-        Err(io::Error::from(io::ErrorKind::NotFound))
+        _ => Err(io::Error::from(io::ErrorKind::NotFound)),
     }
 }
 
@@ -4,6 +4,7 @@ pub mod graders;
 pub mod loader;
 pub mod statements;
 pub mod subtasks;
+pub mod types;
 
 pub use loader::*;
 
@@ -18,6 +19,7 @@ use uuid::Uuid;
 
 use crate::constants::*;
 use crate::middlewares::postgresql::*;
+use subtasks::Limits;
 
 // implementation of https://cms.readthedocs.io/en/v1.4/Task%20types.html
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -26,7 +28,24 @@ pub enum TaskTypes {
     OutputOnly,
     Communication,
     TwoSteps,
-    Custom(()), // not implemented yet
+    Custom(String), // name of a task type registered by types::register_task_type
+}
+
+impl TaskTypes {
+    pub fn name(&self) -> String {
+        match self {
+            Self::Batch => types::BATCH.to_string(),
+            Self::OutputOnly => types::OUTPUT_ONLY.to_string(),
+            Self::Communication => types::COMMUNICATION.to_string(),
+            Self::TwoSteps => types::TWO_STEPS.to_string(),
+            Self::Custom(name) => name.clone(),
+        }
+    }
+
+    // whether a submission needs a stub directory of its language in graders
+    pub fn requires_stub(&self) -> bool {
+        matches!(self, Self::Communication | Self::TwoSteps)
+    }
 }
 
 // * note that for some notations
@@ -68,6 +87,49 @@ pub struct Task {
     pub score_precision: Option<usize>,
     pub task_type_params: Parms,
     pub description: Option<String>,
+    #[serde(default)]
+    pub feedback: Feedback,
+    #[serde(default)]
+    pub time_multipliers: HashMap<Uuid, f64>, // [time_multipliers] of each language UUID
+}
+
+impl Task {
+    // (time limit in ms, memory limit in kB) of a testcase for the language
+    pub fn effective_limits(&self, limits: Limits, lang_uuid: Option<Uuid>) -> (u64, u64) {
+        let multiplier = lang_uuid
+            .and_then(|x| self.time_multipliers.get(&x).copied())
+            .unwrap_or(1.0);
+        let time_limit = limits.time_limit.unwrap_or(self.time_limit) * multiplier;
+        (
+            (time_limit * 1000.0) as u64,
+            limits.memory_limit.unwrap_or(self.memory_limit),
+        )
+    }
+}
+
+// [feedback] in task.toml; tests of the listed subtasks reveal the checker comment
+// and previews of input and output to contestants
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct Feedback {
+    #[serde(default)]
+    pub subtasks: Vec<String>,
+    pub preview_length: Option<usize>, // for default, FEEDBACK_PREVIEW_LENGTH
+}
+
+impl Feedback {
+    pub fn reveals(&self, subtask: &str) -> bool {
+        self.subtasks.iter().any(|x| x == subtask)
+    }
+
+    pub fn preview(&self, raw: &[u8]) -> String {
+        let len = self.preview_length.unwrap_or(FEEDBACK_PREVIEW_LENGTH);
+        let s = String::from_utf8_lossy(raw);
+        if s.chars().count() > len {
+            format!("{}...", s.chars().take(len).collect::<String>())
+        } else {
+            s.to_string()
+        }
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Default)]
@@ -1,12 +1,23 @@
+use async_graphql::*;
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Enum, PartialEq, Eq)]
 pub enum StatementFormat {
     Markdown,
     Tex,
     Pdf,
 }
 
+impl StatementFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Tex => "tex",
+            Self::Pdf => "pdf",
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Statement {
     pub input: StatementFormat,
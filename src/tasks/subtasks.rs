@@ -1,9 +1,96 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use uuid::Uuid;
 
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Default)]
+pub struct Limits {
+    pub time_limit: Option<f64>,   // in seconds
+    pub memory_limit: Option<u64>, // in kB
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Subtask {
     pub name: String,
     pub score: f64,
-    pub testcases: Vec<String>,
+    pub testcases: Vec<String>, // names or glob patterns like "3-*", expanded on load
+    #[serde(default)]
+    pub dependencies: Vec<String>, // subtasks which must pass for this one to score
+    #[serde(default)]
+    pub sample: bool, // every testcase of this subtask is a public example
+    #[serde(default)]
+    pub samples: Vec<String>, // testcases which are public examples
+    pub time_limit: Option<f64>,
+    pub memory_limit: Option<u64>,
+    #[serde(default)]
+    pub limits: HashMap<String, Limits>, // [limits.{testcase}] overrides of this subtask
+}
+
+// `*` matches any sequence and `?` any single character
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = star {
+            p = star_p + 1;
+            n = star_n + 1;
+            star = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|x| *x == '*')
+}
+
+impl Subtask {
+    // Replaces patterns with the matching testcases in `available`, keeping the declared
+    // order. Fails with the first pattern matching nothing, as a subtask without a test
+    // would be scored for free.
+    pub fn expand_testcases(&mut self, available: &[String]) -> Result<(), String> {
+        let mut testcases: Vec<String> = vec![];
+        for pattern in &self.testcases {
+            let mut matched: Vec<&String> = if pattern.contains(|x| x == '*' || x == '?') {
+                available
+                    .iter()
+                    .filter(|x| glob_match(pattern, x))
+                    .collect()
+            } else {
+                vec![pattern]
+            };
+            if matched.is_empty() {
+                return Err(pattern.clone());
+            }
+            matched.sort();
+            for x in matched {
+                if !testcases.contains(x) {
+                    testcases.push(x.clone());
+                }
+            }
+        }
+        if testcases.is_empty() {
+            return Err(String::new());
+        }
+        self.testcases = testcases;
+        Ok(())
+    }
+
+    pub fn is_sample(&self, testcase: &str) -> bool {
+        self.sample || self.samples.iter().any(|x| x == testcase)
+    }
+
+    // a testcase override takes precedence over the subtask
+    pub fn limits(&self, testcase: &str) -> Limits {
+        let test = self.limits.get(testcase).copied().unwrap_or_default();
+        Limits {
+            time_limit: test.time_limit.or(self.time_limit),
+            memory_limit: test.memory_limit.or(self.memory_limit),
+        }
+    }
 }
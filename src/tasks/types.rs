@@ -0,0 +1,201 @@
+use async_std::io;
+use async_std::sync::Arc;
+use futures_util::future::{BoxFuture, FutureExt};
+use std::collections::HashMap;
+use std::sync::RwLock;
+use uuid::Uuid;
+
+use judge_protocol::judge::*;
+
+use super::*;
+use crate::judge::api::Submission;
+use crate::judge::request::*;
+
+pub const BATCH: &'static str = "Batch";
+pub const OUTPUT_ONLY: &'static str = "OutputOnly";
+pub const COMMUNICATION: &'static str = "Communication";
+pub const TWO_STEPS: &'static str = "TwoSteps";
+
+#[derive(Clone, Debug)]
+pub enum SubmissionData {
+    Source(Uuid, String),             // language UUID, source code
+    Outputs(HashMap<String, String>), // testcase name, output
+}
+
+impl SubmissionData {
+    // what is kept in source_db
+    pub fn to_stored(&self) -> String {
+        match self {
+            Self::Source(_, source) => source.clone(),
+            Self::Outputs(outputs) => serde_json::to_string(outputs).unwrap(),
+        }
+    }
+}
+
+// A task type decides how a package is validated, how a submission becomes a judge
+// request and how results from judges are aggregated, so JudgeService stays generic.
+pub trait TaskTypeHandler: Send + Sync {
+    fn validate(&self, task: &TaskLoader) -> bool;
+
+    fn accepts(&self, data: &SubmissionData) -> bool;
+
+    fn make_request<'a>(
+        &'a self,
+        judge_uuid: Uuid,
+        task: &'a TaskLoader,
+        data: &'a SubmissionData,
+    ) -> BoxFuture<'a, io::Result<(Option<RequestJudge>, Submission)>>;
+
+    fn apply(&self, state: &mut Submission, result: JudgeState) {
+        state.apply(result);
+        state.score = self.score(state);
+    }
+
+    fn score(&self, state: &Submission) -> f64 {
+        if state.subtasks.is_empty() {
+            state.cases.values().filter_map(|case| case.score).sum()
+        } else {
+            state.subtask_results().iter().map(|x| x.score).sum()
+        }
+    }
+}
+
+fn make_source_request<'a>(
+    judge_uuid: Uuid,
+    task: &'a TaskLoader,
+    data: &'a SubmissionData,
+) -> BoxFuture<'a, io::Result<(Option<RequestJudge>, Submission)>> {
+    async move {
+        match data {
+            SubmissionData::Source(lang_uuid, source) => {
+                let (request, state) =
+                    make_request(judge_uuid, task, *lang_uuid, source.clone()).await?;
+                Ok((Some(request), state))
+            }
+            _ => Err(io::Error::from(io::ErrorKind::InvalidInput)),
+        }
+    }
+    .boxed()
+}
+
+// executed submissions are checked by judges, which need a checker program
+fn has_checker_program(task: &TaskLoader) -> bool {
+    task.checker.checker_file.is_some()
+}
+
+pub struct Batch;
+
+impl TaskTypeHandler for Batch {
+    fn validate(&self, task: &TaskLoader) -> bool {
+        has_checker_program(task)
+    }
+
+    fn accepts(&self, data: &SubmissionData) -> bool {
+        matches!(data, SubmissionData::Source(..))
+    }
+
+    fn make_request<'a>(
+        &'a self,
+        judge_uuid: Uuid,
+        task: &'a TaskLoader,
+        data: &'a SubmissionData,
+    ) -> BoxFuture<'a, io::Result<(Option<RequestJudge>, Submission)>> {
+        make_source_request(judge_uuid, task, data)
+    }
+}
+
+pub struct OutputOnly;
+
+impl TaskTypeHandler for OutputOnly {
+    // outputs are checked here, so only by built-in checkers
+    fn validate(&self, task: &TaskLoader) -> bool {
+        task.checker.checker.builtin.is_some()
+    }
+
+    fn accepts(&self, data: &SubmissionData) -> bool {
+        matches!(data, SubmissionData::Outputs(..))
+    }
+
+    fn make_request<'a>(
+        &'a self,
+        judge_uuid: Uuid,
+        task: &'a TaskLoader,
+        data: &'a SubmissionData,
+    ) -> BoxFuture<'a, io::Result<(Option<RequestJudge>, Submission)>> {
+        async move {
+            match data {
+                SubmissionData::Outputs(outputs) => {
+                    let mut state = make_output_state(judge_uuid, task, outputs).await?;
+                    state.score = self.score(&state);
+                    Ok((None, state))
+                }
+                _ => Err(io::Error::from(io::ErrorKind::InvalidInput)),
+            }
+        }
+        .boxed()
+    }
+}
+
+pub struct Communication;
+
+impl TaskTypeHandler for Communication {
+    fn validate(&self, task: &TaskLoader) -> bool {
+        task.graders.manager_file.is_some()
+            && !task.graders.graders.is_empty()
+            && has_checker_program(task)
+    }
+
+    fn accepts(&self, data: &SubmissionData) -> bool {
+        Batch.accepts(data)
+    }
+
+    fn make_request<'a>(
+        &'a self,
+        judge_uuid: Uuid,
+        task: &'a TaskLoader,
+        data: &'a SubmissionData,
+    ) -> BoxFuture<'a, io::Result<(Option<RequestJudge>, Submission)>> {
+        make_source_request(judge_uuid, task, data)
+    }
+}
+
+pub struct TwoSteps;
+
+impl TaskTypeHandler for TwoSteps {
+    fn validate(&self, task: &TaskLoader) -> bool {
+        !task.graders.graders.is_empty() && has_checker_program(task)
+    }
+
+    fn accepts(&self, data: &SubmissionData) -> bool {
+        Batch.accepts(data)
+    }
+
+    fn make_request<'a>(
+        &'a self,
+        judge_uuid: Uuid,
+        task: &'a TaskLoader,
+        data: &'a SubmissionData,
+    ) -> BoxFuture<'a, io::Result<(Option<RequestJudge>, Submission)>> {
+        make_source_request(judge_uuid, task, data)
+    }
+}
+
+lazy_static! {
+    static ref REGISTRY: RwLock<HashMap<String, Arc<dyn TaskTypeHandler>>> = {
+        let mut registry: HashMap<String, Arc<dyn TaskTypeHandler>> = HashMap::new();
+        registry.insert(BATCH.to_string(), Arc::new(Batch));
+        registry.insert(OUTPUT_ONLY.to_string(), Arc::new(OutputOnly));
+        registry.insert(COMMUNICATION.to_string(), Arc::new(Communication));
+        registry.insert(TWO_STEPS.to_string(), Arc::new(TwoSteps));
+        RwLock::new(registry)
+    };
+}
+
+// custom task types are referred in task.toml as `task_type = { Custom = "{name}" }`
+pub fn register_task_type(name: &str, handler: Arc<dyn TaskTypeHandler>) {
+    REGISTRY.write().unwrap().insert(name.to_string(), handler);
+}
+
+pub fn task_type_handler(name: &str) -> Option<Arc<dyn TaskTypeHandler>> {
+    REGISTRY.read().unwrap().get(name).cloned()
+}
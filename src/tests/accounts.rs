@@ -0,0 +1,343 @@
+use crate::api::accounts::api_tokens::*;
+use crate::api::accounts::otp::*;
+use crate::api::accounts::tokens::*;
+use crate::api::accounts::validation::*;
+use crate::api::accounts::{AccountError, AccountPerm};
+use crate::db::models::User;
+use chrono::prelude::*;
+use uuid::Uuid;
+
+fn user(id: &str, permission: AccountPerm) -> User {
+    User {
+        id: id.to_string(),
+        pass: String::new(),
+        permission: permission as i32,
+        timezone: String::from("Asia/Seoul"),
+        email: format!("{}@example.com", id),
+        first_name: String::new(),
+        last_name: String::new(),
+        preferred_language: Uuid::nil(),
+        pk: Uuid::new_v4(),
+        email_verified: true,
+        deleted_at: None,
+        is_banned: false,
+        totp_secret: None,
+        totp_enabled: false,
+        totp_last_step: None,
+    }
+}
+
+#[test]
+fn registration_validation_test() {
+    assert_eq!(validate_id("alice_01"), Ok(()));
+    assert_eq!(validate_id("ab"), Err(AccountError::InvalidId));
+    assert_eq!(validate_id("_alice"), Err(AccountError::InvalidId));
+    assert_eq!(validate_id("al ice"), Err(AccountError::InvalidId));
+    assert_eq!(validate_password("alice", "correct horse 1"), Ok(()));
+    assert_eq!(
+        validate_password("alice", "password"),
+        Err(AccountError::WeakPassword)
+    );
+    assert_eq!(
+        validate_password("alice12345", "ALICE12345"),
+        Err(AccountError::WeakPassword)
+    );
+    assert_eq!(validate_email("alice@example.com"), Ok(()));
+    assert_eq!(
+        validate_email("alice@localhost"),
+        Err(AccountError::InvalidEmail)
+    );
+    assert_eq!(
+        validate_email("alice@@example.com"),
+        Err(AccountError::InvalidEmail)
+    );
+    assert_eq!(
+        validate_email("al ice@example.com"),
+        Err(AccountError::InvalidEmail)
+    );
+}
+
+#[test]
+fn signed_token_test() {
+    let now = Utc::now();
+    let token = sign(
+        EMAIL_VERIFICATION,
+        "pk:alice@example.com",
+        now + chrono::Duration::hours(1),
+        "secret",
+    );
+    assert_eq!(
+        verify(&token, EMAIL_VERIFICATION, now, "secret"),
+        Some(String::from("pk:alice@example.com"))
+    );
+    assert_eq!(verify(&token, EMAIL_VERIFICATION, now, "other"), None);
+    assert_eq!(verify(&token, "reset-password", now, "secret"), None);
+    assert_eq!(
+        verify(
+            &token,
+            EMAIL_VERIFICATION,
+            now + chrono::Duration::hours(2),
+            "secret"
+        ),
+        None
+    );
+    let (payload, sig) = token.split_once('.').unwrap();
+    let forged = format!("{}00.{}", payload, sig);
+    assert_eq!(verify(&forged, EMAIL_VERIFICATION, now, "secret"), None);
+}
+
+#[test]
+fn suggest_id_test() {
+    assert_eq!(suggest_id(Some("alice.kim"), None), "alice.kim");
+    assert_eq!(suggest_id(None, Some("bob+oj@example.com")), "bob");
+    assert_eq!(
+        suggest_id(Some("__김철수__"), Some("x@example.com")),
+        "user"
+    );
+    assert_eq!(
+        suggest_id(Some("a-very-long-user-name"), None),
+        "a-very-long-user"
+    );
+    for id in [suggest_id(Some("_._carol"), None), suggest_id(None, None)] {
+        assert_eq!(validate_id(&id), Ok(()));
+    }
+}
+
+#[test]
+fn token_scope_test() {
+    let bits = TokenScope::to_bits(&[TokenScope::Read, TokenScope::Admin]);
+    assert_eq!(
+        TokenScope::from_bits(bits),
+        vec![TokenScope::Read, TokenScope::Admin]
+    );
+    assert_eq!(required_scope("{ info { id } }"), TokenScope::Read);
+    assert_eq!(
+        required_scope("mutation { submit(task: \"x\") { pk } }"),
+        TokenScope::Submit
+    );
+    assert_eq!(
+        required_scope("mutation { submit { pk } banUser(user: \"x\") { status } }"),
+        TokenScope::Admin
+    );
+    assert_eq!(
+        required_scope("mutation { ...f } fragment f on Mutation { submit { pk } }"),
+        TokenScope::Admin
+    );
+}
+
+#[test]
+fn totp_test() {
+    // test vectors of RFC 4226 and RFC 6238
+    let secret = b"12345678901234567890";
+    assert_eq!(hotp(secret, 0), 755224);
+    assert_eq!(hotp(secret, 1), 287082);
+    assert_eq!(verify_totp(secret, "287082", 59), Some(1));
+    assert_eq!(
+        verify_totp(secret, "081804", 1111111109),
+        Some(1111111109 / 30)
+    );
+    assert_eq!(verify_totp(secret, "081804", 1111111109 + 90), None);
+    assert_eq!(verify_totp(secret, "81804", 1111111109), None);
+    let encoded = base32_encode(secret);
+    assert_eq!(encoded, "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ");
+    assert_eq!(
+        base32_decode(&encoded.to_lowercase()).unwrap(),
+        secret.to_vec()
+    );
+    assert_eq!(
+        otpauth_uri("PMS", "alice kim", &encoded),
+        "otpauth://totp/PMS:alice%20kim?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&issuer=PMS&digits=6&period=30"
+    );
+}
+
+#[test]
+fn oidc_link_target_test() {
+    use crate::api::accounts::oidc::*;
+    use crate::config::OidcProvider;
+    use crate::db::models::ExternalIdentity;
+
+    let provider = OidcProvider {
+        name: String::from("mock"),
+        issuer: String::from("https://id.example.com"),
+        client_id: String::from("pms"),
+        client_secret: None,
+        redirect_url: String::from("https://pms.example.com/api/accounts/oidc/mock/callback"),
+        scopes: vec![],
+        auto_provision: false,
+        link_by_email: true,
+    };
+    let claims = ExternalClaims {
+        subject: String::from("1234"),
+        email: Some(String::from("Alice@example.com")),
+        email_verified: true,
+        ..Default::default()
+    };
+    let owner = user("alice", AccountPerm::User);
+    let viewer = Uuid::new_v4();
+    let linked = ExternalIdentity {
+        pk: Uuid::new_v4(),
+        user_pk: owner.pk,
+        provider: provider.name.clone(),
+        subject: claims.subject.clone(),
+        linked_at: Utc::now(),
+    };
+
+    // known identities go to their account, but are not moved to another one
+    assert_eq!(
+        link_target(&provider, &claims, None, Some(&linked), None),
+        Ok(LinkTarget::Linked(owner.pk))
+    );
+    assert_eq!(
+        link_target(&provider, &claims, Some(owner.pk), Some(&linked), None),
+        Ok(LinkTarget::Linked(owner.pk))
+    );
+    assert_eq!(
+        link_target(&provider, &claims, Some(viewer), Some(&linked), None),
+        Err(AccountError::IdentityAlreadyLinked)
+    );
+    // logged in users link to themselves
+    assert_eq!(
+        link_target(&provider, &claims, Some(viewer), None, Some(&owner)),
+        Ok(LinkTarget::LinkTo(viewer))
+    );
+    assert_eq!(
+        link_target(&provider, &claims, None, None, Some(&owner)),
+        Ok(LinkTarget::LinkTo(owner.pk))
+    );
+
+    // emails are matched only if both sides verified them and the provider is trusted
+    let unverified_owner = User {
+        email_verified: false,
+        ..owner.clone()
+    };
+    assert_eq!(
+        link_target(&provider, &claims, None, None, Some(&unverified_owner)),
+        Err(AccountError::IdentityNotLinked)
+    );
+    let unverified_claims = ExternalClaims {
+        email_verified: false,
+        ..claims.clone()
+    };
+    assert_eq!(
+        link_target(&provider, &unverified_claims, None, None, Some(&owner)),
+        Err(AccountError::IdentityNotLinked)
+    );
+    let untrusted = OidcProvider {
+        link_by_email: false,
+        auto_provision: true,
+        ..provider.clone()
+    };
+    assert_eq!(
+        link_target(&untrusted, &claims, None, None, Some(&owner)),
+        Ok(LinkTarget::Provision)
+    );
+}
+
+#[test]
+fn reset_token_test() {
+    use crate::api::accounts::password::*;
+    use std::collections::HashMap;
+
+    struct MemoryTokens(HashMap<String, Uuid>);
+
+    impl ResetTokens for MemoryTokens {
+        fn user_of(&mut self, token: &str) -> Result<Option<Uuid>, AccountError> {
+            Ok(self.0.get(token).copied())
+        }
+
+        fn take(&mut self, token: &str) -> Result<bool, AccountError> {
+            Ok(self.0.remove(token).is_some())
+        }
+    }
+
+    let user_pk = Uuid::new_v4();
+    let mut tokens = MemoryTokens(HashMap::from([(String::from("t"), user_pk)]));
+    let new_password =
+        |pass: &'static str| move |pk: Uuid| validate_password("alice", pass).map(|_| pk);
+    // a weak password does not use up the token
+    assert_eq!(
+        use_reset_token(&mut tokens, "t", new_password("password")),
+        Err(AccountError::WeakPassword)
+    );
+    assert_eq!(
+        use_reset_token(&mut tokens, "t", new_password("correct horse 1")),
+        Ok(user_pk)
+    );
+    // but a good one does
+    assert_eq!(
+        use_reset_token(&mut tokens, "t", |pk| Ok(pk)),
+        Err(AccountError::InvalidToken)
+    );
+    assert_eq!(
+        use_reset_token(&mut tokens, "unknown", |pk| Ok(pk)),
+        Err(AccountError::InvalidToken)
+    );
+}
+
+#[test]
+fn impersonation_test() {
+    use crate::api::accounts::admin::*;
+
+    let admin = user("admin", AccountPerm::Admin);
+    let other_admin = user("root", AccountPerm::Admin);
+    let alice = user("alice", AccountPerm::User);
+    assert_eq!(check_impersonation(admin.pk, &alice), Ok(()));
+    assert_eq!(
+        check_impersonation(admin.pk, &other_admin),
+        Err(AccountError::PermissionDenied)
+    );
+    assert_eq!(
+        check_impersonation(admin.pk, &admin),
+        Err(AccountError::PermissionDenied)
+    );
+
+    // actions of an impersonated session name the admin behind it
+    let log = audit_entry(alice.pk, Some(admin.pk), "ban", other_admin.pk, None);
+    assert_eq!(log.admin_pk, alice.pk);
+    assert_eq!(log.impersonator_pk, Some(admin.pk));
+    assert_eq!(log.target_pk, Some(other_admin.pk));
+    assert_eq!(log.action, "ban");
+}
+
+#[test]
+fn profile_changes_test() {
+    use crate::api::accounts::profile::*;
+
+    let alice = user("alice", AccountPerm::User);
+    assert!(profile_changes(&alice, ProfileUpdate::default())
+        .unwrap()
+        .is_none());
+    let same_email = ProfileUpdate {
+        email: Some(alice.email.clone()),
+        ..Default::default()
+    };
+    assert!(profile_changes(&alice, same_email).unwrap().is_none());
+
+    let form = ProfileUpdate {
+        first_name: Some(String::from("Alice")),
+        timezone: Some(chrono_tz::Europe::Berlin),
+        ..Default::default()
+    };
+    let changes = profile_changes(&alice, form).unwrap().unwrap();
+    assert_eq!(changes.first_name.as_deref(), Some("Alice"));
+    assert_eq!(changes.timezone.as_deref(), Some("Europe/Berlin"));
+    assert_eq!(changes.email, None);
+    assert_eq!(changes.email_verified, None);
+
+    let new_email = ProfileUpdate {
+        email: Some(String::from("alice@example.org")),
+        ..Default::default()
+    };
+    let changes = profile_changes(&alice, new_email).unwrap().unwrap();
+    assert_eq!(changes.email.as_deref(), Some("alice@example.org"));
+    assert_eq!(changes.email_verified, Some(false));
+
+    let bad_email = ProfileUpdate {
+        email: Some(String::from("alice")),
+        ..Default::default()
+    };
+    assert_eq!(
+        profile_changes(&alice, bad_email).err(),
+        Some(AccountError::InvalidEmail)
+    );
+}
@@ -0,0 +1,43 @@
+use crate::tasks::checker::*;
+
+#[test]
+fn builtin_checker_test() {
+    assert_eq!(
+        BuiltinChecker::Token.check("1 2\n3\n", "1  2 3"),
+        Verdict::accepted()
+    );
+    assert_eq!(
+        BuiltinChecker::Token.check("5", "7").message,
+        "expected 5, found 7 on token 1"
+    );
+    assert_eq!(BuiltinChecker::Exact.check("a b\n", "a b").score, 1.0);
+    assert_eq!(BuiltinChecker::Exact.check("a b\n", "a  b").score, 0.0);
+    assert_eq!(
+        BuiltinChecker::CaseInsensitive.check("YES", "yes").score,
+        1.0
+    );
+    assert_eq!(
+        BuiltinChecker::UnorderedLines.check("a\nb\n", "b\na").score,
+        1.0
+    );
+    let float = BuiltinChecker::Float {
+        abs: 1e-6,
+        rel: 1e-6,
+    };
+    assert_eq!(float.check("0.3333333", "0.33333331").score, 1.0);
+    assert_eq!(float.check("0.3333333", "0.3334").score, 0.0);
+}
+
+#[test]
+fn checker_toml_test() {
+    let checker: Checker =
+        toml::from_str("builtin = { Float = { abs = 1e-6, rel = 0.0 } }").unwrap();
+    assert_eq!(
+        checker.builtin,
+        Some(BuiltinChecker::Float {
+            abs: 1e-6,
+            rel: 0.0
+        })
+    );
+    assert_eq!(checker.checker_file, None);
+}
@@ -0,0 +1,125 @@
+use crate::api::judge::{contest_shows_tasks, contest_window_open};
+use crate::contests::Subscriber;
+use crate::db::models::{Clarification, Contest};
+use chrono::prelude::*;
+use chrono::Duration;
+use uuid::Uuid;
+
+fn contest(is_public: bool, participant_duration: Option<i32>) -> Contest {
+    let start_at = Utc.ymd(2022, 11, 1).and_hms(9, 0, 0);
+    Contest {
+        pk: Uuid::new_v4(),
+        name: String::from("contest"),
+        start_at,
+        end_at: start_at + Duration::hours(5),
+        is_public,
+        participant_duration,
+    }
+}
+
+#[test]
+fn contest_task_visibility_test() {
+    let private = contest(false, None);
+    let before = private.start_at - Duration::minutes(1);
+    let during = private.start_at + Duration::hours(1);
+    let after = private.end_at + Duration::hours(1);
+
+    // private contests show tasks only to those who may take part, and only once started
+    assert!(!contest_shows_tasks(&private, false, None, during));
+    assert!(!contest_shows_tasks(&private, false, None, after));
+    assert!(!contest_shows_tasks(&private, true, None, before));
+    assert!(contest_shows_tasks(&private, true, None, during));
+
+    // anonymous viewers are allowed only in public contests and never have a window
+    let windowed = contest(true, Some(60));
+    assert!(!contest_shows_tasks(&windowed, true, None, during));
+    assert!(contest_shows_tasks(&windowed, true, None, after));
+    let window = windowed.personal_window(during - Duration::minutes(10));
+    assert!(contest_shows_tasks(&windowed, true, Some(window), during));
+}
+
+#[test]
+fn clarification_subscriber_test() {
+    let (alice, bob) = (Uuid::new_v4(), Uuid::new_v4());
+    let question = Clarification {
+        pk: Uuid::new_v4(),
+        contest_pk: Uuid::new_v4(),
+        task_pk: None,
+        user_pk: alice,
+        question: Some(String::from("Is n positive?")),
+        answer: None,
+        answered_by: None,
+        is_public: false,
+        asked_at: Utc::now(),
+        answered_at: None,
+    };
+    let subscriber = |user_pk, is_jury| Subscriber { user_pk, is_jury };
+
+    assert!(subscriber(alice, false).can_see(&question));
+    assert!(!subscriber(bob, false).can_see(&question));
+    assert!(subscriber(bob, true).can_see(&question));
+    let announcement = Clarification {
+        is_public: true,
+        ..question
+    };
+    assert!(subscriber(bob, false).can_see(&announcement));
+}
+
+#[test]
+fn personal_window_test() {
+    let windowed = contest(false, Some(90));
+    let early = windowed.start_at + Duration::minutes(30);
+    assert_eq!(
+        windowed.personal_window(early),
+        (early, early + Duration::minutes(90))
+    );
+    // late starters are cut at the end of the contest
+    let late = windowed.end_at - Duration::minutes(30);
+    assert_eq!(windowed.personal_window(late), (late, windowed.end_at));
+    assert_eq!(windowed.duration(), Duration::minutes(90));
+
+    let fixed = contest(false, None);
+    assert_eq!(fixed.personal_window(early), (fixed.start_at, fixed.end_at));
+    assert_eq!(fixed.duration(), Duration::hours(5));
+}
+
+#[test]
+fn contest_window_test() {
+    let windowed = contest(false, Some(60));
+    let now = windowed.start_at + Duration::hours(2);
+    let open = windowed.personal_window(now - Duration::minutes(10));
+    let closed = windowed.personal_window(now - Duration::minutes(70));
+    let mut ended = contest(true, None);
+    ended.start_at = ended.start_at - Duration::days(7);
+    ended.end_at = ended.end_at - Duration::days(7);
+
+    assert!(contest_window_open(&[], now));
+    assert!(contest_window_open(&[(&ended, false, None)], now));
+    assert!(contest_window_open(&[(&windowed, true, Some(open))], now));
+    assert!(!contest_window_open(
+        &[(&windowed, true, Some(closed))],
+        now
+    ));
+    assert!(!contest_window_open(&[(&windowed, true, None)], now));
+    // an ended contest with the same task does not open it, whatever the order
+    assert!(!contest_window_open(
+        &[(&ended, false, None), (&windowed, true, None)],
+        now
+    ));
+    assert!(!contest_window_open(
+        &[(&windowed, true, None), (&ended, false, None)],
+        now
+    ));
+    // but a running contest without windows does
+    let running = contest(false, None);
+    assert!(contest_window_open(
+        &[(&windowed, true, None), (&running, true, None)],
+        now
+    ));
+    // as long as the user is admitted to it
+    assert!(!contest_window_open(&[(&running, false, None)], now));
+    assert!(!contest_window_open(
+        &[(&ended, false, None), (&running, false, None)],
+        now
+    ));
+}
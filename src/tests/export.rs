@@ -0,0 +1,110 @@
+use crate::contests::clics::*;
+use crate::contests::export::*;
+use crate::contests::standings::*;
+use chrono::prelude::*;
+use uuid::Uuid;
+
+#[test]
+fn export_test() {
+    assert_eq!(task_label(0), "A");
+    assert_eq!(task_label(25), "Z");
+    assert_eq!(task_label(26), "AA");
+    assert_eq!(reltime(3723), "1:02:03.000");
+
+    let start_at = Utc.ymd(2022, 10, 1).and_hms(9, 0, 0);
+    let task = ExportTask {
+        pk: Uuid::new_v4(),
+        label: task_label(0),
+        name: String::from("Example"),
+        max_score: 100.0,
+    };
+    let user = ExportUser {
+        pk: Uuid::new_v4(),
+        id: String::from("alice"),
+        name: String::from("Alice, A."),
+    };
+    let submission = ExportSubmission {
+        pk: Uuid::new_v4(),
+        user_pk: user.pk,
+        task_pk: task.pk,
+        language: String::from("C++17"),
+        issued_at: start_at + chrono::Duration::minutes(30),
+        elapsed: 1800,
+        score: 100.0,
+        judgement: String::from("AC"),
+        runs: vec![ExportRun {
+            judgement: String::from("AC"),
+            run_time: 0.25,
+        }],
+    };
+    let attempts = vec![Attempt {
+        user_pk: user.pk,
+        kind: ParticipationKind::Official,
+        task_pk: task.pk,
+        elapsed: submission.elapsed,
+        score: submission.score,
+        solved: true,
+    }];
+    let results = ContestResults {
+        contest: ExportContest {
+            pk: Uuid::new_v4(),
+            name: String::from("Contest"),
+            start_at,
+            end_at: start_at + chrono::Duration::hours(5),
+            duration: 5 * 3600,
+        },
+        standings: build_standings(&[task.pk], attempts, None),
+        tasks: vec![task],
+        users: vec![user],
+        submissions: vec![submission],
+    };
+
+    assert_eq!(
+        export(&results, ExportFormat::Csv),
+        "rank,id,name,A,total,solved,penalty\n1,alice,\"Alice, A.\",100,100,1,30\n"
+    );
+    let feed = event_feed(&results);
+    assert_eq!(feed[0]["data"]["duration"], "5:00:00.000");
+    assert!(feed
+        .iter()
+        .any(|x| x["type"] == "judgements" && x["data"]["judgement_type_id"] == "AC"));
+    assert!(feed
+        .iter()
+        .any(|x| x["type"] == "runs" && x["data"]["run_time"] == 0.25));
+    let cms: serde_json::Value =
+        serde_json::from_str(&export(&results, ExportFormat::Cms)).unwrap();
+    assert_eq!(
+        cms["tasks"][results.tasks[0].pk.to_string()]["short_name"],
+        "A"
+    );
+}
+
+#[test]
+fn clics_windowed_test() {
+    let start_at = Utc.ymd(2022, 10, 1).and_hms(9, 0, 0);
+    let windowed = ExportContest {
+        pk: Uuid::new_v4(),
+        name: String::from("Windowed"),
+        start_at,
+        end_at: start_at + chrono::Duration::hours(24),
+        duration: 2 * 3600,
+    };
+    let late = ExportSubmission {
+        pk: Uuid::new_v4(),
+        user_pk: Uuid::new_v4(),
+        task_pk: Uuid::new_v4(),
+        language: String::from("C++17"),
+        issued_at: start_at + chrono::Duration::hours(20),
+        elapsed: 1800,
+        score: 0.0,
+        judgement: String::from("WA"),
+        runs: vec![],
+    };
+    assert_eq!(contest(&windowed)["duration"], "24:00:00.000");
+    assert_eq!(contest(&windowed)["scoreboard_type"], "score");
+    assert_eq!(submission(&windowed, &late)["contest_time"], "20:00:00.000");
+    assert_eq!(
+        judgement(&windowed, &late)["end_contest_time"],
+        "20:00:00.000"
+    );
+}
@@ -13,3 +13,44 @@ fn loader_test() {
         debug!("{:?}", task);
     });
 }
+
+#[test]
+fn task_languages_test() {
+    block_on(async {
+        init();
+        let task = load_task(PathBuf::from("./assets/task_example"))
+            .await
+            .unwrap();
+        let stub_lang = "aea02f71-ab0d-470e-9d0d-3577ec870e29".parse().unwrap();
+        assert!(task.accepts_language(&stub_lang));
+        assert!(!task.accepts_language(&uuid::Uuid::nil()));
+    });
+}
+
+#[test]
+fn grader_stub_test() {
+    block_on(async {
+        init();
+        let task = load_task(PathBuf::from("./assets/task_example"))
+            .await
+            .unwrap();
+        let stub_lang = "aea02f71-ab0d-470e-9d0d-3577ec870e29".parse().unwrap();
+        assert!(task.graders.graders.contains_key(&stub_lang));
+        assert!(task.accepts_language(&stub_lang));
+        assert!(task.graders.manager_file.is_some());
+    });
+}
+
+#[test]
+fn samples_test() {
+    block_on(async {
+        init();
+        let task = load_task(PathBuf::from("./assets/task_example"))
+            .await
+            .unwrap();
+        let samples = task.samples().await.unwrap();
+        assert_eq!(samples.len(), 1);
+        assert_eq!(samples[0].name, "example");
+        assert!(task.statement("ko-KR").is_some());
+    });
+}
@@ -1,5 +1,25 @@
 #[cfg(test)]
+pub mod accounts;
+#[cfg(test)]
+pub mod checker;
+#[cfg(test)]
+pub mod contests;
+#[cfg(test)]
+pub mod export;
+#[cfg(test)]
 pub mod loader;
+#[cfg(test)]
+pub mod plagiarism;
+#[cfg(test)]
+pub mod rate_limit;
+#[cfg(test)]
+pub mod standings;
+#[cfg(test)]
+pub mod submissions;
+#[cfg(test)]
+pub mod subtasks;
+#[cfg(test)]
+pub mod types;
 
 fn init() {
     let _ = env_logger::builder()
@@ -0,0 +1,114 @@
+use crate::db::models::{Contest, PlagiarismCheck, Submission};
+use crate::plagiarism::winnowing::*;
+use crate::plagiarism::*;
+use chrono::prelude::*;
+use chrono::Duration;
+use uuid::Uuid;
+
+const ORIGINAL: &str = r#"#include <cstdio>
+int main() {
+    int n, sum = 0;
+    scanf("%d", &n);
+    for (int i = 1; i <= n; i++) {
+        sum += i * i;
+    }
+    printf("%d\n", sum);
+    return 0;
+}
+"#;
+
+const RENAMED: &str = r#"#include <cstdio>
+// totally my own code
+int main() {
+    int count, total = 0;
+    scanf("%d", &count);
+    for (int k = 1; k <= count; k++) {
+        total += k * k;
+    }
+    printf("%d\n", total);
+    return 0;
+}
+"#;
+
+#[test]
+fn winnowing_test() {
+    let a = fingerprints(&tokenize(ORIGINAL, CommentStyle::CLike));
+    let b = fingerprints(&tokenize(RENAMED, CommentStyle::CLike));
+    assert_eq!(similarity(&a, &b), 1.0);
+    // the comment only shifts the copied lines by one
+    assert_eq!(
+        matches(&a, &b),
+        vec![Match {
+            left_start: 1,
+            left_end: 9,
+            right_start: 1,
+            right_end: 10,
+        }]
+    );
+    let other = fingerprints(&tokenize("a = input()\nprint(a)", CommentStyle::Hash));
+    assert!(similarity(&a, &other) < 0.3);
+}
+
+#[test]
+fn similar_pairs_test() {
+    let (cpp, python) = (Uuid::new_v4(), Uuid::new_v4());
+    let submission = |user_pk, lang_uuid| Submission {
+        pk: Uuid::new_v4(),
+        user_pk,
+        task_pk: Uuid::nil(),
+        lang_uuid,
+        issued_at: Utc.ymd(2022, 11, 1).and_hms(9, 0, 0),
+    };
+    let (alice, bob) = (Uuid::new_v4(), Uuid::new_v4());
+    let original = fingerprints(&tokenize(ORIGINAL, CommentStyle::CLike));
+    let renamed = fingerprints(&tokenize(RENAMED, CommentStyle::CLike));
+    let items = vec![
+        (submission(alice, cpp), original.clone()),
+        (submission(bob, cpp), renamed.clone()),
+        // resubmitted by the same user
+        (submission(alice, cpp), renamed.clone()),
+        // the same tokens in another language
+        (submission(bob, python), original),
+    ];
+    let pairs = similar_pairs(&items);
+    assert_eq!(pairs.len(), 2);
+    for (a, b, score) in pairs {
+        assert!(a < b);
+        assert_eq!(score, 1.0);
+        assert!([a, b].contains(&items[1].0.pk));
+    }
+}
+
+#[test]
+fn due_contests_test() {
+    let start_at = Utc.ymd(2022, 11, 1).and_hms(9, 0, 0);
+    let contest = |end_at| Contest {
+        pk: Uuid::new_v4(),
+        name: String::from("contest"),
+        start_at,
+        end_at,
+        is_public: true,
+        participant_duration: None,
+    };
+    let now = start_at + Duration::days(1);
+    let running = contest(now + Duration::hours(1));
+    let unchecked = contest(now - Duration::hours(1));
+    let checked = contest(now - Duration::hours(2));
+    let extended = contest(now - Duration::minutes(10));
+    let checks = vec![
+        PlagiarismCheck {
+            contest_pk: checked.pk,
+            checked_at: checked.end_at + Duration::minutes(5),
+        },
+        // checked before its end was moved later
+        PlagiarismCheck {
+            contest_pk: extended.pk,
+            checked_at: now - Duration::hours(1),
+        },
+    ];
+    let contests = vec![running, unchecked.clone(), checked, extended.clone()];
+    assert_eq!(
+        due_contests(&contests, &checks, now),
+        vec![unchecked.pk, extended.pk]
+    );
+}
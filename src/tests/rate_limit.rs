@@ -0,0 +1,97 @@
+use crate::middlewares::rate_limit::*;
+use actix_web::http::header::RETRY_AFTER;
+use actix_web::http::StatusCode;
+use actix_web::{test, web, App, HttpResponse};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+const LIMIT: RateLimit = RateLimit {
+    name: "test",
+    limit: 2,
+    window_secs: 60,
+};
+
+thread_local! {
+    static HITS: RefCell<HashMap<String, i64>> = RefCell::new(HashMap::new());
+}
+
+// the window of these hits never passes
+fn memory_hit(limit: &RateLimit, subject: &str) -> Option<usize> {
+    HITS.with(|hits| {
+        let mut hits = hits.borrow_mut();
+        let count = hits.entry(limit.key(subject)).or_insert(0);
+        *count += 1;
+        limit.retry_after(*count, 42)
+    })
+}
+
+#[test]
+fn rate_limit_window_test() {
+    assert_eq!(LIMIT.key("ip:10.0.0.1"), "rate_limit:test:ip:10.0.0.1");
+    assert_ne!(LIMIT.key("user:a"), LIMIT.key("user:b"));
+    assert_eq!(LIMIT.retry_after(1, 60), None);
+    assert_eq!(LIMIT.retry_after(2, 60), None);
+    assert_eq!(LIMIT.retry_after(3, 17), Some(17));
+    // a key whose window just ended or has no expiry still makes the client wait a bit
+    assert_eq!(LIMIT.retry_after(3, 0), Some(1));
+    assert_eq!(LIMIT.retry_after(3, -1), Some(1));
+}
+
+#[test]
+fn forwarded_client_test() {
+    let ip = |x: &str| x.parse::<IpAddr>().unwrap();
+    let proxies = [ip("10.0.0.1"), ip("10.0.0.2")];
+
+    // forwarded addresses are ignored unless a trusted proxy sent the request
+    assert_eq!(
+        forwarded_client(ip("203.0.113.9"), Some("198.51.100.7"), &proxies),
+        ip("203.0.113.9")
+    );
+    assert_eq!(
+        forwarded_client(ip("10.0.0.1"), Some("198.51.100.7"), &proxies),
+        ip("198.51.100.7")
+    );
+    assert_eq!(
+        forwarded_client(ip("10.0.0.1"), None, &proxies),
+        ip("10.0.0.1")
+    );
+    // whatever the client put left of the address its proxy saw is not believed
+    assert_eq!(
+        forwarded_client(
+            ip("10.0.0.1"),
+            Some("1.2.3.4, 198.51.100.7, 10.0.0.2"),
+            &proxies
+        ),
+        ip("198.51.100.7")
+    );
+    assert_eq!(
+        forwarded_client(ip("10.0.0.1"), Some("garbage, 198.51.100.7:4711"), &proxies),
+        ip("198.51.100.7")
+    );
+    assert_eq!(
+        forwarded_client(ip("10.0.0.1"), Some("198.51.100.7, garbage"), &proxies),
+        ip("10.0.0.1")
+    );
+}
+
+#[actix_web::test]
+async fn rate_limiter_test() {
+    let app = test::init_service(
+        App::new()
+            .wrap(RateLimiter::with_counter(
+                LIMIT,
+                LimitBy::ClientAddr,
+                memory_hit,
+            ))
+            .route("/", web::get().to(|| async { HttpResponse::Ok().finish() })),
+    )
+    .await;
+    for _ in 0..LIMIT.limit {
+        let res = test::call_service(&app, test::TestRequest::get().to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+    let res = test::call_service(&app, test::TestRequest::get().to_request()).await;
+    assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(res.headers().get(RETRY_AFTER).unwrap(), "42");
+}
@@ -0,0 +1,42 @@
+use crate::contests::standings::*;
+use uuid::Uuid;
+
+#[test]
+fn standings_test() {
+    let tasks = vec![Uuid::new_v4(), Uuid::new_v4()];
+    let (alice, bob) = (Uuid::new_v4(), Uuid::new_v4());
+    let attempt = |user_pk, kind, task: usize, elapsed, score| Attempt {
+        user_pk,
+        kind,
+        task_pk: tasks[task],
+        elapsed,
+        score,
+        solved: score >= 100.0,
+    };
+    let attempts = vec![
+        attempt(alice, ParticipationKind::Official, 0, 600, 30.0),
+        attempt(alice, ParticipationKind::Official, 0, 1200, 100.0),
+        attempt(alice, ParticipationKind::Official, 1, 3000, 100.0),
+        attempt(bob, ParticipationKind::Virtual, 0, 300, 100.0),
+        attempt(bob, ParticipationKind::Virtual, 1, 1800, 100.0),
+        attempt(bob, ParticipationKind::Upsolving, 1, 60, 100.0),
+    ];
+
+    let rows = build_standings(&tasks, attempts.clone(), None);
+    assert_eq!(rows.len(), 3);
+    assert_eq!(
+        (rows[0].user_pk, rows[0].kind),
+        (bob, ParticipationKind::Virtual)
+    );
+    assert_eq!((rows[0].total, rows[0].penalty), (200.0, 35));
+    assert_eq!(rows[1].user_pk, alice);
+    assert_eq!(rows[1].tasks[0].attempts, 2);
+    assert_eq!(rows[1].penalty, 20 + 20 + 50);
+    assert_eq!(rows[2].kind, ParticipationKind::Upsolving);
+    assert_eq!(rows[2].rank, 3);
+
+    // frozen at 20 minutes of elapsed time
+    let rows = build_standings(&tasks, attempts, Some(1200));
+    let alice_row = rows.iter().find(|x| x.user_pk == alice).unwrap();
+    assert_eq!((alice_row.total, alice_row.solved), (30.0, 0));
+}
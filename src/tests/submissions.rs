@@ -0,0 +1,112 @@
+use crate::api::graphql::submissions::SubmissionCursor;
+use crate::api::judge::scan_submissions;
+use crate::db::judge::SubmissionQuery;
+use crate::db::models::Submission;
+use async_graphql::connection::CursorType;
+use chrono::prelude::*;
+use uuid::Uuid;
+
+fn key(x: &Submission) -> (DateTime<Utc>, Uuid) {
+    (x.issued_at, x.pk)
+}
+
+// what search_submissions does in SQL
+fn fetch(all: &[Submission], query: SubmissionQuery) -> Vec<Submission> {
+    let mut items: Vec<Submission> = all
+        .iter()
+        .filter(|x| query.older_than.map(|bound| key(x) < bound).unwrap_or(true))
+        .filter(|x| query.newer_than.map(|bound| key(x) > bound).unwrap_or(true))
+        .cloned()
+        .collect();
+    items.sort_by_key(key);
+    if !query.oldest_first {
+        items.reverse();
+    }
+    items.truncate(query.limit.map(|x| x as usize).unwrap_or(items.len()));
+    items
+}
+
+#[test]
+fn submission_cursor_test() {
+    let cursor = SubmissionCursor {
+        issued_at: Utc.ymd(2022, 10, 1).and_hms_micro(9, 0, 0, 123456),
+        pk: Uuid::new_v4(),
+    };
+    assert_eq!(
+        SubmissionCursor::decode_cursor(&cursor.encode_cursor()),
+        Ok(cursor)
+    );
+    assert!(SubmissionCursor::decode_cursor("garbage").is_err());
+    assert!(SubmissionCursor::decode_cursor("2022-10-01T09:00:00Z/garbage").is_err());
+}
+
+#[actix_web::test]
+async fn scan_submissions_test() {
+    let (shown, hidden) = (Uuid::new_v4(), Uuid::new_v4());
+    let start_at = Utc.ymd(2022, 10, 1).and_hms(9, 0, 0);
+    // three submissions a second, so that ties of issued_at are broken by pk
+    let all: Vec<Submission> = (0..600)
+        .map(|idx| Submission {
+            pk: Uuid::new_v4(),
+            user_pk: Uuid::new_v4(),
+            task_pk: if idx % 4 == 0 { shown } else { hidden },
+            lang_uuid: Uuid::nil(),
+            issued_at: start_at + chrono::Duration::seconds(idx / 3),
+        })
+        .collect();
+    let mut expected: Vec<Submission> =
+        all.iter().filter(|x| x.task_pk == shown).cloned().collect();
+    expected.sort_by_key(key);
+    expected.reverse();
+    let keep = |rows: Vec<Submission>| async move {
+        rows.into_iter()
+            .filter(|x| x.task_pk == shown)
+            .map(|x| x.pk)
+            .collect::<Vec<Uuid>>()
+    };
+
+    let mut pages = vec![];
+    let mut query = SubmissionQuery::default();
+    loop {
+        let (page, more) = scan_submissions(query.clone(), Some(40), |q| Ok(fetch(&all, q)), keep)
+            .await
+            .unwrap();
+        assert_eq!(page.len(), if more { 40 } else { 150 % 40 });
+        let last = all.iter().find(|x| x.pk == *page.last().unwrap()).unwrap();
+        query.older_than = Some(key(last));
+        pages.extend(page);
+        if !more {
+            break;
+        }
+    }
+    assert_eq!(pages, expected.iter().map(|x| x.pk).collect::<Vec<Uuid>>());
+
+    let backward = SubmissionQuery {
+        oldest_first: true,
+        ..Default::default()
+    };
+    let (oldest, more) = scan_submissions(backward, Some(5), |q| Ok(fetch(&all, q)), keep)
+        .await
+        .unwrap();
+    assert!(more);
+    assert_eq!(
+        oldest,
+        expected
+            .iter()
+            .rev()
+            .take(5)
+            .map(|x| x.pk)
+            .collect::<Vec<Uuid>>()
+    );
+
+    let (everything, more) = scan_submissions(
+        SubmissionQuery::default(),
+        None,
+        |q| Ok(fetch(&all, q)),
+        keep,
+    )
+    .await
+    .unwrap();
+    assert!(!more);
+    assert_eq!(everything.len(), 150);
+}
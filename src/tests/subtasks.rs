@@ -0,0 +1,237 @@
+use crate::judge::api::*;
+use crate::judge::request::fill_previews;
+use crate::judge::Test;
+use crate::tasks::loader::SubtaskLoader;
+use crate::tasks::subtasks::*;
+use crate::tasks::{Feedback, Task};
+use async_std::path::PathBuf;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+#[test]
+fn subtask_glob_test() {
+    assert!(glob_match("3-*", "3-01"));
+    assert!(glob_match("*-0?", "12-05"));
+    assert!(glob_match("*", ""));
+    assert!(!glob_match("3-*", "13-01"));
+    assert!(!glob_match("3-?", "3-10"));
+
+    let mut subtask: Subtask = toml::from_str(
+        r#"
+        name = "3-full"
+        score = 50.0
+        testcases = ["1-*", "3-*", "1-02"]
+        dependencies = ["1-small"]
+        "#,
+    )
+    .unwrap();
+    let available: Vec<String> = ["3-02", "1-01", "3-01", "1-02", "2-01"]
+        .iter()
+        .map(|x| x.to_string())
+        .collect();
+    assert_eq!(subtask.expand_testcases(&available), Ok(()));
+    assert_eq!(subtask.testcases, vec!["1-01", "1-02", "3-01", "3-02"]);
+    assert_eq!(subtask.dependencies, vec!["1-small"]);
+
+    // a pattern matching nothing is a mistake of the package
+    subtask.testcases = vec![String::from("1-01"), String::from("4-*")];
+    assert_eq!(
+        subtask.expand_testcases(&available),
+        Err(String::from("4-*"))
+    );
+}
+
+fn subtask(name: &str, testcases: &[&str], dependencies: &[&str]) -> Subtask {
+    Subtask {
+        name: name.to_string(),
+        score: 50.0,
+        testcases: testcases.iter().map(|x| x.to_string()).collect(),
+        dependencies: dependencies.iter().map(|x| x.to_string()).collect(),
+        sample: false,
+        samples: vec![],
+        time_limit: None,
+        memory_limit: None,
+        limits: HashMap::new(),
+    }
+}
+
+#[test]
+fn shared_tests_test() {
+    let test = |name: &str| Test {
+        stdin: PathBuf::from(format!("tests/{}.in", name)),
+        stdout: PathBuf::from(format!("tests/{}.out", name)),
+    };
+    let loader = SubtaskLoader {
+        subtasks: [
+            subtask("1-small", &["1-01", "1-02"], &[]),
+            subtask("2-full", &["1-02", "2-01"], &["1-small"]),
+        ]
+        .into_iter()
+        .map(|x| {
+            let tests = x.testcases.iter().map(|name| test(name.as_str())).collect();
+            (x.name.clone(), (x, tests))
+        })
+        .collect(),
+    };
+    let shared = loader.shared_tests();
+    let names: Vec<&String> = shared.iter().map(|x| x.name).collect();
+    assert_eq!(names, vec!["1-01", "1-02", "2-01"]);
+    let subtasks: Vec<Vec<&String>> = shared
+        .iter()
+        .map(|x| x.subtasks.iter().map(|subtask| &subtask.name).collect())
+        .collect();
+    assert_eq!(
+        subtasks,
+        vec![vec!["1-small"], vec!["1-small", "2-full"], vec!["2-full"]]
+    );
+    assert!(loader.has_valid_dependencies());
+}
+
+fn test_case(subtasks: &[&str], score: Option<f64>) -> TestCase {
+    TestCase {
+        test_uuid: Uuid::new_v4(),
+        subtasks: subtasks.iter().map(|x| x.to_string()).collect(),
+        name: String::new(),
+        status: TestState::Success,
+        score,
+        exit_code: None,
+        exit_sig: None,
+        time: None,
+        mem: None,
+        message: None,
+        sample: false,
+        revealed: false,
+        input_preview: None,
+        output_preview: None,
+    }
+}
+
+#[test]
+fn subtask_results_test() {
+    let case = |subtasks: &[&str], score: f64| test_case(subtasks, Some(score));
+    let state = |max_score, dependencies: &[&str]| SubtaskState {
+        max_score,
+        dependencies: dependencies.iter().map(|x| x.to_string()).collect(),
+    };
+    let submission = |cases: Vec<TestCase>| Submission {
+        judge_uuid: Uuid::new_v4(),
+        submission_state: SubmissionState::Success,
+        number_of_cases: cases.len(),
+        number_of_done: cases.len(),
+        compile_output: String::new(),
+        score: 0.0,
+        cases: cases.into_iter().map(|x| (x.test_uuid, x)).collect(),
+        task_type: String::new(),
+        subtasks: HashMap::from([
+            (String::from("1-small"), state(30.0, &[])),
+            (String::from("2-medium"), state(30.0, &["1-small"])),
+            (String::from("3-full"), state(40.0, &["2-medium"])),
+            (String::from("4-empty"), state(10.0, &[])),
+        ]),
+    };
+    let scores = |submission: Submission| -> Vec<f64> {
+        submission
+            .subtask_results()
+            .into_iter()
+            .map(|x| x.score)
+            .collect()
+    };
+
+    let all_passed = submission(vec![
+        case(&["1-small"], 1.0),
+        case(&["1-small", "2-medium"], 1.0),
+        case(&["3-full"], 1.0),
+    ]);
+    // a subtask without tests scores nothing
+    assert_eq!(scores(all_passed), vec![30.0, 30.0, 40.0, 0.0]);
+
+    // failing the smallest subtask zeroes everything depending on it, transitively
+    let small_failed = submission(vec![
+        case(&["1-small"], 0.5),
+        case(&["1-small", "2-medium"], 1.0),
+        case(&["3-full"], 1.0),
+    ]);
+    assert_eq!(scores(small_failed), vec![15.0, 0.0, 0.0, 0.0]);
+}
+
+#[test]
+fn feedback_test() {
+    let feedback: Feedback = toml::from_str(
+        r#"
+        subtasks = ["1-small"]
+        preview_length = 4
+        "#,
+    )
+    .unwrap();
+    assert!(feedback.reveals("1-small"));
+    assert!(!feedback.reveals("2-full"));
+    assert_eq!(feedback.preview(b"1 2"), "1 2");
+    assert_eq!(feedback.preview(b"1 2 3"), "1 2 ...");
+    assert_eq!(feedback.preview("가나다라마".as_bytes()), "가나다라...");
+
+    let mut hidden = test_case(&["2-full"], None);
+    fill_previews(&mut hidden, &feedback, b"3\n", b"6\n");
+    assert_eq!((hidden.input_preview, hidden.output_preview), (None, None));
+    let mut revealed = TestCase {
+        revealed: true,
+        ..test_case(&["1-small"], None)
+    };
+    fill_previews(&mut revealed, &feedback, b"1 2\n", b"3\n");
+    assert_eq!(revealed.input_preview.as_deref(), Some("1 2\n"));
+    assert_eq!(revealed.output_preview.as_deref(), Some("3\n"));
+}
+
+#[test]
+fn limits_test() {
+    let mut subtask = subtask("2-large", &["2-01", "2-02"], &[]);
+    subtask.time_limit = Some(2.0);
+    subtask.limits.insert(
+        String::from("2-02"),
+        Limits {
+            time_limit: Some(3.0),
+            memory_limit: Some(65536),
+        },
+    );
+    let limits = subtask.limits("2-01");
+    assert_eq!((limits.time_limit, limits.memory_limit), (Some(2.0), None));
+    let limits = subtask.limits("2-02");
+    assert_eq!(
+        (limits.time_limit, limits.memory_limit),
+        (Some(3.0), Some(65536))
+    );
+
+    let slow = Uuid::new_v4();
+    let task: Task = toml::from_str(&format!(
+        r#"
+        name = "Task"
+        code = "example"
+        task_type = "Batch"
+        time_limit = 1.0
+        memory_limit = 262144
+
+        [task_type_params]
+
+        [time_multipliers]
+        "{}" = 2.5
+        "#,
+        slow
+    ))
+    .unwrap();
+    assert_eq!(
+        task.effective_limits(Limits::default(), None),
+        (1000, 262144)
+    );
+    assert_eq!(
+        task.effective_limits(subtask.limits("2-01"), None),
+        (2000, 262144)
+    );
+    assert_eq!(
+        task.effective_limits(subtask.limits("2-02"), Some(slow)),
+        (7500, 65536)
+    );
+    // languages without a multiplier get the plain limits
+    assert_eq!(
+        task.effective_limits(subtask.limits("2-02"), Some(Uuid::new_v4())),
+        (3000, 65536)
+    );
+}
@@ -0,0 +1,18 @@
+use crate::tasks::types::*;
+use crate::tasks::*;
+use async_std::sync::Arc;
+
+#[test]
+fn task_type_registry_test() {
+    let task_type: TaskTypes =
+        toml::from_str::<toml::Value>("task_type = { Custom = \"Reactive\" }").unwrap()
+            ["task_type"]
+            .clone()
+            .try_into()
+            .unwrap();
+    assert_eq!(task_type.name(), "Reactive");
+    assert!(task_type_handler(&task_type.name()).is_none());
+    register_task_type("Reactive", Arc::new(Batch));
+    assert!(task_type_handler(&task_type.name()).is_some());
+    assert!(task_type_handler(&TaskTypes::Communication.name()).is_some());
+}